@@ -0,0 +1,432 @@
+//! Configuration files.
+//!
+//! A [`DisplayConfig`] describes one installation: how the controller is
+//! wired, the VCOM printed on the panel and how it is mounted. It is read
+//! from TOML, and every field is optional, falling back to the defaults of
+//! [`IT8951Builder`].
+//!
+//! ```toml
+//! vcom = "-1.53V"
+//! rotation = "rotate90"
+//! panel = "10.3"
+//!
+//! [spi]
+//! path = "/dev/spidev0.0"
+//! data_speed_hz = 16000000
+//!
+//! [gpio]
+//! hrdy = 24
+//! reset = 17
+//!
+//! [timeouts]
+//! ready_ms = 5000
+//! init_ms = 3000
+//!
+//! [refresh]
+//! mode = "gc16"
+//! full_refresh_every = 10
+//! ```
+//!
+//! A panel that is not built in is described in a `custom_panel` table
+//! instead, with the fields of [`PanelProfile`]:
+//!
+//! ```toml
+//! [custom_panel]
+//! name = "5.2"
+//! width = 1280
+//! height = 720
+//! vcom_range = [1200, 2200]
+//! mode_count = 8
+//! ```
+//!
+//! Requires the `config` feature.
+
+use crate::device::{IT8951Builder, DEFAULT_INIT_TIMEOUT};
+use crate::error::{Error, Result};
+use crate::hal::linux::{pins, speed};
+use crate::panel::PanelProfile;
+use crate::protocol::transport::DEFAULT_TIMEOUT_MS;
+use crate::types::{DisplayMode, Rotation, Vcom};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+
+/// Configuration of one display installation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    /// VCOM as printed on the panel, e.g. `"-1.53V"`, or its magnitude in
+    /// millivolts
+    pub vcom: Vcom,
+
+    /// Rotation of the logical view
+    pub rotation: Rotation,
+
+    /// Name of a built-in panel profile (see [`PanelProfile::by_name`]);
+    /// detected from the device information when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub panel: Option<String>,
+
+    /// Profile of a panel that is not built in, used instead of `panel`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_panel: Option<PanelProfile>,
+
+    /// SPI bus settings
+    pub spi: SpiConfig,
+
+    /// GPIO line settings
+    pub gpio: GpioConfig,
+
+    /// Timeouts
+    pub timeouts: TimeoutConfig,
+
+    /// Refresh policy
+    pub refresh: RefreshConfig,
+}
+
+/// SPI bus settings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpiConfig {
+    /// SPI device path
+    pub path: String,
+
+    /// Clock for commands and register access
+    pub command_speed_hz: u32,
+
+    /// Clock for bulk data transfers, e.g. from
+    /// [`IT8951::calibrate_spi_speed`](crate::IT8951::calibrate_spi_speed)
+    pub data_speed_hz: u32,
+}
+
+impl Default for SpiConfig {
+    fn default() -> Self {
+        Self {
+            path: "/dev/spidev0.0".to_string(),
+            command_speed_hz: speed::COMMAND_HZ,
+            data_speed_hz: speed::DATA_HZ,
+        }
+    }
+}
+
+/// GPIO line settings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GpioConfig {
+    /// GPIO character device
+    pub chip: String,
+
+    /// HRDY (host ready) input line
+    pub hrdy: u32,
+
+    /// Reset output line
+    pub reset: u32,
+}
+
+impl Default for GpioConfig {
+    fn default() -> Self {
+        Self {
+            chip: "/dev/gpiochip0".to_string(),
+            hrdy: pins::HRDY,
+            reset: pins::RST,
+        }
+    }
+}
+
+/// Timeouts, in milliseconds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimeoutConfig {
+    /// Maximum wait for the controller to become ready
+    pub ready_ms: u64,
+
+    /// Maximum wait for the controller to boot after a reset
+    pub init_ms: u64,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self {
+            ready_ms: DEFAULT_TIMEOUT_MS,
+            init_ms: DEFAULT_INIT_TIMEOUT.as_millis() as u64,
+        }
+    }
+}
+
+/// Refresh policy for applications driving the display.
+///
+/// The driver does not refresh on its own; these settings are carried in
+/// the configuration so applications can share them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RefreshConfig {
+    /// Mode for regular updates
+    pub mode: DisplayMode,
+
+    /// Number of regular updates between full `Init` refreshes that clear
+    /// ghosting; 0 never forces one
+    pub full_refresh_every: u32,
+}
+
+impl Default for RefreshConfig {
+    fn default() -> Self {
+        Self {
+            mode: DisplayMode::Gc16,
+            full_refresh_every: 0,
+        }
+    }
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            vcom: Vcom(1500),
+            rotation: Rotation::Rotate0,
+            panel: None,
+            custom_panel: None,
+            spi: SpiConfig::default(),
+            gpio: GpioConfig::default(),
+            timeouts: TimeoutConfig::default(),
+            refresh: RefreshConfig::default(),
+        }
+    }
+}
+
+impl DisplayConfig {
+    /// Parses a configuration from TOML.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use it8951::DisplayConfig;
+    ///
+    /// let config = DisplayConfig::from_toml("vcom = 1530").unwrap();
+    /// assert_eq!(config.vcom.millivolts(), 1530);
+    /// ```
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let config: Self = toml::from_str(text).map_err(|e| Error::Config(e.to_string()))?;
+        config.panel_profile()?;
+        Ok(config)
+    }
+
+    /// Looks up the configured panel profile.
+    ///
+    /// Returns `Error::Config` if the name matches no built-in profile, if
+    /// both `panel` and `custom_panel` are set, or if the custom panel has
+    /// no size.
+    pub fn panel_profile(&self) -> Result<Option<PanelProfile>> {
+        match (&self.panel, &self.custom_panel) {
+            (Some(_), Some(_)) => Err(Error::Config(
+                "panel and custom_panel are mutually exclusive".to_string(),
+            )),
+            (Some(name), None) => PanelProfile::by_name(name)
+                .map(Some)
+                .ok_or_else(|| Error::Config(format!("unknown panel \"{}\"", name))),
+            (None, Some(custom)) if custom.width == 0 || custom.height == 0 => Err(
+                Error::Config("custom_panel needs a width and height".to_string()),
+            ),
+            (None, custom) => Ok(custom.clone()),
+        }
+    }
+
+    /// Reads a configuration from a TOML file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        Self::from_toml(&text).map_err(|e| match e {
+            Error::Config(msg) => Error::Config(format!("{}: {}", path.display(), msg)),
+            e => e,
+        })
+    }
+
+    /// Serializes the configuration to TOML.
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string(self).map_err(|e| Error::Config(e.to_string()))
+    }
+
+    /// Writes the configuration to a TOML file, e.g. to persist a
+    /// calibrated SPI speed.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::write(path, self.to_toml()?)?;
+        Ok(())
+    }
+
+    /// Creates a builder with these settings.
+    ///
+    /// An unknown panel name is ignored here; it is rejected when the
+    /// configuration is parsed.
+    pub fn builder(&self) -> IT8951Builder {
+        let builder = match self.panel_profile() {
+            Ok(Some(panel)) => IT8951Builder::new().panel(panel),
+            _ => IT8951Builder::new(),
+        };
+        builder
+            .vcom_voltage(self.vcom)
+            .rotation(self.rotation)
+            .spi_path(self.spi.path.clone())
+            .command_speed_hz(self.spi.command_speed_hz)
+            .data_speed_hz(self.spi.data_speed_hz)
+            .gpio_chip(self.gpio.chip.clone())
+            .hrdy_pin(self.gpio.hrdy)
+            .reset_pin(self.gpio.reset)
+            .ready_timeout(Duration::from_millis(self.timeouts.ready_ms))
+            .init_timeout(Duration::from_millis(self.timeouts.init_ms))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Area, PixelFormat};
+
+    #[test]
+    fn test_parse_full_config() {
+        let config = DisplayConfig::from_toml(
+            r#"
+            vcom = "-1.53V"
+            rotation = "rotate90"
+            panel = "10.3"
+
+            [spi]
+            path = "/dev/spidev1.0"
+            command_speed_hz = 2000000
+            data_speed_hz = 16000000
+
+            [gpio]
+            chip = "/dev/gpiochip4"
+            hrdy = 5
+            reset = 6
+
+            [timeouts]
+            ready_ms = 8000
+            init_ms = 2500
+
+            [refresh]
+            mode = "du"
+            full_refresh_every = 10
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.vcom.millivolts(), 1530);
+        assert_eq!(config.rotation, Rotation::Rotate90);
+        assert_eq!(config.panel.as_deref(), Some("10.3"));
+        assert_eq!(config.spi.path, "/dev/spidev1.0");
+        assert_eq!(config.spi.data_speed_hz, 16_000_000);
+        assert_eq!(config.gpio.hrdy, 5);
+        assert_eq!(config.timeouts.ready_ms, 8000);
+        assert_eq!(config.timeouts.init_ms, 2500);
+        assert_eq!(config.refresh.mode, DisplayMode::Du);
+        assert_eq!(config.refresh.full_refresh_every, 10);
+    }
+
+    #[test]
+    fn test_missing_fields_use_defaults() {
+        let config = DisplayConfig::from_toml("[spi]\ndata_speed_hz = 8000000").unwrap();
+        assert_eq!(config.vcom.millivolts(), 1500);
+        assert_eq!(DisplayConfig::from_toml("vcom = 1530").unwrap().vcom.millivolts(), 1530);
+        assert!(matches!(
+            DisplayConfig::from_toml("vcom = 6000"),
+            Err(Error::Config(_))
+        ));
+        assert_eq!(config.spi.path, "/dev/spidev0.0");
+        assert_eq!(config.spi.data_speed_hz, 8_000_000);
+        assert_eq!(config.gpio, GpioConfig::default());
+    }
+
+    #[test]
+    fn test_rejects_unknown_fields() {
+        assert!(matches!(
+            DisplayConfig::from_toml("vcomm = 1530"),
+            Err(Error::Config(_))
+        ));
+        assert!(matches!(
+            DisplayConfig::from_toml("panel = \"42\""),
+            Err(Error::Config(_))
+        ));
+        assert!(matches!(
+            DisplayConfig::from_toml("rotation = \"sideways\""),
+            Err(Error::Config(_))
+        ));
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let config = DisplayConfig {
+            vcom: Vcom(2100),
+            rotation: Rotation::Rotate180,
+            ..Default::default()
+        };
+        let text = config.to_toml().unwrap();
+        assert_eq!(DisplayConfig::from_toml(&text).unwrap(), config);
+    }
+
+    #[test]
+    fn test_builder_from_config_file() {
+        let path = std::env::temp_dir().join(format!("it8951-config-{}.toml", std::process::id()));
+        std::fs::write(&path, "vcom = 1530\nrotation = \"rotate270\"\npanel = \"9.7\"").unwrap();
+        let builder = IT8951Builder::from_config(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let device = builder.unwrap().build_mock().unwrap();
+        assert_eq!(device.vcom_voltage().unwrap().millivolts(), 1530);
+        assert_eq!(device.rotation(), Rotation::Rotate270);
+        assert_eq!(device.panel().unwrap().name, "9.7");
+    }
+
+    #[test]
+    fn test_custom_panel() {
+        let config = DisplayConfig::from_toml(
+            r#"
+            [custom_panel]
+            name = "5.2"
+            width = 1280
+            height = 720
+            lut_versions = ["M841_X"]
+            mirrored = true
+            mode_count = 8
+            "#,
+        )
+        .unwrap();
+
+        let panel = config.panel_profile().unwrap().unwrap();
+        assert_eq!((panel.width, panel.height), (1280, 720));
+        assert_eq!(panel.lut_versions, ["M841_X"]);
+        assert_eq!(panel.mode_count, Some(8));
+        assert_eq!(panel.pixel_alignment, 4);
+
+        let device = config.builder().build_mock().unwrap();
+        assert_eq!(device.panel(), Some(panel));
+        assert_eq!(DisplayConfig::from_toml(&config.to_toml().unwrap()).unwrap(), config);
+
+        assert!(matches!(
+            DisplayConfig::from_toml("[custom_panel]\nname = \"x\""),
+            Err(Error::Config(_))
+        ));
+        assert!(matches!(
+            DisplayConfig::from_toml("panel = \"6\"\n[custom_panel]\nwidth = 1\nheight = 1"),
+            Err(Error::Config(_))
+        ));
+        assert!(matches!(
+            DisplayConfig::from_toml("[custom_panel]\nwidth = 1\nheight = 1\nbogus = 1"),
+            Err(Error::Config(_))
+        ));
+    }
+
+    #[test]
+    fn test_types_serialize() {
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Doc {
+            area: Area,
+            format: PixelFormat,
+        }
+
+        let doc = Doc {
+            area: Area::new(1, 2, 3, 4),
+            format: PixelFormat::Bpp4,
+        };
+        let text = toml::to_string(&doc).unwrap();
+        assert!(text.contains("format = \"bpp4\""));
+        assert_eq!(toml::from_str::<Doc>(&text).unwrap(), doc);
+    }
+}
@@ -1,9 +1,12 @@
 //! Builder pattern for IT8951 device construction.
 
-use crate::device::IT8951;
+use crate::device::{RecoveryPolicy, IT8951};
 use crate::error::{Error, Result};
 use crate::hal::linux::{pins, speed, LinuxInputPin, LinuxOutputPin, LinuxSpi, NoOpOutputPin};
 use crate::hal::PinState;
+use crate::panel::PanelProfile;
+use crate::types::{Rotation, Vcom};
+use std::time::Duration;
 
 /// Builder for constructing an IT8951 device.
 ///
@@ -15,49 +18,212 @@ use crate::hal::PinState;
 /// use it8951::IT8951;
 ///
 /// let display = IT8951::builder()
-///     .vcom(1500)
+///     .vcom_voltage("-1.50V".parse()?)
 ///     .build_mock()?; // For testing
 /// ```
 #[derive(Debug, Clone)]
 pub struct IT8951Builder {
     vcom: u16,
+    recovery: RecoveryPolicy,
+    rotation: Rotation,
+    panel: Option<PanelProfile>,
+    spi_path: String,
+    command_speed_hz: u32,
+    data_speed_hz: Option<u32>,
+    gpio_chip: String,
+    hrdy_pin: u32,
+    reset_pin: u32,
+    ready_timeout: Option<Duration>,
+    init_timeout: Option<Duration>,
 }
 
 impl IT8951Builder {
     /// Creates a new builder with default values.
     pub fn new() -> Self {
-        Self { vcom: 1500 }
+        Self {
+            vcom: 1500,
+            recovery: RecoveryPolicy::default(),
+            rotation: Rotation::Rotate0,
+            panel: None,
+            spi_path: "/dev/spidev0.0".to_string(),
+            command_speed_hz: speed::COMMAND_HZ,
+            data_speed_hz: None,
+            gpio_chip: "/dev/gpiochip0".to_string(),
+            hrdy_pin: pins::HRDY,
+            reset_pin: pins::RST,
+            ready_timeout: None,
+            init_timeout: None,
+        }
     }
 
-    /// Sets the VCOM voltage value.
+    /// Sets the VCOM voltage.
     ///
-    /// # Arguments
+    /// # Examples
     ///
-    /// * `vcom` - VCOM value (e.g., 1500 for -1.50V)
+    /// ```
+    /// use it8951::{IT8951Builder, Vcom};
+    ///
+    /// let vcom: Vcom = "-1.53V".parse().unwrap();
+    /// let builder = IT8951Builder::new().vcom_voltage(vcom);
+    /// ```
+    pub fn vcom_voltage(mut self, vcom: Vcom) -> Self {
+        self.vcom = vcom.millivolts();
+        self
+    }
+
+    /// Sets the VCOM value as a magnitude in millivolts (e.g., 1500 for
+    /// -1.50V). Out of range values are rejected when building.
+    #[deprecated(note = "use `vcom_voltage` with a `Vcom`")]
+    pub fn vcom(mut self, vcom: u16) -> Self {
+        self.vcom = vcom;
+        self
+    }
+
+    /// Sets the policy for recovering from transient transport failures.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use it8951::{IT8951Builder, RecoveryPolicy};
+    ///
+    /// let builder = IT8951Builder::new().recovery_policy(RecoveryPolicy::new(3));
+    /// ```
+    pub fn recovery_policy(mut self, policy: RecoveryPolicy) -> Self {
+        self.recovery = policy;
+        self
+    }
+
+    /// Sets the display rotation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use it8951::{IT8951Builder, Rotation};
+    ///
+    /// // Panel mounted in portrait orientation
+    /// let builder = IT8951Builder::new().rotation(Rotation::Rotate90);
+    /// ```
+    pub fn rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Sets the panel profile instead of detecting it during `init()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use it8951::{IT8951Builder, PanelProfile};
+    ///
+    /// let builder = IT8951Builder::new().panel(PanelProfile::by_name("10.3").unwrap());
+    /// ```
+    pub fn panel(mut self, panel: PanelProfile) -> Self {
+        self.panel = Some(panel);
+        self
+    }
+
+    /// Sets the SPI device used by [`build`](Self::build).
+    ///
+    /// Defaults to `/dev/spidev0.0`.
+    pub fn spi_path(mut self, path: impl Into<String>) -> Self {
+        self.spi_path = path.into();
+        self
+    }
+
+    /// Sets the SPI clock used for commands and register access.
+    pub fn command_speed_hz(mut self, hz: u32) -> Self {
+        self.command_speed_hz = hz;
+        self
+    }
+
+    /// Sets the SPI clock used for bulk data transfers.
+    ///
+    /// Defaults to a conservative clock that works on most wiring. Use the
+    /// result of [`IT8951::calibrate_spi_speed`] to run faster.
     ///
     /// # Examples
     ///
     /// ```
     /// use it8951::IT8951Builder;
     ///
-    /// let builder = IT8951Builder::new().vcom(1530);
+    /// let builder = IT8951Builder::new().data_speed_hz(16_000_000);
     /// ```
-    pub fn vcom(mut self, vcom: u16) -> Self {
-        self.vcom = vcom;
+    pub fn data_speed_hz(mut self, hz: u32) -> Self {
+        self.data_speed_hz = Some(hz);
         self
     }
 
+    /// Sets the GPIO character device holding the HRDY and reset lines.
+    ///
+    /// Defaults to `/dev/gpiochip0`.
+    pub fn gpio_chip(mut self, path: impl Into<String>) -> Self {
+        self.gpio_chip = path.into();
+        self
+    }
+
+    /// Sets the GPIO line of the HRDY (host ready) input.
+    pub fn hrdy_pin(mut self, line: u32) -> Self {
+        self.hrdy_pin = line;
+        self
+    }
+
+    /// Sets the GPIO line of the reset output.
+    pub fn reset_pin(mut self, line: u32) -> Self {
+        self.reset_pin = line;
+        self
+    }
+
+    /// Sets how long to wait for the controller to become ready.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use it8951::IT8951Builder;
+    /// use std::time::Duration;
+    ///
+    /// let builder = IT8951Builder::new().ready_timeout(Duration::from_secs(10));
+    /// ```
+    pub fn ready_timeout(mut self, timeout: Duration) -> Self {
+        self.ready_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets how long `init()` waits for the controller to boot after reset.
+    pub fn init_timeout(mut self, timeout: Duration) -> Self {
+        self.init_timeout = Some(timeout);
+        self
+    }
+
+    /// Creates a builder from a TOML configuration file.
+    ///
+    /// See [`DisplayConfig`](crate::config::DisplayConfig) for the format.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let mut display = IT8951Builder::from_config("/etc/it8951.toml")?.build()?;
+    /// display.init()?;
+    /// ```
+    #[cfg(feature = "config")]
+    pub fn from_config<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
+        Ok(crate::config::DisplayConfig::from_file(path)?.builder())
+    }
+
     /// Validates the builder configuration.
     fn validate(&self) -> Result<()> {
         if self.vcom > 5000 {
             return Err(Error::InvalidVcom(self.vcom));
         }
+        if self.command_speed_hz == 0 || self.data_speed_hz == Some(0) {
+            return Err(Error::InvalidParameter("SPI speed must be non-zero"));
+        }
         Ok(())
     }
 
     /// Builds an IT8951 device with real Linux hardware.
     ///
-    /// Uses default SPI device `/dev/spidev0.0` and default GPIO pins.
+    /// Uses the configured SPI device and GPIO lines, by default
+    /// `/dev/spidev0.0` and the Waveshare HAT pins.
     ///
     /// # Examples
     ///
@@ -65,13 +231,14 @@ impl IT8951Builder {
     /// use it8951::IT8951;
     ///
     /// let mut display = IT8951::builder()
-    ///     .vcom(1500)
+    ///     .vcom_voltage("-1.50V".parse()?)
     ///     .build()?;
     ///
     /// display.init()?;
     /// ```
     pub fn build(self) -> Result<IT8951<LinuxSpi, LinuxInputPin, NoOpOutputPin, LinuxOutputPin>> {
-        self.build_with_spi("/dev/spidev0.0")
+        let spi_path = self.spi_path.clone();
+        self.build_with_spi(&spi_path)
     }
 
     /// Builds an IT8951 device with a custom SPI device path.
@@ -85,18 +252,26 @@ impl IT8951Builder {
     ) -> Result<IT8951<LinuxSpi, LinuxInputPin, NoOpOutputPin, LinuxOutputPin>> {
         self.validate()?;
 
-        let gpio_chip = "/dev/gpiochip0";
-
-        // Initialize SPI at data speed
-        let spi = LinuxSpi::new(spi_path, speed::COMMAND_HZ)?;
+        // Initialize SPI at command speed
+        let spi = LinuxSpi::new(spi_path, self.command_speed_hz)?;
 
         // Initialize GPIO pins (CS is handled by SPI driver, so we use NoOp)
-        let hrdy = LinuxInputPin::new(gpio_chip, pins::HRDY)?;
+        let hrdy = LinuxInputPin::new(&self.gpio_chip, self.hrdy_pin)?;
         let cs = NoOpOutputPin;
-        let reset = LinuxOutputPin::new(gpio_chip, pins::RST, PinState::High)?;
+        let reset = LinuxOutputPin::new(&self.gpio_chip, self.reset_pin, PinState::High)?;
 
         let mut device = IT8951::new(spi, hrdy, cs, reset, self.vcom);
-        device.transport.set_speeds(speed::COMMAND_HZ, speed::DATA_HZ);
+        let data_hz = self.data_speed_hz.unwrap_or(speed::DATA_HZ);
+        device.transport.set_speeds(self.command_speed_hz, data_hz);
+        if let Some(timeout) = self.ready_timeout {
+            device.transport.set_timeout(timeout);
+        }
+        if let Some(timeout) = self.init_timeout {
+            device.set_init_timeout(timeout);
+        }
+        device.set_recovery_policy(self.recovery);
+        device.set_rotation(self.rotation);
+        device.set_panel(self.panel);
         Ok(device)
     }
 
@@ -123,7 +298,20 @@ impl IT8951Builder {
         let cs = MockOutputPin::new(PinState::High);
         let reset = MockOutputPin::new(PinState::High);
 
-        Ok(IT8951::new(spi, hrdy, cs, reset, self.vcom))
+        let mut device = IT8951::new(spi, hrdy, cs, reset, self.vcom);
+        if let Some(hz) = self.data_speed_hz {
+            device.transport.set_speeds(0, hz);
+        }
+        if let Some(timeout) = self.ready_timeout {
+            device.transport.set_timeout(timeout);
+        }
+        if let Some(timeout) = self.init_timeout {
+            device.set_init_timeout(timeout);
+        }
+        device.set_recovery_policy(self.recovery);
+        device.set_rotation(self.rotation);
+        device.set_panel(self.panel);
+        Ok(device)
     }
 }
 
@@ -145,11 +333,12 @@ mod tests {
 
     #[test]
     fn test_builder_vcom() {
-        let builder = IT8951Builder::new().vcom(1530);
+        let builder = IT8951Builder::new().vcom_voltage("-1.53V".parse().unwrap());
         assert_eq!(builder.vcom, 1530);
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_builder_validation() {
         let builder = IT8951Builder::new().vcom(6000);
         assert!(matches!(builder.validate(), Err(Error::InvalidVcom(6000))));
@@ -157,11 +346,56 @@ mod tests {
 
     #[test]
     fn test_build_mock() {
-        let device = IT8951Builder::new().vcom(1500).build_mock().unwrap();
-        assert_eq!(device.vcom(), 1500);
+        let device = IT8951Builder::new()
+            .vcom_voltage(Vcom::from_millivolts(1500).unwrap())
+            .build_mock()
+            .unwrap();
+        assert_eq!(device.vcom_voltage().unwrap().millivolts(), 1500);
+    }
+
+    #[test]
+    fn test_build_mock_recovery_policy() {
+        let device = IT8951Builder::new()
+            .recovery_policy(RecoveryPolicy::new(2))
+            .build_mock()
+            .unwrap();
+        assert_eq!(device.recovery_policy().max_retries, 2);
+    }
+
+    #[test]
+    fn test_build_mock_rotation() {
+        let device = IT8951Builder::new()
+            .rotation(Rotation::Rotate270)
+            .build_mock()
+            .unwrap();
+        assert_eq!(device.rotation(), Rotation::Rotate270);
+    }
+
+    #[test]
+    fn test_build_mock_data_speed() {
+        let device = IT8951Builder::new()
+            .data_speed_hz(16_000_000)
+            .build_mock()
+            .unwrap();
+        assert_eq!(device.transport.speeds().1, 16_000_000);
+
+        let result = IT8951Builder::new().data_speed_hz(0).build_mock();
+        assert!(matches!(result, Err(Error::InvalidParameter(_))));
+    }
+
+    #[test]
+    fn test_build_mock_timeouts() {
+        let device = IT8951Builder::new()
+            .ready_timeout(Duration::from_millis(800))
+            .init_timeout(Duration::from_millis(1200))
+            .build_mock()
+            .unwrap();
+        assert_eq!(device.transport.timeout(), Duration::from_millis(800));
+        assert_eq!(device.init_timeout(), Duration::from_millis(1200));
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_build_mock_invalid_vcom() {
         let result = IT8951Builder::new().vcom(6000).build_mock();
         assert!(matches!(result, Err(Error::InvalidVcom(6000))));
@@ -0,0 +1,179 @@
+//! SPI data clock calibration.
+
+use crate::device::IT8951;
+use crate::error::{Error, Result};
+use crate::hal::{InputPin, OutputPin, SpiTransfer};
+
+/// Data clocks tried by `calibrate_spi_speed`, slowest first.
+pub const DEFAULT_SPEED_STEPS: [u32; 9] = [
+    4_000_000, 8_000_000, 12_000_000, 16_000_000, 20_000_000, 24_000_000, 32_000_000,
+    40_000_000, 48_000_000,
+];
+
+/// Bytes written per test pattern.
+const PATTERN_SIZE: usize = 8192;
+
+/// Share of the highest reliable clock that is actually used, in percent.
+const SAFETY_MARGIN_PERCENT: u64 = 80;
+
+/// Command clock used during calibration if none is configured.
+const FALLBACK_COMMAND_HZ: u32 = 1_000_000;
+
+/// Result of an SPI clock calibration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpiCalibration {
+    /// Data clock selected, with the safety margin applied
+    pub data_speed_hz: u32,
+
+    /// Highest data clock that passed every test pattern
+    pub max_reliable_hz: u32,
+
+    /// Every clock tried and whether it passed
+    pub steps: Vec<(u32, bool)>,
+}
+
+/// Returns the test patterns: alternating bits, alternating bytes and
+/// pseudo-random data.
+fn test_patterns() -> [Vec<u8>; 3] {
+    let mut seed = 0x2545_F491u32;
+    let random = (0..PATTERN_SIZE)
+        .map(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed as u8
+        })
+        .collect();
+
+    [
+        [0x55, 0xAA].repeat(PATTERN_SIZE / 2),
+        [0x00, 0xFF].repeat(PATTERN_SIZE / 2),
+        random,
+    ]
+}
+
+impl<SPI, HRDY, CS, RESET> IT8951<SPI, HRDY, CS, RESET>
+where
+    SPI: SpiTransfer,
+    HRDY: InputPin,
+    CS: OutputPin,
+    RESET: OutputPin,
+{
+    /// Finds the highest reliable SPI data clock and switches to it.
+    ///
+    /// Test patterns are written to scratch SDRAM at each clock in
+    /// [`DEFAULT_SPEED_STEPS`] and read back at the command clock. The
+    /// search stops at the first clock that corrupts data; 80% of the
+    /// highest passing clock is then used for bulk transfers. Store
+    /// [`SpiCalibration::data_speed_hz`] and pass it to
+    /// [`IT8951Builder::data_speed_hz`](crate::IT8951Builder::data_speed_hz)
+    /// to skip calibration on the next start.
+    ///
+    /// Requires `init()` to have been called.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Device` if even the slowest clock fails, in which
+    /// case the previous speeds are kept.
+    pub fn calibrate_spi_speed(&mut self) -> Result<SpiCalibration> {
+        self.calibrate_spi_speed_with(&DEFAULT_SPEED_STEPS)
+    }
+
+    /// Calibrates the SPI data clock over custom clock steps.
+    ///
+    /// `steps` should be sorted slowest first. See
+    /// [`calibrate_spi_speed`](Self::calibrate_spi_speed).
+    pub fn calibrate_spi_speed_with(&mut self, steps: &[u32]) -> Result<SpiCalibration> {
+        let scratch = self.scratch_addr(PATTERN_SIZE)?;
+        let (command_hz, original_data_hz) = self.transport.speeds();
+        let command_hz = if command_hz == 0 {
+            FALLBACK_COMMAND_HZ
+        } else {
+            command_hz
+        };
+
+        let patterns = test_patterns();
+        let mut results = Vec::with_capacity(steps.len());
+        let mut max_reliable = None;
+
+        for &hz in steps {
+            self.transport.set_speeds(command_hz, hz);
+            let passed = match self.patterns_survive(scratch, &patterns) {
+                Ok(passed) => passed,
+                Err(e) if e.is_retryable() => false,
+                Err(e) => {
+                    self.transport.set_speeds(command_hz, original_data_hz);
+                    return Err(e);
+                }
+            };
+            log::debug!("SPI data clock {} Hz: {}", hz, if passed { "ok" } else { "failed" });
+
+            results.push((hz, passed));
+            if !passed {
+                break;
+            }
+            max_reliable = Some(hz);
+        }
+
+        let Some(max_reliable_hz) = max_reliable else {
+            self.transport.set_speeds(command_hz, original_data_hz);
+            return Err(Error::Device("no reliable SPI data clock found".to_string()));
+        };
+
+        let data_speed_hz = (max_reliable_hz as u64 * SAFETY_MARGIN_PERCENT / 100) as u32;
+        self.transport.set_speeds(command_hz, data_speed_hz);
+        log::info!(
+            "SPI data clock set to {} Hz (highest reliable {} Hz)",
+            data_speed_hz,
+            max_reliable_hz
+        );
+
+        Ok(SpiCalibration {
+            data_speed_hz,
+            max_reliable_hz,
+            steps: results,
+        })
+    }
+
+    /// Writes each pattern at the data clock and checks the read-back.
+    fn patterns_survive(&mut self, addr: u32, patterns: &[Vec<u8>]) -> Result<bool> {
+        for pattern in patterns {
+            self.write_memory(addr, pattern)?;
+            if self.read_memory(addr, pattern.len())? != *pattern {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::mock::mock_device;
+
+    #[test]
+    fn test_calibrate_spi_speed() {
+        let (controller, mut device) = mock_device();
+        controller.set_max_reliable_hz(20_000_000);
+
+        let calibration = device.calibrate_spi_speed().unwrap();
+        assert_eq!(calibration.max_reliable_hz, 20_000_000);
+        assert_eq!(calibration.data_speed_hz, 16_000_000);
+        assert_eq!(calibration.steps.last(), Some(&(24_000_000, false)));
+        assert_eq!(device.transport.speeds(), (1_000_000, 16_000_000));
+    }
+
+    #[test]
+    fn test_calibrate_spi_speed_all_fail() {
+        let (controller, mut device) = mock_device();
+        controller.set_max_reliable_hz(1_000_000);
+        device.transport.set_speeds(1_000_000, 12_000_000);
+
+        assert!(matches!(
+            device.calibrate_spi_speed(),
+            Err(Error::Device(_))
+        ));
+        assert_eq!(device.transport.speeds(), (1_000_000, 12_000_000));
+    }
+}
@@ -0,0 +1,79 @@
+//! Firmware capability table.
+
+/// Features supported by the controller firmware and panel.
+///
+/// [`Capabilities::permissive`] holds the documented feature set:
+///
+/// | Feature       | Supported | Source                                       |
+/// |---------------|-----------|----------------------------------------------|
+/// | 1bpp updates  | yes       | IT8951 I80 programming guide, 1bpp mode      |
+/// | Hardware fill | probed    | `UP1SR` fill enable bit reads back as set    |
+/// | Temperature   | yes       | I80 user command `0x0040`                    |
+/// | SPI flash     | no        | USB vendor commands, not part of the I80 set |
+///
+/// No firmware release is known to lack a documented feature, so the
+/// table is not keyed on the [`FirmwareVersion`](crate::FirmwareVersion)
+/// or [`LutVersion`](crate::LutVersion) and every controller starts from
+/// this set. The alignment comes from the
+/// [`PanelProfile`](crate::PanelProfile) where one is known. The table can
+/// be overridden with
+/// [`IT8951::set_capabilities`](crate::IT8951::set_capabilities), which
+/// is also how the SPI flash commands are enabled on firmware that accepts
+/// them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// 1bpp display updates (`refresh_area_1bpp`)
+    pub one_bpp: bool,
+
+    /// Fill rectangle engine; when `true` support is still probed on first use
+    pub hardware_fill: bool,
+
+    /// Temperature read and force commands
+    pub temperature: bool,
+
+    /// SPI flash read, erase and write commands
+    pub flash: bool,
+
+    /// Required alignment of x and width, in pixels
+    pub pixel_alignment: u16,
+
+    /// Required alignment of x and width for 1bpp updates, in pixels
+    pub pixel_alignment_1bpp: u16,
+}
+
+impl Capabilities {
+    /// Every documented feature, with the SPI flash commands disabled.
+    pub fn permissive() -> Self {
+        Self {
+            one_bpp: true,
+            hardware_fill: true,
+            temperature: true,
+            flash: false,
+            pixel_alignment: 4,
+            pixel_alignment_1bpp: 32,
+        }
+    }
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self::permissive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capability_table() {
+        let caps = Capabilities::permissive();
+        assert!(caps.one_bpp);
+        assert!(caps.hardware_fill);
+        assert!(caps.temperature);
+        assert!(!caps.flash);
+        assert_eq!(caps.pixel_alignment, 4);
+        assert_eq!(caps.pixel_alignment_1bpp, 32);
+        assert_eq!(Capabilities::default(), caps);
+    }
+}
@@ -0,0 +1,638 @@
+//! Self-test and diagnostics report.
+
+use crate::device::IT8951;
+use crate::error::Result;
+use crate::hal::{InputPin, OutputPin, SpiTransfer};
+use crate::protocol::{Command, Register};
+use crate::types::{DeviceInfo, Vcom};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Registers written during the round trip test; their values are restored.
+const ROUND_TRIP_REGISTERS: [Register; 2] = [Register::LISAR, Register::new(0x020A)];
+
+/// Values written to each register during the round trip test.
+const ROUND_TRIP_PATTERNS: [u16; 4] = [0x0000, 0xFFFF, 0x5555, 0xAAAA];
+
+/// Number of HRDY latency samples.
+const LATENCY_SAMPLES: usize = 16;
+
+/// Bytes written per throughput measurement.
+const THROUGHPUT_BYTES: usize = 16384;
+
+/// Result of one register write and read-back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterCheck {
+    /// Register tested
+    pub register: Register,
+
+    /// Value written
+    pub written: u16,
+
+    /// Value read back, or the error that prevented it
+    pub read: std::result::Result<u16, String>,
+}
+
+impl RegisterCheck {
+    /// Returns whether the value read back matches the value written.
+    pub fn passed(&self) -> bool {
+        self.read == Ok(self.written)
+    }
+}
+
+/// Time from a command until HRDY signals the controller is ready.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyStats {
+    /// Number of samples
+    pub samples: usize,
+
+    /// Shortest wait
+    pub min: Duration,
+
+    /// Average wait
+    pub mean: Duration,
+
+    /// Longest wait
+    pub max: Duration,
+}
+
+/// Measured SPI write throughput.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Throughput {
+    /// Clock for commands and register access
+    pub command_speed_hz: u32,
+
+    /// Clock for bulk data transfers
+    pub data_speed_hz: u32,
+
+    /// Bytes per second written at the command clock
+    pub command_bytes_per_sec: f64,
+
+    /// Bytes per second written at the data clock
+    pub data_bytes_per_sec: f64,
+}
+
+/// Configured VCOM and the value the controller reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VcomCheck {
+    /// VCOM the driver is configured with
+    pub configured: Vcom,
+
+    /// VCOM read back from the controller
+    pub read_back: Vcom,
+}
+
+impl VcomCheck {
+    /// Returns whether the controller uses the configured VCOM.
+    pub fn passed(&self) -> bool {
+        self.configured == self.read_back
+    }
+}
+
+/// Report produced by [`IT8951::diagnose`].
+///
+/// Each check holds its result, or the error that prevented it as text.
+/// The report prints as text with `{}` and as JSON with
+/// [`to_json`](Self::to_json).
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiagnosticReport {
+    /// Device information as reported, if it is plausible
+    pub device_info: std::result::Result<DeviceInfo, String>,
+
+    /// Register write and read-back round trips, or why they were skipped
+    pub registers: std::result::Result<Vec<RegisterCheck>, String>,
+
+    /// Registers whose original value could not be restored after the
+    /// round trips
+    pub restore_failures: Vec<String>,
+
+    /// HRDY latency after a command
+    pub hrdy_latency: std::result::Result<LatencyStats, String>,
+
+    /// SPI write throughput at the command and data clocks
+    pub throughput: std::result::Result<Throughput, String>,
+
+    /// LUT engine status register; 0 when every engine is idle
+    pub lut_status: std::result::Result<u16, String>,
+
+    /// VCOM read-back
+    pub vcom: std::result::Result<VcomCheck, String>,
+
+    /// Temperature in degrees Celsius, or `None` if the firmware has no
+    /// temperature command
+    pub temperature: std::result::Result<Option<i16>, String>,
+}
+
+impl DiagnosticReport {
+    /// Returns whether every check passed.
+    pub fn passed(&self) -> bool {
+        self.device_info.is_ok()
+            && self
+                .registers
+                .as_ref()
+                .is_ok_and(|checks| checks.iter().all(RegisterCheck::passed))
+            && self.restore_failures.is_empty()
+            && self.hrdy_latency.is_ok()
+            && self.throughput.is_ok()
+            && self.lut_status == Ok(0)
+            && self.vcom.as_ref().is_ok_and(VcomCheck::passed)
+            && self.temperature.is_ok()
+    }
+
+    /// Formats the report as a JSON object.
+    pub fn to_json(&self) -> String {
+        let device_info = json_check(&self.device_info, |info| {
+            format!(
+                "{{\"panel_width\": {}, \"panel_height\": {}, \"img_buf_addr\": {}, \
+                 \"fw_version\": {}, \"lut_version\": {}}}",
+                info.panel_width,
+                info.panel_height,
+                info.img_buf_addr,
+                json_string(&info.fw_version),
+                json_string(&info.lut_version)
+            )
+        });
+        let registers = json_check(&self.registers, |checks| {
+            let checks: Vec<String> = checks
+                .iter()
+                .map(|check| {
+                    let read = match &check.read {
+                        Ok(value) => value.to_string(),
+                        Err(e) => json_string(e),
+                    };
+                    format!(
+                        "{{\"register\": {}, \"written\": {}, \"read\": {}, \"passed\": {}}}",
+                        check.register.addr(),
+                        check.written,
+                        read,
+                        check.passed()
+                    )
+                })
+                .collect();
+            format!("[{}]", checks.join(", "))
+        });
+        let restore_failures: Vec<String> = self
+            .restore_failures
+            .iter()
+            .map(|e| json_string(e))
+            .collect();
+        let hrdy_latency = json_check(&self.hrdy_latency, |stats| {
+            format!(
+                "{{\"samples\": {}, \"min_ns\": {}, \"mean_ns\": {}, \"max_ns\": {}}}",
+                stats.samples,
+                stats.min.as_nanos(),
+                stats.mean.as_nanos(),
+                stats.max.as_nanos()
+            )
+        });
+        let throughput = json_check(&self.throughput, |t| {
+            format!(
+                "{{\"command_speed_hz\": {}, \"data_speed_hz\": {}, \
+                 \"command_bytes_per_sec\": {:.0}, \"data_bytes_per_sec\": {:.0}}}",
+                t.command_speed_hz, t.data_speed_hz, t.command_bytes_per_sec, t.data_bytes_per_sec
+            )
+        });
+        let lut_status = json_check(&self.lut_status, u16::to_string);
+        let vcom = json_check(&self.vcom, |check| {
+            format!(
+                "{{\"configured_mv\": {}, \"read_back_mv\": {}}}",
+                check.configured.millivolts(),
+                check.read_back.millivolts()
+            )
+        });
+        let temperature = json_check(&self.temperature, |celsius| match celsius {
+            Some(celsius) => celsius.to_string(),
+            None => "null".to_string(),
+        });
+
+        format!(
+            "{{\n  \"passed\": {},\n  \"device_info\": {},\n  \"registers\": {},\n  \
+             \"restore_failures\": [{}],\n  \"hrdy_latency\": {},\n  \
+             \"throughput\": {},\n  \"lut_status\": {},\n  \"vcom\": {},\n  \
+             \"temperature\": {}\n}}",
+            self.passed(),
+            device_info,
+            registers,
+            restore_failures.join(", "),
+            hrdy_latency,
+            throughput,
+            lut_status,
+            vcom,
+            temperature
+        )
+    }
+}
+
+/// Formats a check's value, or its error as `{"error": "..."}`.
+fn json_check<T>(check: &std::result::Result<T, String>, value: impl Fn(&T) -> String) -> String {
+    match check {
+        Ok(v) => value(v),
+        Err(e) => format!("{{\"error\": {}}}", json_string(e)),
+    }
+}
+
+/// Quotes and escapes a string for JSON.
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+impl fmt::Display for DiagnosticReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verdict = if self.passed() { "PASS" } else { "FAIL" };
+        writeln!(f, "IT8951 diagnostics: {}", verdict)?;
+
+        match &self.device_info {
+            Ok(info) => writeln!(
+                f,
+                "  Device info:    {}x{}, buffer 0x{:08X}, firmware {:?}, LUT {:?}",
+                info.panel_width,
+                info.panel_height,
+                info.img_buf_addr,
+                info.fw_version,
+                info.lut_version
+            )?,
+            Err(e) => writeln!(f, "  Device info:    FAILED: {}", e)?,
+        }
+
+        let checks = match &self.registers {
+            Ok(checks) => {
+                let passed = checks.iter().filter(|c| c.passed()).count();
+                writeln!(
+                    f,
+                    "  Registers:      {}/{} round trips ok",
+                    passed,
+                    checks.len()
+                )?;
+                checks.as_slice()
+            }
+            Err(e) => {
+                writeln!(f, "  Registers:      SKIPPED: {}", e)?;
+                &[]
+            }
+        };
+        for check in checks.iter().filter(|c| !c.passed()) {
+            let name = check.register.name().unwrap_or("?");
+            match &check.read {
+                Ok(value) => writeln!(
+                    f,
+                    "    0x{:04X} ({}): wrote 0x{:04X}, read 0x{:04X}",
+                    check.register.addr(),
+                    name,
+                    check.written,
+                    value
+                )?,
+                Err(e) => writeln!(
+                    f,
+                    "    0x{:04X} ({}): wrote 0x{:04X}, read failed: {}",
+                    check.register.addr(),
+                    name,
+                    check.written,
+                    e
+                )?,
+            }
+        }
+        for failure in &self.restore_failures {
+            writeln!(f, "    NOT RESTORED {}", failure)?;
+        }
+
+        match &self.hrdy_latency {
+            Ok(stats) => writeln!(
+                f,
+                "  HRDY latency:   {} samples, min {:?}, mean {:?}, max {:?}",
+                stats.samples, stats.min, stats.mean, stats.max
+            )?,
+            Err(e) => writeln!(f, "  HRDY latency:   FAILED: {}", e)?,
+        }
+
+        match &self.throughput {
+            Ok(t) => writeln!(
+                f,
+                "  SPI throughput: {:.1} KiB/s at {} Hz (command), {:.1} KiB/s at {} Hz (data)",
+                t.command_bytes_per_sec / 1024.0,
+                t.command_speed_hz,
+                t.data_bytes_per_sec / 1024.0,
+                t.data_speed_hz
+            )?,
+            Err(e) => writeln!(f, "  SPI throughput: FAILED: {}", e)?,
+        }
+
+        match &self.lut_status {
+            Ok(0) => writeln!(f, "  LUT engines:    idle")?,
+            Ok(status) => writeln!(f, "  LUT engines:    BUSY (LUTAFSR 0x{:04X})", status)?,
+            Err(e) => writeln!(f, "  LUT engines:    FAILED: {}", e)?,
+        }
+
+        match &self.vcom {
+            Ok(check) if check.passed() => writeln!(f, "  VCOM:           {}", check.read_back)?,
+            Ok(check) => writeln!(
+                f,
+                "  VCOM:           MISMATCH: {} (configured {})",
+                check.read_back, check.configured
+            )?,
+            Err(e) => writeln!(f, "  VCOM:           FAILED: {}", e)?,
+        }
+
+        match &self.temperature {
+            Ok(Some(celsius)) => write!(f, "  Temperature:    {} °C", celsius),
+            Ok(None) => write!(f, "  Temperature:    not supported"),
+            Err(e) => write!(f, "  Temperature:    FAILED: {}", e),
+        }
+    }
+}
+
+impl<SPI, HRDY, CS, RESET> IT8951<SPI, HRDY, CS, RESET>
+where
+    SPI: SpiTransfer,
+    HRDY: InputPin,
+    CS: OutputPin,
+    RESET: OutputPin,
+{
+    /// Runs a self-test and collects a diagnostics report.
+    ///
+    /// Checks the device information, writes test patterns to the image
+    /// buffer address registers and reads them back (restoring them
+    /// afterwards, and skipped while a LUT engine is busy), measures HRDY
+    /// latency and SPI write throughput into scratch SDRAM, and reads the
+    /// LUT status, VCOM and temperature. The display content is not changed. Checks that fail are recorded in the
+    /// report rather than returned as errors.
+    ///
+    /// Requires `init()` to have been called for the throughput test.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let report = display.diagnose();
+    /// println!("{}", report);
+    /// std::fs::write("it8951-report.json", report.to_json())?;
+    /// ```
+    pub fn diagnose(&mut self) -> DiagnosticReport {
+        if let Err(e) = self.ensure_awake() {
+            // The individual checks will show what is not responding
+            log::warn!("Diagnostics could not wake the controller: {}", e);
+        }
+
+        let device_info = self
+            .get_device_info()
+            .and_then(|info| info.validate().map(|()| info));
+        // Checked first: LISAR must not change while a LUT engine is busy
+        let lut_status = self
+            .transport
+            .read_register(Register::LUTAFSR)
+            .map_err(|e| e.to_string());
+        let (registers, restore_failures) = match &lut_status {
+            Ok(0) => {
+                let (checks, restore_failures) = self.check_registers();
+                (Ok(checks), restore_failures)
+            }
+            Ok(status) => (
+                Err(format!("LUT engines busy (LUTAFSR 0x{:04X})", status)),
+                Vec::new(),
+            ),
+            Err(e) => (Err(format!("LUT status unknown: {}", e)), Vec::new()),
+        };
+        let hrdy_latency = self.measure_hrdy_latency();
+        let throughput = self.measure_throughput();
+        let vcom = self.vcom_voltage().and_then(|configured| {
+            self.read_vcom_voltage().map(|read_back| VcomCheck {
+                configured,
+                read_back,
+            })
+        });
+        let temperature = if self.capabilities().temperature {
+            self.read_temperature().map(Some)
+        } else {
+            Ok(None)
+        };
+
+        DiagnosticReport {
+            device_info: device_info.map_err(|e| e.to_string()),
+            registers,
+            restore_failures,
+            hrdy_latency: hrdy_latency.map_err(|e| e.to_string()),
+            throughput: throughput.map_err(|e| e.to_string()),
+            lut_status,
+            vcom: vcom.map_err(|e| e.to_string()),
+            temperature: temperature.map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Writes test patterns to registers and reads them back.
+    ///
+    /// Returns the checks and the registers that could not be restored.
+    /// Only call this while every LUT engine is idle.
+    fn check_registers(&mut self) -> (Vec<RegisterCheck>, Vec<String>) {
+        let mut checks = Vec::new();
+        let mut restore_failures = Vec::new();
+        for register in ROUND_TRIP_REGISTERS {
+            let original = match self.transport.read_register(register) {
+                Ok(value) => value,
+                Err(e) => {
+                    checks.push(RegisterCheck {
+                        register,
+                        written: 0,
+                        read: Err(e.to_string()),
+                    });
+                    continue;
+                }
+            };
+
+            for written in ROUND_TRIP_PATTERNS {
+                let read = self
+                    .transport
+                    .write_register(register, written)
+                    .and_then(|()| self.transport.read_register(register))
+                    .map_err(|e| e.to_string());
+                checks.push(RegisterCheck {
+                    register,
+                    written,
+                    read,
+                });
+            }
+
+            let restored = self
+                .transport
+                .write_register(register, original)
+                .and_then(|()| self.transport.read_register(register));
+            match restored {
+                Ok(value) if value == original => {}
+                Ok(value) => restore_failures.push(format!(
+                    "0x{:04X}: wrote 0x{:04X}, read 0x{:04X}",
+                    register.addr(),
+                    original,
+                    value
+                )),
+                Err(e) => restore_failures.push(format!(
+                    "0x{:04X}: writing 0x{:04X} failed: {}",
+                    register.addr(),
+                    original,
+                    e
+                )),
+            }
+        }
+        (checks, restore_failures)
+    }
+
+    /// Times HRDY after a series of commands.
+    fn measure_hrdy_latency(&mut self) -> Result<LatencyStats> {
+        let timeout = self.transport.timeout();
+        let mut samples = Vec::with_capacity(LATENCY_SAMPLES);
+        for _ in 0..LATENCY_SAMPLES {
+            // SysRun leaves a running controller unchanged
+            self.transport.write_command(Command::SysRun)?;
+            let start = Instant::now();
+            self.transport.wait_ready_within(timeout)?;
+            samples.push(start.elapsed());
+        }
+
+        let total: Duration = samples.iter().sum();
+        Ok(LatencyStats {
+            samples: samples.len(),
+            min: samples.iter().copied().min().unwrap_or_default(),
+            mean: total / samples.len() as u32,
+            max: samples.iter().copied().max().unwrap_or_default(),
+        })
+    }
+
+    /// Times writes to scratch SDRAM at the command and data clocks.
+    fn measure_throughput(&mut self) -> Result<Throughput> {
+        let scratch = self.scratch_addr(THROUGHPUT_BYTES)?;
+        let (command_speed_hz, data_speed_hz) = self.transport.speeds();
+        let pattern = [0x5A, 0xA5].repeat(THROUGHPUT_BYTES / 2);
+
+        let rate = |device: &mut Self, hz: u32| -> Result<f64> {
+            device.transport.set_speeds(command_speed_hz, hz);
+            let start = Instant::now();
+            let result = device.write_memory(scratch, &pattern);
+            let elapsed = start.elapsed().as_secs_f64().max(1e-9);
+            result.map(|()| THROUGHPUT_BYTES as f64 / elapsed)
+        };
+
+        let command = rate(self, command_speed_hz);
+        let data = rate(self, data_speed_hz);
+        self.transport.set_speeds(command_speed_hz, data_speed_hz);
+
+        Ok(Throughput {
+            command_speed_hz,
+            data_speed_hz,
+            command_bytes_per_sec: command?,
+            data_bytes_per_sec: data?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::mock::{mock_device, MockController, MockDevice, IMG_BUF_ADDR};
+
+    fn setup() -> (MockController, MockDevice) {
+        let (controller, mut device) = mock_device();
+        controller.set_device_info(800, 600, IMG_BUF_ADDR, "SWv_0.2.1T", "M641");
+        device.init().unwrap();
+        (controller, device)
+    }
+
+    #[test]
+    fn test_diagnose_healthy_device() {
+        let (controller, mut device) = setup();
+        controller.set_temperature(23);
+        let lisar = controller.register(Register::LISAR);
+
+        let report = device.diagnose();
+
+        assert!(report.passed(), "{}", report);
+        assert_eq!(report.registers.as_ref().unwrap().len(), 8);
+        assert!(report.restore_failures.is_empty());
+        assert_eq!(report.hrdy_latency.as_ref().unwrap().samples, LATENCY_SAMPLES);
+        assert_eq!(report.temperature, Ok(Some(23)));
+        assert_eq!(controller.register(Register::LISAR), lisar);
+
+        let text = report.to_string();
+        assert!(text.starts_with("IT8951 diagnostics: PASS"));
+        assert!(text.contains("8/8 round trips ok"));
+
+        let json = report.to_json();
+        assert!(json.contains("\"passed\": true"));
+        assert!(json.contains("\"temperature\": 23"));
+    }
+
+    #[test]
+    fn test_diagnose_reports_failures() {
+        let (controller, mut device) = setup();
+        controller.set_read_only(Register::LISAR);
+
+        let report = device.diagnose();
+
+        assert!(!report.passed());
+        let checks = report.registers.as_ref().unwrap();
+        assert!(checks.iter().any(|check| !check.passed()));
+
+        let text = report.to_string();
+        assert!(text.starts_with("IT8951 diagnostics: FAIL"));
+        assert!(text.contains("(LISAR): wrote"));
+        assert!(report.to_json().contains("\"passed\": false"));
+    }
+
+    #[test]
+    fn test_diagnose_skips_registers_while_lut_busy() {
+        let (controller, mut device) = setup();
+        controller.set_register(Register::LUTAFSR, 0x0001);
+        let lisar = controller.register(Register::LISAR);
+
+        let report = device.diagnose();
+
+        assert!(!report.passed());
+        assert_eq!(report.lut_status, Ok(1));
+        assert!(report.registers.is_err());
+        assert_eq!(controller.register(Register::LISAR), lisar);
+
+        let text = report.to_string();
+        assert!(text.contains("Registers:      SKIPPED: LUT engines busy"));
+        assert!(text.contains("BUSY (LUTAFSR 0x0001)"));
+        assert!(report.to_json().contains("\"registers\": {\"error\": "));
+    }
+
+    #[test]
+    fn test_restore_failures_fail_the_report() {
+        let (_controller, mut device) = setup();
+        let mut report = device.diagnose();
+        assert!(report.passed(), "{}", report);
+
+        report
+            .restore_failures
+            .push("0x0208: writing 0x36E0 failed: SPI error".to_string());
+
+        assert!(!report.passed());
+        assert!(report.to_string().contains("NOT RESTORED 0x0208"));
+        assert!(report
+            .to_json()
+            .contains("\"restore_failures\": [\"0x0208: writing 0x36E0 failed: SPI error\"]"));
+    }
+
+    #[test]
+    fn test_diagnose_without_init() {
+        let (_controller, mut device) = mock_device();
+        device.device_info = None;
+
+        let report = device.diagnose();
+        assert!(report.throughput.is_err());
+        assert!(report.to_json().contains("\"throughput\": {\"error\": "));
+    }
+
+    #[test]
+    fn test_json_string_escapes() {
+        assert_eq!(json_string("a\"b\\c\n\u{1}"), "\"a\\\"b\\\\c\\n\\u0001\"");
+    }
+}
@@ -0,0 +1,447 @@
+//! SPI NOR flash access through the controller.
+//!
+//! The IT8951 boots its firmware and waveform LUTs from an external SPI
+//! NOR flash. The controller copies flash ranges to and from its SDRAM,
+//! which the host then reads or writes with memory bursts, staging the
+//! copies in scratch SDRAM just past the image buffer.
+//!
+//! # Experimental
+//!
+//! The flash commands reuse the codes of the IT8951 USB vendor commands.
+//! No datasheet documents them for the I80/SPI host interface, and they
+//! have not been verified on hardware, so this API may change or be
+//! removed. The commands are only sent once
+//! [`Capabilities::flash`](crate::Capabilities::flash) is enabled with
+//! [`IT8951::set_capabilities`], and every read checks that the controller
+//! actually copied the flash: the staging area is filled with a known
+//! pattern first, and a read that returns the pattern fails.
+//!
+//! Writing is disabled until explicitly enabled with
+//! [`IT8951::set_flash_writes_enabled`], and every restore must carry the
+//! CRC-32 of the image being written. Since a bad write can leave the
+//! controller unable to boot, back up the flash first and keep a way to
+//! reprogram it externally.
+
+use crate::device::IT8951;
+use crate::error::{Error, Result};
+use crate::hal::{InputPin, OutputPin, SpiTransfer};
+use crate::protocol::UserCommand;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Erase granularity of the SPI NOR flash, in bytes.
+pub const FLASH_SECTOR_SIZE: usize = 4096;
+
+/// Bytes copied through SDRAM per flash command.
+const FLASH_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Summary of a flash backup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlashBackup {
+    /// Flash address the backup starts at
+    pub addr: u32,
+
+    /// Number of bytes read
+    pub len: usize,
+
+    /// CRC-32 (IEEE) of the bytes read
+    pub crc32: u32,
+}
+
+/// Computes the CRC-32 (IEEE 802.3) checksum used for flash images.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(it8951::device::crc32(b"123456789"), 0xCBF4_3926);
+/// ```
+pub fn crc32(data: &[u8]) -> u32 {
+    !crc32_update(!0, data)
+}
+
+/// Feeds `data` into a running (non-inverted) CRC-32.
+fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+/// Pattern written to the staging area before a flash read.
+///
+/// The two seeds give patterns that differ in every byte, so flash that
+/// happens to hold one of them cannot match the other as well.
+fn sentinel(len: usize, seed: u8) -> Vec<u8> {
+    (0..len)
+        .map(|i| (i as u8).wrapping_mul(0x1D) ^ seed)
+        .collect()
+}
+
+/// Splits a 32-bit value into low and high argument words.
+fn split(value: usize) -> [u16; 2] {
+    [(value & 0xFFFF) as u16, (value >> 16) as u16]
+}
+
+impl<SPI, HRDY, CS, RESET> IT8951<SPI, HRDY, CS, RESET>
+where
+    SPI: SpiTransfer,
+    HRDY: InputPin,
+    CS: OutputPin,
+    RESET: OutputPin,
+{
+    /// Reads `len` bytes of SPI flash starting at `addr`.
+    ///
+    /// Experimental: the flash command codes are not documented for the
+    /// SPI interface and untested on hardware. Requires `init()` to have
+    /// been called.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Unsupported` unless the flash commands are enabled
+    /// in the [`Capabilities`](crate::Capabilities), and `Error::Flash` if
+    /// the controller ignores the read.
+    pub fn read_flash(&mut self, addr: u32, len: usize) -> Result<Vec<u8>> {
+        let mut data = Vec::with_capacity(len);
+        self.read_flash_chunks(addr, len, |chunk| {
+            data.extend_from_slice(chunk);
+            Ok(())
+        })?;
+        Ok(data)
+    }
+
+    /// Reads `len` bytes of SPI flash starting at `addr` into `writer`.
+    ///
+    /// The flash is read in chunks, so the image is never held in memory
+    /// as a whole. Returns the checksum of the bytes written.
+    pub fn backup_flash<W: Write>(
+        &mut self,
+        addr: u32,
+        len: usize,
+        mut writer: W,
+    ) -> Result<FlashBackup> {
+        let mut crc = !0;
+        self.read_flash_chunks(addr, len, |chunk| {
+            crc = crc32_update(crc, chunk);
+            writer.write_all(chunk)?;
+            Ok(())
+        })?;
+        writer.flush()?;
+
+        Ok(FlashBackup {
+            addr,
+            len,
+            crc32: !crc,
+        })
+    }
+
+    /// Reads `len` bytes of SPI flash starting at `addr` into a file.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// // Back up a 2 MiB flash before experimenting
+    /// let backup = display.backup_flash_to_file("it8951-flash.bin", 0, 2 * 1024 * 1024)?;
+    /// println!("CRC-32: {:08X}", backup.crc32);
+    /// ```
+    pub fn backup_flash_to_file<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        addr: u32,
+        len: usize,
+    ) -> Result<FlashBackup> {
+        let file = File::create(path)?;
+        self.backup_flash(addr, len, BufWriter::new(file))
+    }
+
+    /// Enables or disables flash writes. Writes are disabled by default.
+    pub fn set_flash_writes_enabled(&mut self, enabled: bool) {
+        self.flash_writes_enabled = enabled;
+    }
+
+    /// Returns whether flash writes are enabled.
+    pub fn flash_writes_enabled(&self) -> bool {
+        self.flash_writes_enabled
+    }
+
+    /// Writes a flash image starting at `addr` and verifies it.
+    ///
+    /// Experimental: the flash command codes are not documented for the
+    /// SPI interface and untested on hardware. The write is refused unless
+    /// the flash commands were enabled explicitly with
+    /// [`set_capabilities`](Self::set_capabilities), flash writes are
+    /// enabled, and `expected_crc32` matches the checksum of `data`, which
+    /// guards against restoring a truncated or wrong file. `addr` and the
+    /// length of `data` must be multiples of [`FLASH_SECTOR_SIZE`]. After
+    /// writing, the range is read back and compared.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Unsupported` unless the flash commands were enabled
+    /// with `set_capabilities`, and `Error::Flash` if writes are disabled,
+    /// the checksum does not match, or the read-back differs.
+    pub fn restore_flash(&mut self, addr: u32, data: &[u8], expected_crc32: u32) -> Result<()> {
+        // Never from a table default: this rewrites the firmware flash
+        let explicit = self.capabilities.is_some_and(|caps| caps.flash);
+        self.require(explicit, "SPI flash commands (enable with set_capabilities)")?;
+        self.ensure_awake()?;
+        if !self.flash_writes_enabled {
+            return Err(Error::Flash(
+                "flash writes are disabled; call set_flash_writes_enabled(true)".to_string(),
+            ));
+        }
+        let actual = crc32(data);
+        if actual != expected_crc32 {
+            return Err(Error::Flash(format!(
+                "image checksum {:08X} does not match expected {:08X}",
+                actual, expected_crc32
+            )));
+        }
+        if addr as usize % FLASH_SECTOR_SIZE != 0 || data.len() % FLASH_SECTOR_SIZE != 0 {
+            return Err(Error::InvalidParameter(
+                "flash restore must cover whole sectors",
+            ));
+        }
+
+        let staging = self.scratch_addr(FLASH_CHUNK_SIZE)?;
+        for (i, chunk) in data.chunks(FLASH_CHUNK_SIZE).enumerate() {
+            let flash_addr = addr as usize + i * FLASH_CHUNK_SIZE;
+            log::debug!(
+                "Writing {} bytes of flash at 0x{:06X}",
+                chunk.len(),
+                flash_addr
+            );
+
+            self.write_memory(staging, chunk)?;
+
+            let [flash_l, flash_h] = split(flash_addr);
+            let [len_l, len_h] = split(chunk.len());
+            let [addr_l, addr_h] = split(staging as usize);
+            self.transport.write_user_command_with_args(
+                UserCommand::SpiFlashErase,
+                &[flash_l, flash_h, len_l, len_h],
+            )?;
+            self.transport.write_user_command_with_args(
+                UserCommand::SpiFlashWrite,
+                &[flash_l, flash_h, addr_l, addr_h, len_l, len_h],
+            )?;
+        }
+
+        let mut offset = 0;
+        self.read_flash_chunks(addr, data.len(), |chunk| {
+            if let Some(pos) = chunk
+                .iter()
+                .zip(&data[offset..])
+                .position(|(read, written)| read != written)
+            {
+                return Err(Error::Flash(format!(
+                    "verification failed at 0x{:06X}",
+                    addr as usize + offset + pos
+                )));
+            }
+            offset += chunk.len();
+            Ok(())
+        })
+    }
+
+    /// Writes a flash image from a file; see [`restore_flash`](Self::restore_flash).
+    pub fn restore_flash_from_file<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        addr: u32,
+        expected_crc32: u32,
+    ) -> Result<()> {
+        let data = std::fs::read(path)?;
+        self.restore_flash(addr, &data, expected_crc32)
+    }
+
+    /// Copies flash to SDRAM chunk by chunk and hands each chunk to `sink`.
+    fn read_flash_chunks<F>(&mut self, addr: u32, len: usize, mut sink: F) -> Result<()>
+    where
+        F: FnMut(&[u8]) -> Result<()>,
+    {
+        self.require(self.capabilities().flash, "SPI flash commands")?;
+        self.ensure_awake()?;
+        let staging = self.scratch_addr(FLASH_CHUNK_SIZE)?;
+        let mut offset = 0;
+
+        while offset < len {
+            let chunk_len = (len - offset).min(FLASH_CHUNK_SIZE);
+            let flash_addr = addr as usize + offset;
+
+            // Flash that holds the first pattern cannot hold the second
+            let mut chunk = self.read_flash_chunk(flash_addr, staging, chunk_len, 0x5A)?;
+            if chunk == sentinel(chunk_len, 0x5A) {
+                chunk = self.read_flash_chunk(flash_addr, staging, chunk_len, 0xA5)?;
+                if chunk == sentinel(chunk_len, 0xA5) {
+                    return Err(Error::Flash(format!(
+                        "controller did not read flash at 0x{:06X}",
+                        flash_addr
+                    )));
+                }
+            }
+            sink(&chunk)?;
+
+            offset += chunk_len;
+        }
+
+        Ok(())
+    }
+
+    /// Copies one chunk of flash to the staging area, after filling the
+    /// staging area with the sentinel for `seed`.
+    fn read_flash_chunk(
+        &mut self,
+        flash_addr: usize,
+        staging: u32,
+        len: usize,
+        seed: u8,
+    ) -> Result<Vec<u8>> {
+        self.write_memory(staging, &sentinel(len, seed))?;
+
+        let [flash_l, flash_h] = split(flash_addr);
+        let [len_l, len_h] = split(len);
+        let [addr_l, addr_h] = split(staging as usize);
+        self.transport.write_user_command_with_args(
+            UserCommand::SpiFlashRead,
+            &[flash_l, flash_h, addr_l, addr_h, len_l, len_h],
+        )?;
+        self.read_memory(staging, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::mock::{mock_device, MockController, MockDevice};
+    use crate::Capabilities;
+
+    fn setup() -> (MockController, MockDevice) {
+        let (controller, mut device) = mock_device();
+        let image: Vec<u8> = (0..3 * FLASH_CHUNK_SIZE as u32)
+            .map(|i| (i ^ (i >> 8)) as u8)
+            .collect();
+        controller.set_flash(image);
+        device.set_capabilities(Capabilities {
+            flash: true,
+            ..Capabilities::permissive()
+        });
+        (controller, device)
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_backup_flash_to_file() {
+        let (controller, mut device) = setup();
+        let path = std::env::temp_dir().join(format!("it8951-flash-{}.bin", std::process::id()));
+
+        let len = 2 * FLASH_CHUNK_SIZE + 100;
+        let backup = device.backup_flash_to_file(&path, 0x100, len).unwrap();
+        let written = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let expected = &controller.flash()[0x100..0x100 + len];
+        assert_eq!(written, expected);
+        assert_eq!(backup.len, len);
+        assert_eq!(backup.crc32, crc32(expected));
+    }
+
+    #[test]
+    fn test_flash_requires_capability() {
+        let (controller, mut device) = setup();
+        device.set_capabilities(Capabilities::permissive());
+        device.set_flash_writes_enabled(true);
+        controller.clear_transfers();
+
+        assert!(matches!(
+            device.read_flash(0, 16),
+            Err(Error::Unsupported(_))
+        ));
+        let image = vec![0xA5; FLASH_SECTOR_SIZE];
+        assert!(matches!(
+            device.restore_flash(0, &image, crc32(&image)),
+            Err(Error::Unsupported(_))
+        ));
+        assert!(controller.get_transfers().is_empty());
+
+        // Without set_capabilities the table default applies
+        let (controller, mut device) = mock_device();
+        device.set_flash_writes_enabled(true);
+        assert!(matches!(
+            device.restore_flash(0, &image, crc32(&image)),
+            Err(Error::Unsupported(_))
+        ));
+        assert!(controller.get_transfers().is_empty());
+    }
+
+    #[test]
+    fn test_ignored_flash_read_fails() {
+        let (controller, mut device) = setup();
+        // Reads past the end of the emulated flash leave SDRAM untouched
+        controller.set_flash(Vec::new());
+
+        assert!(matches!(device.read_flash(0, 16), Err(Error::Flash(_))));
+    }
+
+    #[test]
+    fn test_flash_holding_sentinel_is_read() {
+        let (controller, mut device) = setup();
+        controller.set_flash(sentinel(16, 0x5A));
+
+        assert_eq!(device.read_flash(0, 16).unwrap(), sentinel(16, 0x5A));
+    }
+
+    #[test]
+    fn test_restore_flash_is_guarded() {
+        let (controller, mut device) = setup();
+        let image = vec![0xA5; 2 * FLASH_SECTOR_SIZE];
+        let crc = crc32(&image);
+        let original = controller.flash();
+
+        assert!(matches!(
+            device.restore_flash(0, &image, crc),
+            Err(Error::Flash(_))
+        ));
+
+        device.set_flash_writes_enabled(true);
+        assert!(matches!(
+            device.restore_flash(0, &image, crc ^ 1),
+            Err(Error::Flash(_))
+        ));
+        assert!(matches!(
+            device.restore_flash(0, &image[..100], crc32(&image[..100])),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert_eq!(controller.flash(), original);
+    }
+
+    #[test]
+    fn test_restore_flash_writes_and_verifies() {
+        let (controller, mut device) = setup();
+        let image: Vec<u8> = (0..FLASH_CHUNK_SIZE + FLASH_SECTOR_SIZE)
+            .map(|i| (i * 13) as u8)
+            .collect();
+        let addr = FLASH_SECTOR_SIZE as u32;
+
+        device.set_flash_writes_enabled(true);
+        device.restore_flash(addr, &image, crc32(&image)).unwrap();
+
+        let flash = controller.flash();
+        assert_eq!(
+            &flash[addr as usize..addr as usize + image.len()],
+            &image[..]
+        );
+    }
+}
@@ -0,0 +1,125 @@
+//! Direct access to the controller's SDRAM.
+
+use crate::device::IT8951;
+use crate::error::{Error, Result};
+use crate::hal::{InputPin, OutputPin, SpiTransfer};
+use crate::protocol::Command;
+use crate::types::SDRAM_SIZE;
+
+/// Words read per memory burst, kept small enough for a single SPI transfer.
+const BURST_READ_WORDS: usize = 1024;
+
+impl<SPI, HRDY, CS, RESET> IT8951<SPI, HRDY, CS, RESET>
+where
+    SPI: SpiTransfer,
+    HRDY: InputPin,
+    CS: OutputPin,
+    RESET: OutputPin,
+{
+    /// Reads `len` bytes of controller SDRAM starting at `addr`.
+    ///
+    /// Memory is accessed in 16-bit words, stored little-endian, so `addr`
+    /// should be even. An odd `len` reads one extra byte, which is dropped.
+    pub fn read_memory(&mut self, addr: u32, len: usize) -> Result<Vec<u8>> {
+        self.ensure_awake()?;
+        self.flush_fills_in_memory(addr, len)?;
+        let mut data = Vec::with_capacity(len + 1);
+        let mut offset = 0;
+
+        while offset < len {
+            let words = ((len - offset + 1) / 2).min(BURST_READ_WORDS);
+            let start = addr + offset as u32;
+            let args = [
+                (start & 0xFFFF) as u16,
+                (start >> 16) as u16,
+                (words & 0xFFFF) as u16,
+                (words >> 16) as u16,
+            ];
+
+            self.transport
+                .write_command_with_args(Command::MemBurstReadTrigger, &args)?;
+            self.transport.write_command(Command::MemBurstReadStart)?;
+            for word in self.transport.read_data_batch(words)? {
+                data.extend_from_slice(&word.to_le_bytes());
+            }
+            self.transport.write_command(Command::MemBurstEnd)?;
+
+            offset += words * 2;
+        }
+
+        data.truncate(len);
+        Ok(data)
+    }
+
+    /// Writes `data` to controller SDRAM starting at `addr`.
+    ///
+    /// Memory is accessed in 16-bit words, stored little-endian, so `addr`
+    /// should be even. An odd trailing byte is padded with zero.
+    pub fn write_memory(&mut self, addr: u32, data: &[u8]) -> Result<()> {
+        self.ensure_awake()?;
+        self.flush_fills_in_memory(addr, data.len())?;
+        let words: Vec<u16> = data
+            .chunks(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair.get(1).copied().unwrap_or(0)]))
+            .collect();
+        let args = [
+            (addr & 0xFFFF) as u16,
+            (addr >> 16) as u16,
+            (words.len() & 0xFFFF) as u16,
+            (words.len() >> 16) as u16,
+        ];
+
+        self.transport
+            .write_command_with_args(Command::MemBurstWrite, &args)?;
+        self.transport.write_data_batch(&words)?;
+        self.transport.write_command(Command::MemBurstEnd)?;
+
+        Ok(())
+    }
+
+    /// Returns the start of a `len`-byte scratch region of SDRAM, just past
+    /// the image buffer.
+    ///
+    /// The region is not used for display updates, but is shared by all
+    /// maintenance operations (flash staging, speed calibration).
+    pub(crate) fn scratch_addr(&self, len: usize) -> Result<u32> {
+        let info = self
+            .device_info
+            .as_ref()
+            .ok_or_else(|| Error::Init("Device not initialized".to_string()))?;
+
+        let frame = info.panel_width as u32 * info.panel_height as u32;
+        let addr = (info.img_buf_addr + frame + 3) & !3;
+        if addr as usize + len > SDRAM_SIZE as usize {
+            return Err(Error::Memory("no SDRAM left for scratch buffer".to_string()));
+        }
+
+        Ok(addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::mock::{MockController, MockInputPin, MockOutputPin};
+    use crate::hal::PinState;
+
+    #[test]
+    fn test_memory_roundtrip() {
+        let controller = MockController::new();
+        let mut device = IT8951::new(
+            controller.clone(),
+            MockInputPin::new(PinState::High),
+            MockOutputPin::new(PinState::High),
+            MockOutputPin::new(PinState::High),
+            1500,
+        );
+
+        let data: Vec<u8> = (0..5000u32).map(|i| (i * 7) as u8).collect();
+        device.write_memory(0x0020_0000, &data).unwrap();
+
+        assert_eq!(controller.memory(0x0020_0000, 5000), data);
+        assert_eq!(device.read_memory(0x0020_0000, 5000).unwrap(), data);
+        assert_eq!(device.read_memory(0x0020_0002, 3).unwrap(), data[2..5]);
+    }
+}
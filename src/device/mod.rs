@@ -5,14 +5,47 @@
 //! and power state control.
 
 mod builder;
+mod calibration;
+mod capabilities;
+mod diagnostics;
+mod flash;
+mod memory;
+mod power;
+mod ready;
+mod recovery;
+mod session;
 
 pub use builder::IT8951Builder;
+pub use calibration::{SpiCalibration, DEFAULT_SPEED_STEPS};
+pub use capabilities::Capabilities;
+pub use diagnostics::{DiagnosticReport, LatencyStats, RegisterCheck, Throughput, VcomCheck};
+pub use flash::{crc32, FlashBackup, FLASH_SECTOR_SIZE};
+pub use power::{IdlePolicy, PowerState};
+pub use ready::Ready;
+pub use recovery::RecoveryPolicy;
+pub use session::Session;
 
+use crate::display::{AlignPadding, PendingFill, VerifyPolicy, VerifyStats};
 use crate::error::{Error, Result};
 use crate::hal::{InputPin, OutputPin, SpiTransfer};
+use crate::panel::PanelProfile;
 use crate::protocol::{Command, Register, Transport, UserCommand};
-use crate::types::DeviceInfo;
-use std::time::Duration;
+use crate::types::{DeviceInfo, DisplayMode, Endian, Rotation, Vcom};
+use crate::waveform::{ModeName, Waveform};
+use power::PowerTracker;
+use std::time::{Duration, Instant};
+
+/// Lowest temperature accepted by `force_temperature`, in degrees Celsius.
+const MIN_TEMPERATURE: i16 = -20;
+
+/// Highest temperature accepted by `force_temperature`, in degrees Celsius.
+const MAX_TEMPERATURE: i16 = 60;
+
+/// Default limit for the controller to boot after a reset.
+pub const DEFAULT_INIT_TIMEOUT: Duration = Duration::from_millis(3000);
+
+/// Delay after releasing reset before HRDY reflects the booting controller.
+const RESET_SETTLE: Duration = Duration::from_millis(10);
 
 /// Main IT8951 e-paper display controller.
 ///
@@ -25,11 +58,11 @@ use std::time::Duration;
 /// use it8951::IT8951;
 ///
 /// // Create using builder pattern (Phase 3+)
-/// let mut display = IT8951::builder()
+/// let display = IT8951::builder()
 ///     .spi_device("/dev/spidev0.0")?
-///     .build()?;
+///     .build()?
+///     .into_ready()?;
 ///
-/// display.init()?;
 /// println!("Panel: {}x{}", display.width(), display.height());
 /// ```
 #[derive(Debug)]
@@ -38,6 +71,22 @@ pub struct IT8951<SPI, HRDY, CS, RESET> {
     reset: RESET,
     pub(crate) device_info: Option<DeviceInfo>,
     vcom: u16,
+    forced_temperature: Option<i16>,
+    recovery: RecoveryPolicy,
+    in_recovery: bool,
+    pub(crate) rotation: Rotation,
+    pub(crate) endian: Endian,
+    pub(crate) hardware_fill: Option<bool>,
+    pub(crate) pending_fills: Vec<PendingFill>,
+    flash_writes_enabled: bool,
+    waveform: Option<Waveform>,
+    capabilities: Option<Capabilities>,
+    panel: Option<PanelProfile>,
+    pub(crate) verify: Option<VerifyPolicy>,
+    pub(crate) align: Option<AlignPadding>,
+    pub(crate) verify_stats: VerifyStats,
+    init_timeout: Duration,
+    power: PowerTracker,
 }
 
 impl<SPI, HRDY, CS, RESET> IT8951<SPI, HRDY, CS, RESET>
@@ -49,13 +98,32 @@ where
 {
     /// Creates a new IT8951 device.
     ///
-    /// Use the builder pattern via `IT8951::builder()` for easier construction.
+    /// `vcom` is the magnitude in millivolts. It is not checked here; an
+    /// out of range value is reported by `init()` and
+    /// [`vcom_voltage`](Self::vcom_voltage). Use the builder pattern via
+    /// `IT8951::builder()` for easier construction, which checks it up front.
     pub fn new(spi: SPI, hrdy: HRDY, cs: CS, reset: RESET, vcom: u16) -> Self {
         Self {
             transport: Transport::new(spi, hrdy, cs),
             reset,
             device_info: None,
             vcom,
+            forced_temperature: None,
+            recovery: RecoveryPolicy::default(),
+            in_recovery: false,
+            rotation: Rotation::Rotate0,
+            endian: Endian::Little,
+            hardware_fill: None,
+            pending_fills: Vec::new(),
+            flash_writes_enabled: false,
+            waveform: None,
+            capabilities: None,
+            panel: None,
+            verify: None,
+            align: None,
+            verify_stats: VerifyStats::default(),
+            init_timeout: DEFAULT_INIT_TIMEOUT,
+            power: PowerTracker::new(),
         }
     }
 
@@ -66,46 +134,219 @@ where
 
     /// Initializes the IT8951 device.
     ///
-    /// This performs a hardware reset, retrieves device information,
-    /// and configures the VCOM voltage.
+    /// This performs a hardware reset, waits for HRDY to signal that the
+    /// controller has booted, retrieves and validates device information,
+    /// configures the image buffer address and packed mode (reading both
+    /// back), and configures the VCOM voltage.
     ///
     /// # Errors
     ///
     /// Returns an error if:
     /// - Hardware reset fails
-    /// - Device info retrieval fails
+    /// - The controller does not become ready within the init timeout
+    /// - Device info retrieval fails or the info is implausible
+    /// - A register does not read back the value written
     /// - VCOM configuration fails
     pub fn init(&mut self) -> Result<()> {
         // Perform hardware reset
         self.reset()?;
 
-        // Wait for device to be ready after reset (can take up to 2 seconds)
-        std::thread::sleep(Duration::from_millis(2000));
+        // HRDY stays low while the controller boots (up to about 2 seconds)
+        std::thread::sleep(RESET_SETTLE);
+        self.transport.wait_ready_within(self.init_timeout)?;
+        self.power.reset();
+
+        self.configure()
+    }
+
+    /// Attaches to a controller that is already running, without a reset.
+    ///
+    /// Wakes the controller, re-reads the device information and restores
+    /// the image buffer address, packed mode and VCOM, leaving the image
+    /// buffer and the panel untouched. This lets a restarted process resume
+    /// without the reset and boot delay of `init()`.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// // Fall back to a full init if the controller is not running
+    /// if display.attach().is_err() {
+    ///     display.init()?;
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the controller does not respond, or reports
+    /// implausible device information.
+    pub fn attach(&mut self) -> Result<()> {
+        self.transport.write_command(Command::SysRun)?;
+        self.power.reset();
+        self.configure()
+    }
 
+    /// Reads the device information and applies the host configuration.
+    fn configure(&mut self) -> Result<()> {
         // Get device information
         let device_info = self.get_device_info()?;
+        device_info.validate()?;
         let img_buf_addr = device_info.img_buf_addr;
         self.device_info = Some(device_info);
 
         // Set image buffer base address (required before any image operations)
         let addr_high = (img_buf_addr >> 16) as u16;
         let addr_low = (img_buf_addr & 0xFFFF) as u16;
-        self.transport.write_register(Register::new(0x020A), addr_high)?;
-        self.transport.write_register(Register::new(0x0208), addr_low)?;
+        self.transport
+            .write_register_verified(Register::new(0x020A), addr_high)?;
+        self.transport
+            .write_register_verified(Register::new(0x0208), addr_low)?;
 
         // Enable I80 packed mode
         self.transport
-            .write_register(Register::I80CPCR, 0x0001)?;
+            .write_register_verified(Register::I80CPCR, 0x0001)?;
+
+        let vcom = self.vcom_voltage()?;
+        if let Some(panel) = self.panel() {
+            log::debug!("IT8951 panel profile: {}", panel.description);
+            if !panel.vcom_in_range(vcom) {
+                log::warn!(
+                    "VCOM {} is outside the typical range of the {} panel",
+                    vcom,
+                    panel.name
+                );
+            }
+        }
 
         // Configure VCOM if different from current value
-        let current_vcom = self.read_vcom()?;
-        if current_vcom != self.vcom {
-            self.write_vcom(self.vcom)?;
+        if self.read_vcom_raw()? != vcom.millivolts() {
+            self.write_vcom_voltage(vcom)?;
         }
 
         Ok(())
     }
 
+    /// Sets how long `init()` waits for the controller to boot after reset.
+    pub fn set_init_timeout(&mut self, timeout: Duration) {
+        self.init_timeout = timeout;
+    }
+
+    /// Gets the boot timeout used by `init()`.
+    pub fn init_timeout(&self) -> Duration {
+        self.init_timeout
+    }
+
+    /// Sets the policy for recovering from transient transport failures.
+    pub fn set_recovery_policy(&mut self, policy: RecoveryPolicy) {
+        self.recovery = policy;
+    }
+
+    /// Gets the recovery policy.
+    pub fn recovery_policy(&self) -> RecoveryPolicy {
+        self.recovery
+    }
+
+    /// Brings a wedged controller back into a known state.
+    ///
+    /// Tries `attach()` first, which restores LISAR, I80CPCR and VCOM
+    /// without a reset, so the image buffer survives. Only if the
+    /// controller does not respond is it reset and re-initialized with
+    /// `init()`, which clears the image buffer. A forced temperature is
+    /// re-applied afterwards.
+    pub fn recover(&mut self) -> Result<()> {
+        self.recover_with(false)
+    }
+
+    /// Recovers the controller, resetting it right away if `reset` is set.
+    fn recover_with(&mut self, reset: bool) -> Result<()> {
+        if reset {
+            log::warn!("IT8951 still failing after attach, resetting");
+            self.init()?;
+        } else if let Err(err) = self.attach() {
+            log::warn!("IT8951 attach failed ({}), resetting", err);
+            self.init()?;
+        }
+
+        if let Some(celsius) = self.forced_temperature {
+            self.force_temperature(celsius)?;
+        }
+
+        Ok(())
+    }
+
+    /// Runs an operation, recovering and retrying on transient failures.
+    ///
+    /// Retryable errors trigger a backoff delay, `recover()` and another
+    /// attempt, up to the limits of the recovery policy. A controller that
+    /// still answers but stays wedged, such as with a stuck LUT engine, is
+    /// not fixed by attaching, so once a retry has failed, the following
+    /// recoveries reset it instead. Fatal errors, and the last retryable
+    /// error once retries are exhausted, are returned unchanged.
+    ///
+    /// The display operations in this crate already run through this
+    /// method; use it to give the same treatment to custom command
+    /// sequences. Calls nested inside `op` run only once, so a failure
+    /// anywhere in a sequence such as a load followed by a refresh retries
+    /// the whole sequence.
+    pub fn with_recovery<T, F>(&mut self, mut op: F) -> Result<T>
+    where
+        F: FnMut(&mut Self) -> Result<T>,
+    {
+        if self.in_recovery {
+            // The outermost call retries the whole sequence
+            self.ensure_awake()?;
+            return op(self);
+        }
+
+        self.in_recovery = true;
+        let result = self.retry(op);
+        self.in_recovery = false;
+        result
+    }
+
+    /// Runs the retry loop of `with_recovery`.
+    fn retry<T, F>(&mut self, mut op: F) -> Result<T>
+    where
+        F: FnMut(&mut Self) -> Result<T>,
+    {
+        let mut attempt = 0;
+        loop {
+            let result = self.ensure_awake().and_then(|()| op(self));
+            let err = match result {
+                Ok(value) => {
+                    // The operation itself is done; a failed power-down
+                    // is retried with the next one
+                    if let Err(err) = self.idle_after_refresh() {
+                        log::warn!("IT8951 could not power down after refresh: {}", err);
+                    }
+                    return Ok(value);
+                }
+                Err(err) => err,
+            };
+
+            if !err.is_retryable() || attempt >= self.recovery.max_retries {
+                return Err(err);
+            }
+
+            log::warn!(
+                "IT8951 operation failed ({}), recovering (retry {}/{})",
+                err,
+                attempt + 1,
+                self.recovery.max_retries
+            );
+            std::thread::sleep(self.recovery.backoff(attempt));
+            attempt += 1;
+
+            // The operation failed again after a recovery
+            let reset = attempt > 1;
+            if let Err(recover_err) = self.recover_with(reset) {
+                if !recover_err.is_retryable() {
+                    return Err(recover_err);
+                }
+                log::warn!("IT8951 recovery failed: {}", recover_err);
+            }
+        }
+    }
+
     /// Performs a hardware reset of the IT8951.
     ///
     /// Toggles the RESET pin low for 100ms, then high.
@@ -137,28 +378,102 @@ where
         self.device_info.as_ref()
     }
 
-    /// Returns the panel width in pixels.
+    /// Returns the device information read by `init()` or `attach()`.
     ///
     /// # Panics
     ///
     /// Panics if called before `init()`.
-    pub fn width(&self) -> u16 {
+    pub(crate) fn info(&self) -> &DeviceInfo {
         self.device_info
             .as_ref()
             .expect("init() must be called first")
-            .panel_width
     }
 
-    /// Returns the panel height in pixels.
+    /// Returns the display width in pixels, as seen with the current rotation.
+    ///
+    /// For 90 and 270 degree rotations this is the physical panel height.
     ///
     /// # Panics
     ///
     /// Panics if called before `init()`.
+    #[deprecated(note = "use `into_ready` and `Ready::width`, which cannot panic")]
+    pub fn width(&self) -> u16 {
+        self.display_size().expect("init() must be called first").0
+    }
+
+    /// Returns the display height in pixels, as seen with the current rotation.
+    ///
+    /// For 90 and 270 degree rotations this is the physical panel width.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called before `init()`.
+    #[deprecated(note = "use `into_ready` and `Ready::height`, which cannot panic")]
     pub fn height(&self) -> u16 {
-        self.device_info
+        self.display_size().expect("init() must be called first").1
+    }
+
+    /// Returns the physical panel width in pixels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called before `init()`.
+    #[deprecated(note = "use `into_ready` and `Ready::panel_width`, which cannot panic")]
+    pub fn panel_width(&self) -> u16 {
+        self.info().panel_width
+    }
+
+    /// Returns the physical panel height in pixels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called before `init()`.
+    #[deprecated(note = "use `into_ready` and `Ready::panel_height`, which cannot panic")]
+    pub fn panel_height(&self) -> u16 {
+        self.info().panel_height
+    }
+
+    /// Returns the display size as seen with the current rotation.
+    ///
+    /// Returns `Error::Init` if `init()` has not been called yet.
+    pub(crate) fn display_size(&self) -> Result<(u16, u16)> {
+        let device_info = self
+            .device_info
             .as_ref()
-            .expect("init() must be called first")
-            .panel_height
+            .ok_or_else(|| Error::Init("Device not initialized".to_string()))?;
+
+        if self.rotation.is_portrait() {
+            Ok((device_info.panel_height, device_info.panel_width))
+        } else {
+            Ok((device_info.panel_width, device_info.panel_height))
+        }
+    }
+
+    /// Sets the display rotation.
+    ///
+    /// All areas passed to display operations are in rotated coordinates.
+    /// The controller rotates image data while loading it, and refresh
+    /// areas are mapped back to panel coordinates.
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        self.rotation = rotation;
+    }
+
+    /// Gets the display rotation.
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    /// Sets the byte order of host image buffers passed to `load_image`.
+    ///
+    /// With `Endian::Big`, buffers are sent to the controller untouched
+    /// instead of being byte-swapped into little-endian words.
+    pub fn set_endian(&mut self, endian: Endian) {
+        self.endian = endian;
+    }
+
+    /// Gets the byte order of host image buffers.
+    pub fn endian(&self) -> Endian {
+        self.endian
     }
 
     /// Returns the image buffer base address.
@@ -166,72 +481,340 @@ where
     /// # Panics
     ///
     /// Panics if called before `init()`.
+    #[deprecated(note = "use `into_ready` and `Ready::img_buf_addr`, which cannot panic")]
     pub fn img_buf_addr(&self) -> u32 {
-        self.device_info
-            .as_ref()
-            .expect("init() must be called first")
-            .img_buf_addr
+        self.info().img_buf_addr
+    }
+
+    /// Returns the features supported by the connected firmware.
+    ///
+    /// The documented feature set (see [`Capabilities`]) unless overridden
+    /// with `set_capabilities`, with the alignment taken from the panel
+    /// profile.
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities.unwrap_or_else(|| {
+            let mut caps = Capabilities::permissive();
+            if let Some(panel) = self.panel() {
+                caps.pixel_alignment = panel.pixel_alignment;
+                caps.pixel_alignment_1bpp = panel.pixel_alignment_1bpp;
+            }
+            caps
+        })
+    }
+
+    /// Overrides the capability table for the connected firmware.
+    pub fn set_capabilities(&mut self, capabilities: Capabilities) {
+        self.capabilities = Some(capabilities);
+    }
+
+    /// Returns `Error::Unsupported` unless `supported` is set.
+    pub(crate) fn require(&self, supported: bool, feature: &'static str) -> Result<()> {
+        if supported {
+            Ok(())
+        } else {
+            Err(Error::Unsupported(feature))
+        }
+    }
+
+    /// Returns the profile of the connected panel.
+    ///
+    /// This is the profile set with [`set_panel`](Self::set_panel), or
+    /// else the built-in profile matching the device information (see
+    /// [`PanelProfile::for_device`]). Returns `None` before `init()` or for
+    /// panels without a profile.
+    pub fn panel(&self) -> Option<PanelProfile> {
+        self.panel.clone().or_else(|| {
+            self.device_info
+                .as_ref()
+                .and_then(PanelProfile::for_device)
+        })
+    }
+
+    /// Sets the panel profile, overriding automatic detection.
+    ///
+    /// Pass `None` to detect the panel from the device information again.
+    pub fn set_panel(&mut self, panel: Option<PanelProfile>) {
+        self.panel = panel;
+    }
+
+    /// Returns the mode recommended for regular updates of the panel.
+    ///
+    /// Taken from the panel profile, falling back to GC16.
+    pub fn default_mode(&self) -> DisplayMode {
+        self.panel()
+            .map_or(DisplayMode::Gc16, |panel| panel.default_mode)
+    }
+
+    /// Returns whether images are mirrored for the panel.
+    pub(crate) fn mirrored(&self) -> bool {
+        self.panel().is_some_and(|panel| panel.mirrored)
+    }
+
+    /// Sets the panel's waveform, used to resolve named update modes.
+    pub fn set_waveform(&mut self, waveform: Waveform) {
+        self.waveform = Some(waveform);
+    }
+
+    /// Returns the panel's waveform, if one was set.
+    pub fn waveform(&self) -> Option<&Waveform> {
+        self.waveform.as_ref()
+    }
+
+    /// Returns the mode number for a named update mode.
+    ///
+    /// Uses the waveform if one was set. Otherwise the number of modes is
+    /// taken from the panel profile or the LUT version, falling back to the
+    /// standard numbering of [`DisplayMode`] (INIT, DU, GC16, GL16, A2).
+    pub fn mode_number(&self, name: ModeName) -> Option<u16> {
+        if let Some(waveform) = &self.waveform {
+            return waveform.mode_number(name);
+        }
+        let mode_count = self
+            .panel()
+            .and_then(|panel| panel.mode_count)
+            .or_else(|| self.device_info.as_ref()?.lut().mode_count())
+            .unwrap_or(5);
+        (0..mode_count as u16).find(|&mode| ModeName::for_mode(mode, mode_count) == Some(name))
     }
 
     /// Reads the current VCOM value from the device.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidVcom` if the controller reports a value out
+    /// of range.
+    pub fn read_vcom_voltage(&mut self) -> Result<Vcom> {
+        Vcom::from_millivolts(self.read_vcom_raw()?)
+    }
+
+    /// Reads the current VCOM value from the device, in millivolts.
+    #[deprecated(note = "use `read_vcom_voltage`, which returns a `Vcom`")]
     pub fn read_vcom(&mut self) -> Result<u16> {
+        self.read_vcom_raw()
+    }
+
+    fn read_vcom_raw(&mut self) -> Result<u16> {
+        self.ensure_awake()?;
         self.transport.write_user_command(UserCommand::Vcom)?;
         self.transport.write_data(0)?; // 0 = read
         self.transport.read_data()
     }
 
-    /// Writes the VCOM value to the device.
+    /// Writes the VCOM value to the device and reads it back.
     ///
-    /// # Arguments
+    /// The value is lost on reset; see [`persist_vcom`](Self::persist_vcom).
+    ///
+    /// # Examples
     ///
-    /// * `vcom` - VCOM value (e.g., 1500 for -1.50V)
+    /// ```ignore
+    /// display.write_vcom_voltage("-1.53V".parse()?)?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::VcomMismatch` if the value read back differs.
+    pub fn write_vcom_voltage(&mut self, vcom: Vcom) -> Result<()> {
+        self.send_vcom(1, vcom)?; // 1 = write
+        self.vcom = vcom.millivolts();
+        Ok(())
+    }
+
+    /// Writes the VCOM value to the device, in millivolts.
     ///
     /// # Errors
     ///
     /// Returns an error if the VCOM value is out of range (0-5000).
+    #[deprecated(note = "use `write_vcom_voltage` with a `Vcom`")]
     pub fn write_vcom(&mut self, vcom: u16) -> Result<()> {
-        if vcom > 5000 {
-            return Err(Error::InvalidVcom(vcom));
+        self.write_vcom_voltage(Vcom::from_millivolts(vcom)?)
+    }
+
+    /// Writes the VCOM value to the controller's SPI flash, in millivolts.
+    ///
+    /// Unlike `write_vcom`, the value survives a reset or power cycle.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the VCOM value is out of range (0-5000).
+    #[deprecated(note = "use `write_vcom_voltage` followed by `persist_vcom`")]
+    pub fn write_vcom_to_flash(&mut self, vcom: u16) -> Result<()> {
+        let vcom = Vcom::from_millivolts(vcom)?;
+        self.send_vcom(2, vcom)?; // 2 = write to flash
+        self.vcom = vcom.millivolts();
+        Ok(())
+    }
+
+    /// Stores the configured VCOM in the controller's SPI flash, so it
+    /// survives a reset or power cycle.
+    ///
+    /// Flash writes must first be enabled with
+    /// [`set_flash_writes_enabled`](Self::set_flash_writes_enabled), since
+    /// a wrong value would be applied on every boot.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Flash` if flash writes are disabled, and
+    /// `Error::VcomMismatch` if the value read back differs.
+    pub fn persist_vcom(&mut self) -> Result<()> {
+        if !self.flash_writes_enabled {
+            return Err(Error::Flash(
+                "flash writes are disabled; call set_flash_writes_enabled(true)".to_string(),
+            ));
         }
+        let vcom = self.vcom_voltage()?;
+        self.send_vcom(2, vcom) // 2 = write to flash
+    }
 
+    /// Sends a VCOM write with the given sub-command and confirms it.
+    fn send_vcom(&mut self, operation: u16, vcom: Vcom) -> Result<()> {
+        self.ensure_awake()?;
         self.transport.write_user_command(UserCommand::Vcom)?;
-        self.transport.write_data(1)?; // 1 = write
-        self.transport.write_data(vcom)?;
-
-        self.vcom = vcom;
+        self.transport.write_data(operation)?;
+        self.transport.write_data(vcom.millivolts())?;
 
+        let actual = self.read_vcom_raw()?;
+        if actual != vcom.millivolts() {
+            return Err(Error::VcomMismatch {
+                expected: vcom.millivolts(),
+                actual,
+            });
+        }
         Ok(())
     }
 
     /// Gets the configured VCOM value.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidVcom` if the device was created with a value
+    /// out of range.
+    pub fn vcom_voltage(&self) -> Result<Vcom> {
+        Vcom::from_millivolts(self.vcom)
+    }
+
+    /// Gets the configured VCOM value, in millivolts.
+    #[deprecated(note = "use `vcom_voltage`, which returns a `Vcom`")]
     pub fn vcom(&self) -> u16 {
         self.vcom
     }
 
+    /// Reads the onboard sensor temperature in degrees Celsius.
+    ///
+    /// The controller answers with the sensor reading followed by the
+    /// forced value; the latter is tracked by
+    /// [`forced_temperature`](Self::forced_temperature).
+    ///
+    /// Returns `Error::Unsupported` if the firmware lacks the temperature
+    /// command.
+    pub fn read_temperature(&mut self) -> Result<i16> {
+        self.require(self.capabilities().temperature, "temperature command")?;
+        self.ensure_awake()?;
+        self.transport
+            .write_user_command(UserCommand::Temperature)?;
+        self.transport.write_data(0)?; // 0 = read
+        // Both words must be read, or the forced value is left pending
+        let words = self.transport.read_data_batch(2)?;
+        Ok(words[0] as i16)
+    }
+
+    /// Forces the controller to use a fixed temperature.
+    ///
+    /// Useful when the onboard sensor is unreliable, e.g. in enclosures that
+    /// heat up unevenly.
+    ///
+    /// # Arguments
+    ///
+    /// * `celsius` - Temperature in degrees Celsius
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the temperature is out of range (-20 to 60).
+    pub fn force_temperature(&mut self, celsius: i16) -> Result<()> {
+        if !(MIN_TEMPERATURE..=MAX_TEMPERATURE).contains(&celsius) {
+            return Err(Error::InvalidTemperature(celsius));
+        }
+        self.require(self.capabilities().temperature, "temperature command")?;
+        self.ensure_awake()?;
+
+        self.transport
+            .write_user_command(UserCommand::Temperature)?;
+        self.transport.write_data(1)?; // 1 = force set
+        self.transport.write_data(celsius as u16)?;
+
+        self.forced_temperature = Some(celsius);
+
+        Ok(())
+    }
+
+    /// Forgets the forced temperature on the host, without changing the
+    /// controller.
+    ///
+    /// The temperature command has no operation to release a forced value,
+    /// so the controller keeps using it until its next reset; the onboard
+    /// sensor is not restored by this call. Afterwards `recover()` no
+    /// longer re-applies the value, so it is dropped once a recovery or
+    /// `init()` resets the controller.
+    pub fn forget_forced_temperature(&mut self) {
+        self.forced_temperature = None;
+    }
+
+    /// Gets the forced temperature, if one is set.
+    pub fn forced_temperature(&self) -> Option<i16> {
+        self.forced_temperature
+    }
+
+    /// Turns the panel power sequence on or off.
+    ///
+    /// The controller normally sequences panel power around each update;
+    /// this overrides it, e.g. to keep the panel powered between updates.
+    pub fn power_sequence(&mut self, on: bool) -> Result<()> {
+        self.ensure_awake()?;
+        self.transport
+            .write_user_command_with_args(UserCommand::PowerSequence, &[on as u16])
+    }
+
     /// Puts the device into system run mode.
     pub fn run(&mut self) -> Result<()> {
-        self.transport.write_command(Command::SysRun)
+        self.enter_power_state(PowerState::Active)
     }
 
     /// Puts the device into standby mode (low power).
+    ///
+    /// Display operations wake the device automatically.
     pub fn standby(&mut self) -> Result<()> {
-        self.transport.write_command(Command::Standby)
+        self.enter_power_state(PowerState::Standby)
     }
 
     /// Puts the device into sleep mode (lowest power).
+    ///
+    /// Display operations wake the device automatically. The image buffer
+    /// is lost in sleep, so areas must be loaded or filled again before
+    /// they are refreshed.
     pub fn sleep(&mut self) -> Result<()> {
-        self.transport.write_command(Command::Sleep)
+        self.enter_power_state(PowerState::Sleep)
     }
 
     /// Waits for the display to be ready.
     ///
     /// Polls the LUTAFSR register until all LUT engines are free.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Timeout` if the LUT engines are still busy after the
+    /// transport timeout.
     pub fn wait_display_ready(&mut self) -> Result<()> {
+        self.ensure_awake()?;
+        let timeout = self.transport.timeout();
+        let start = Instant::now();
         loop {
             let status = self.transport.read_register(Register::LUTAFSR)?;
             if status == 0 {
+                self.refresh_completed();
                 break;
             }
+            if start.elapsed() > timeout {
+                return Err(Error::Timeout(timeout.as_millis() as u64));
+            }
             std::thread::yield_now();
         }
         Ok(())
@@ -242,8 +825,14 @@ where
     /// Returns `true` if all LUT engines are free and a new update can be started.
     /// This is useful for pipelining operations - you can start capturing the next
     /// frame while waiting for the current display update to complete.
+    /// Like [`wait_display_ready`](Self::wait_display_ready), it wakes the
+    /// controller if it was powered down.
     pub fn is_display_ready(&mut self) -> Result<bool> {
+        self.ensure_awake()?;
         let status = self.transport.read_register(Register::LUTAFSR)?;
+        if status == 0 {
+            self.refresh_completed();
+        }
         Ok(status == 0)
     }
 }
@@ -251,7 +840,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::hal::mock::{MockInputPin, MockOutputPin, MockSpi};
+    use crate::hal::mock::{MockController, MockInputPin, MockOutputPin, MockSpi};
     use crate::hal::PinState;
 
     fn setup_device() -> IT8951<MockSpi, MockInputPin, MockOutputPin, MockOutputPin> {
@@ -263,13 +852,54 @@ mod tests {
         IT8951::new(spi, hrdy, cs, reset, 1500)
     }
 
+    #[test]
+    fn test_out_of_range_vcom_rejected() {
+        let controller = MockController::new();
+        let hrdy = MockInputPin::new(PinState::High);
+        let cs = MockOutputPin::new(PinState::High);
+        let reset = MockOutputPin::new(PinState::High);
+        let mut device = IT8951::new(controller, hrdy, cs, reset, 6000);
+
+        assert!(matches!(device.vcom_voltage(), Err(Error::InvalidVcom(6000))));
+        assert!(matches!(device.attach(), Err(Error::InvalidVcom(6000))));
+    }
+
     #[test]
     fn test_device_creation() {
         let device = setup_device();
-        assert_eq!(device.vcom(), 1500);
+        assert_eq!(device.vcom_voltage().unwrap().millivolts(), 1500);
         assert!(device.device_info().is_none());
     }
 
+    #[test]
+    #[allow(deprecated)]
+    fn test_rotation_swaps_logical_size() {
+        let mut device = setup_device();
+        device.device_info = Some(DeviceInfo {
+            panel_width: 800,
+            panel_height: 600,
+            img_buf_addr: 0x001236E0,
+            fw_version: "test".to_string(),
+            lut_version: "test".to_string(),
+        });
+
+        assert_eq!((device.width(), device.height()), (800, 600));
+
+        device.set_rotation(Rotation::Rotate90);
+        assert_eq!((device.width(), device.height()), (600, 800));
+        assert_eq!((device.panel_width(), device.panel_height()), (800, 600));
+        assert_eq!(device.display_size().unwrap(), (600, 800));
+
+        device.set_rotation(Rotation::Rotate180);
+        assert_eq!((device.width(), device.height()), (800, 600));
+    }
+
+    #[test]
+    fn test_display_size_without_init() {
+        let device = setup_device();
+        assert!(matches!(device.display_size(), Err(Error::Init(_))));
+    }
+
     #[test]
     fn test_reset() {
         let mut device = setup_device();
@@ -281,17 +911,410 @@ mod tests {
     }
 
     #[test]
-    fn test_vcom_validation() {
-        let mut device = setup_device();
+    fn test_write_vcom_reads_back() {
+        let controller = MockController::new();
+        let hrdy = MockInputPin::new(PinState::High);
+        let cs = MockOutputPin::new(PinState::High);
+        let reset = MockOutputPin::new(PinState::High);
+        let mut device = IT8951::new(controller.clone(), hrdy, cs, reset, 1500);
 
-        // Valid VCOM
-        assert!(device.write_vcom(1500).is_ok());
+        device.write_vcom_voltage("-1.53V".parse().unwrap()).unwrap();
+        assert_eq!(controller.vcom(), 1530);
+        assert_eq!(device.vcom_voltage().unwrap().to_string(), "-1.53V");
+        assert_eq!(device.read_vcom_voltage().unwrap().millivolts(), 1530);
+    }
 
-        // Invalid VCOM (too high)
+    #[test]
+    #[allow(deprecated)]
+    fn test_deprecated_vcom_methods() {
+        let controller = MockController::new();
+        let hrdy = MockInputPin::new(PinState::High);
+        let cs = MockOutputPin::new(PinState::High);
+        let reset = MockOutputPin::new(PinState::High);
+        let mut device = IT8951::new(controller.clone(), hrdy, cs, reset, 1500);
+
+        device.write_vcom(1530).unwrap();
+        assert_eq!(device.vcom(), 1530);
+        assert_eq!(device.read_vcom().unwrap(), 1530);
         assert!(matches!(
             device.write_vcom(6000),
             Err(Error::InvalidVcom(6000))
         ));
+
+        device.write_vcom_to_flash(1620).unwrap();
+        assert_eq!(controller.vcom(), 1620);
+        assert_eq!(device.vcom(), 1620);
+        assert!(matches!(
+            device.write_vcom_to_flash(6000),
+            Err(Error::InvalidVcom(6000))
+        ));
+    }
+
+    #[test]
+    fn test_write_vcom_mismatch() {
+        // The bare mock reads back zero
+        let mut device = setup_device();
+
+        assert!(matches!(
+            device.write_vcom_voltage(Vcom::from_millivolts(1530).unwrap()),
+            Err(Error::VcomMismatch {
+                expected: 1530,
+                actual: 0
+            })
+        ));
+        assert_eq!(device.vcom_voltage().unwrap().millivolts(), 1500);
+    }
+
+    #[test]
+    fn test_persist_vcom() {
+        let controller = MockController::new();
+        let hrdy = MockInputPin::new(PinState::High);
+        let cs = MockOutputPin::new(PinState::High);
+        let reset = MockOutputPin::new(PinState::High);
+        let mut device = IT8951::new(controller.clone(), hrdy, cs, reset, 1530);
+
+        assert!(matches!(device.persist_vcom(), Err(Error::Flash(_))));
+        assert_eq!(controller.vcom(), 1500);
+
+        device.set_flash_writes_enabled(true);
+        device.persist_vcom().unwrap();
+        assert_eq!(controller.vcom(), 1530);
+
+        let ops = crate::protocol::decode::decode(&controller.get_transfers());
+        assert!(ops.iter().any(|op| matches!(
+            op,
+            crate::protocol::decode::Operation::UserCommand {
+                command: UserCommand::Vcom,
+                args,
+            } if args[..] == [2, 1530]
+        )));
+    }
+
+    #[test]
+    fn test_power_sequence() {
+        let spi = MockSpi::new();
+        let hrdy = MockInputPin::new(PinState::High);
+        let cs = MockOutputPin::new(PinState::High);
+        let reset = MockOutputPin::new(PinState::High);
+        let mut device = IT8951::new(spi.clone(), hrdy, cs, reset, 1500);
+
+        device.power_sequence(true).unwrap();
+        device.power_sequence(false).unwrap();
+
+        let transfers = spi.get_transfers();
+        assert_eq!(transfers[0], vec![0x60, 0x00, 0x00, 0x38]);
+        assert_eq!(transfers[1], vec![0x00, 0x00, 0x00, 0x01]);
+        assert_eq!(transfers[2], vec![0x60, 0x00, 0x00, 0x38]);
+        assert_eq!(transfers[3], vec![0x00, 0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_read_temperature() {
+        let mut spi = MockSpi::new();
+        let hrdy = MockInputPin::new(PinState::High);
+        let cs = MockOutputPin::new(PinState::High);
+        let reset = MockOutputPin::new(PinState::High);
+
+        // Command and argument transfers, then preamble + dummy + sensor
+        // and forced values
+        spi.add_response(vec![0x00; 4]);
+        spi.add_response(vec![0x00; 4]);
+        spi.add_response(vec![0x00, 0x00, 0x00, 0x00, 0xFF, 0xFB, 0x00, 0x19]);
+
+        let mut device = IT8951::new(spi.clone(), hrdy, cs, reset, 1500);
+        assert_eq!(device.read_temperature().unwrap(), -5);
+        assert_eq!(spi.get_transfers()[2].len(), 8);
+    }
+
+    #[test]
+    fn test_force_temperature() {
+        let mut device = setup_device();
+
+        device.force_temperature(25).unwrap();
+        assert_eq!(device.forced_temperature(), Some(25));
+
+        device.forget_forced_temperature();
+        assert_eq!(device.forced_temperature(), None);
+    }
+
+    #[test]
+    fn test_force_temperature_validation() {
+        let mut device = setup_device();
+
+        assert!(device.force_temperature(-20).is_ok());
+        assert!(device.force_temperature(60).is_ok());
+        assert!(matches!(
+            device.force_temperature(61),
+            Err(Error::InvalidTemperature(61))
+        ));
+        assert!(matches!(
+            device.force_temperature(-21),
+            Err(Error::InvalidTemperature(-21))
+        ));
+        assert_eq!(device.forced_temperature(), Some(60));
+    }
+
+    #[test]
+    fn test_unsupported_features_fail_early() {
+        let spi = MockSpi::new();
+        let mut device = IT8951::new(
+            spi.clone(),
+            MockInputPin::new(PinState::High),
+            MockOutputPin::new(PinState::High),
+            MockOutputPin::new(PinState::High),
+            1500,
+        );
+        device.device_info = Some(DeviceInfo {
+            panel_width: 800,
+            panel_height: 600,
+            img_buf_addr: 0x001236E0,
+            fw_version: "SWv_0.1.1".to_string(),
+            lut_version: "M641".to_string(),
+        });
+        device.set_capabilities(Capabilities {
+            temperature: false,
+            ..Capabilities::permissive()
+        });
+
+        assert!(!device.capabilities().temperature);
+        assert!(matches!(
+            device.read_temperature(),
+            Err(Error::Unsupported(_))
+        ));
+        assert!(matches!(
+            device.force_temperature(25),
+            Err(Error::Unsupported(_))
+        ));
+        assert!(spi.get_transfers().is_empty());
+
+        device.set_capabilities(Capabilities::permissive());
+        device.force_temperature(25).unwrap();
+    }
+
+    #[test]
+    fn test_with_recovery_fatal_error_not_retried() {
+        let mut device = setup_device();
+        device.set_recovery_policy(RecoveryPolicy::new(3));
+
+        let mut calls = 0;
+        let result: Result<()> = device.with_recovery(|_| {
+            calls += 1;
+            Err(Error::InvalidParameter("bad"))
+        });
+
+        assert!(matches!(result, Err(Error::InvalidParameter(_))));
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_with_recovery_disabled_by_default() {
+        let mut device = setup_device();
+
+        let mut calls = 0;
+        let result: Result<()> = device.with_recovery(|_| {
+            calls += 1;
+            Err(Error::Timeout(10))
+        });
+
+        assert!(matches!(result, Err(Error::Timeout(10))));
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_init_with_controller() {
+        let controller = MockController::new();
+        let hrdy = MockInputPin::new(PinState::High);
+        let cs = MockOutputPin::new(PinState::High);
+        let reset = MockOutputPin::new(PinState::High);
+        let mut device = IT8951::new(controller.clone(), hrdy, cs, reset, 1530);
+
+        device.init().unwrap();
+
+        assert_eq!(device.width(), 800);
+        assert_eq!(device.height(), 600);
+        assert_eq!(controller.register(Register::LISAR), 0x36E0);
+        assert_eq!(controller.register(Register::new(0x020A)), 0x0012);
+        assert_eq!(controller.register(Register::I80CPCR), 0x0001);
+        assert_eq!(controller.vcom(), 1530);
+    }
+
+    #[test]
+    fn test_init_times_out_while_booting() {
+        let controller = MockController::new();
+        let hrdy = MockInputPin::new(PinState::Low);
+        let cs = MockOutputPin::new(PinState::High);
+        let reset = MockOutputPin::new(PinState::High);
+        let mut device = IT8951::new(controller, hrdy, cs, reset, 1500);
+        device.set_init_timeout(Duration::from_millis(20));
+
+        assert!(matches!(device.init(), Err(Error::Timeout(20))));
+        assert!(device.device_info().is_none());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_attach_skips_reset() {
+        let controller = MockController::new();
+        let hrdy = MockInputPin::new(PinState::High);
+        let cs = MockOutputPin::new(PinState::High);
+        let reset = MockOutputPin::new(PinState::High);
+        let mut device = IT8951::new(controller.clone(), hrdy, cs, reset.clone(), 1500);
+
+        device.attach().unwrap();
+
+        assert_eq!(reset.get_history(), vec![PinState::High]);
+        assert_eq!(device.width(), 800);
+        assert_eq!(controller.register(Register::LISAR), 0x36E0);
+        assert_eq!(controller.register(Register::new(0x020A)), 0x0012);
+        assert_eq!(controller.register(Register::I80CPCR), 0x0001);
+    }
+
+    #[test]
+    fn test_init_rejects_floating_bus() {
+        let controller = MockController::new();
+        controller.set_device_info_raw(vec![0xFFFF; 20]);
+        let hrdy = MockInputPin::new(PinState::High);
+        let cs = MockOutputPin::new(PinState::High);
+        let reset = MockOutputPin::new(PinState::High);
+        let mut device = IT8951::new(controller, hrdy, cs, reset, 1500);
+
+        let err = device.init().unwrap_err();
+        assert!(matches!(err, Error::BusFault(_)));
+        assert!(err.to_string().contains("check wiring or SPI mode"));
+        assert!(device.device_info().is_none());
+    }
+
+    fn recovering_device(
+        controller: &MockController,
+    ) -> IT8951<MockController, MockInputPin, MockOutputPin, MockOutputPin> {
+        let hrdy = MockInputPin::new(PinState::High);
+        let cs = MockOutputPin::new(PinState::High);
+        let reset = MockOutputPin::new(PinState::High);
+        let mut device = IT8951::new(controller.clone(), hrdy, cs, reset, 1500);
+        device.set_recovery_policy(
+            RecoveryPolicy::new(1).initial_backoff(Duration::from_millis(1)),
+        );
+        device
+    }
+
+    #[test]
+    fn test_with_recovery_reattaches_and_retries() {
+        let controller = MockController::new();
+        let mut device = recovering_device(&controller);
+
+        let mut calls = 0;
+        let result = device.with_recovery(|device| {
+            calls += 1;
+            if calls == 1 {
+                Err(Error::Spi("bus error".to_string()))
+            } else {
+                device.run()
+            }
+        });
+
+        assert!(result.is_ok());
+        assert_eq!(calls, 2);
+
+        // The controller still answered, so the image buffer was kept
+        assert!(!device.reset.get_history().contains(&PinState::Low));
+        assert!(device.device_info().is_some());
+    }
+
+    #[test]
+    fn test_with_recovery_resets_unresponsive_controller() {
+        let controller = MockController::new();
+        let mut device = recovering_device(&controller);
+
+        let mut calls = 0;
+        let result = device.with_recovery(|device| {
+            calls += 1;
+            if calls == 1 {
+                // The attach attempt fails as well
+                controller.fail_next(1);
+                Err(Error::Spi("bus error".to_string()))
+            } else {
+                device.run()
+            }
+        });
+
+        assert!(result.is_ok());
+        assert_eq!(calls, 2);
+        assert!(device.reset.get_history().contains(&PinState::Low));
+    }
+
+    #[test]
+    fn test_with_recovery_resets_stuck_lut_engine() {
+        let controller = MockController::new();
+        let mut device = recovering_device(&controller);
+        device.set_recovery_policy(
+            RecoveryPolicy::new(3).initial_backoff(Duration::from_millis(1)),
+        );
+        device.transport.set_timeout(Duration::from_millis(1));
+        controller.set_register(Register::LUTAFSR, 0x0001);
+
+        let mut calls = 0;
+        let result = device.with_recovery(|device| {
+            calls += 1;
+            if device.reset.get_history().contains(&PinState::Low) {
+                // Only a reset frees the LUT engine
+                controller.set_register(Register::LUTAFSR, 0);
+            }
+            device.wait_display_ready()
+        });
+
+        // The attach did not help, so the second recovery reset the controller
+        assert!(result.is_ok());
+        assert_eq!(calls, 3);
+        assert!(device.reset.get_history().contains(&PinState::Low));
+    }
+
+    #[test]
+    fn test_with_recovery_retries_nested_sequence() {
+        let controller = MockController::new();
+        let mut device = recovering_device(&controller);
+
+        let (mut loads, mut refreshes) = (0, 0);
+        let result = device.with_recovery(|device| {
+            device.with_recovery(|_| {
+                loads += 1;
+                Ok(())
+            })?;
+            device.with_recovery(|_| {
+                refreshes += 1;
+                if refreshes == 1 {
+                    Err(Error::Timeout(10))
+                } else {
+                    Ok(())
+                }
+            })
+        });
+
+        assert!(result.is_ok());
+        assert_eq!((loads, refreshes), (2, 2));
+    }
+
+    #[test]
+    fn test_wait_display_ready_timeout() {
+        let mut spi = MockSpi::new();
+        let hrdy = MockInputPin::new(PinState::High);
+        let cs = MockOutputPin::new(PinState::High);
+        let reset = MockOutputPin::new(PinState::High);
+
+        // LUTAFSR reads back busy
+        for _ in 0..1000 {
+            spi.add_response(vec![0x00; 4]);
+            spi.add_response(vec![0x00; 4]);
+            spi.add_response(vec![0x00, 0x00, 0x00, 0x00, 0x00, 0x01]);
+        }
+
+        let mut device = IT8951::new(spi, hrdy, cs, reset, 1500);
+        device.transport.set_timeout(Duration::from_millis(1));
+
+        assert!(matches!(
+            device.wait_display_ready(),
+            Err(Error::Timeout(1))
+        ));
     }
 
     #[test]
@@ -0,0 +1,437 @@
+//! Power state tracking, automatic wake-up and idle power saving.
+//!
+//! Every operation that talks to the controller wakes it first. Waking
+//! from [`PowerState::Sleep`] also marks the image buffer as lost, and
+//! refreshing pixels that were not loaded or filled since then fails
+//! instead of showing whatever the SDRAM holds.
+
+use crate::device::IT8951;
+use crate::error::{Error, Result};
+use crate::hal::{InputPin, OutputPin, SpiTransfer};
+use crate::protocol::Command;
+use crate::types::Area;
+use std::time::{Duration, Instant};
+
+/// Power state of the controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerState {
+    /// Running and accepting commands
+    Active,
+
+    /// Clocks stopped, woken by SysRun
+    Standby,
+
+    /// Clocks and SDRAM refresh stopped, losing the image buffer; lowest
+    /// power, woken by SysRun
+    Sleep,
+}
+
+/// Policy for entering a low power state once the display is idle.
+///
+/// The idle period starts when the last refresh completes, as seen by
+/// [`IT8951::wait_display_ready`], [`IT8951::is_display_ready`] or
+/// [`IT8951::check_idle`].
+///
+/// # Examples
+///
+/// ```
+/// use it8951::{IdlePolicy, PowerState};
+/// use std::time::Duration;
+///
+/// // Sleep 30 seconds after the last update finished
+/// let policy = IdlePolicy::new(Duration::from_secs(30), PowerState::Sleep);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdlePolicy {
+    /// Idle period after the last refresh completed
+    pub timeout: Duration,
+
+    /// State to enter, `Standby` or `Sleep`
+    pub state: PowerState,
+}
+
+impl IdlePolicy {
+    /// Creates a policy entering `state` after `timeout` of idleness.
+    pub fn new(timeout: Duration, state: PowerState) -> Self {
+        Self { timeout, state }
+    }
+}
+
+impl<SPI, HRDY, CS, RESET> IT8951<SPI, HRDY, CS, RESET>
+where
+    SPI: SpiTransfer,
+    HRDY: InputPin,
+    CS: OutputPin,
+    RESET: OutputPin,
+{
+    /// Returns the power state the controller was last put into.
+    pub fn power_state(&self) -> PowerState {
+        self.power.state
+    }
+
+    /// Sets the policy for entering a low power state when idle.
+    ///
+    /// With a zero timeout, refresh operations wait for the update to
+    /// finish and power down immediately. Otherwise call
+    /// [`check_idle`](Self::check_idle) periodically, e.g. from the main
+    /// loop. Display operations wake the controller automatically either
+    /// way.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidParameter` if the target state is `Active`.
+    pub fn set_idle_policy(&mut self, policy: Option<IdlePolicy>) -> Result<()> {
+        if policy.is_some_and(|p| p.state == PowerState::Active) {
+            return Err(Error::InvalidParameter(
+                "idle state must be Standby or Sleep",
+            ));
+        }
+        self.power.idle = policy;
+        Ok(())
+    }
+
+    /// Gets the idle policy.
+    pub fn idle_policy(&self) -> Option<IdlePolicy> {
+        self.power.idle
+    }
+
+    /// Enters the idle policy's power state if the display has been idle
+    /// long enough.
+    ///
+    /// Returns the state entered, or `None` if nothing changed.
+    pub fn check_idle(&mut self) -> Result<Option<PowerState>> {
+        let Some(policy) = self.power.idle else {
+            return Ok(None);
+        };
+        if self.power.state != PowerState::Active {
+            return Ok(None);
+        }
+        if self.power.refresh_pending && !self.is_display_ready()? {
+            return Ok(None);
+        }
+
+        let idle_since = self.power.idle_since.get_or_insert_with(Instant::now);
+        if idle_since.elapsed() < policy.timeout {
+            return Ok(None);
+        }
+
+        self.enter_power_state(policy.state)?;
+        Ok(Some(policy.state))
+    }
+
+    /// Sends the command for `state` and records it.
+    pub(crate) fn enter_power_state(&mut self, state: PowerState) -> Result<()> {
+        let command = match state {
+            PowerState::Active => Command::SysRun,
+            PowerState::Standby => Command::Standby,
+            PowerState::Sleep => Command::Sleep,
+        };
+        self.transport.write_command(command)?;
+        self.power.state = state;
+        log::debug!("IT8951 power state: {:?}", state);
+        Ok(())
+    }
+
+    /// Wakes the controller if it is in standby or sleep.
+    ///
+    /// Sends SysRun and waits for HRDY before returning.
+    pub(crate) fn ensure_awake(&mut self) -> Result<()> {
+        if self.power.state == PowerState::Active {
+            return Ok(());
+        }
+        log::debug!("Waking IT8951 from {:?}", self.power.state);
+        let slept = self.power.state == PowerState::Sleep;
+        self.enter_power_state(PowerState::Active)?;
+        self.transport.wait_ready_within(self.transport.timeout())?;
+
+        if let (true, Some(info)) = (slept, self.device_info.as_ref()) {
+            self.power.lost = vec![Area::new(0, 0, info.panel_width, info.panel_height)];
+        }
+        Ok(())
+    }
+
+    /// Records that a panel area of the image buffer was written.
+    pub(crate) fn buffer_written(&mut self, area: &Area) {
+        if !self.power.lost.is_empty() {
+            let lost = std::mem::take(&mut self.power.lost);
+            self.power.lost = lost.iter().flat_map(|lost| subtract(lost, area)).collect();
+        }
+    }
+
+    /// Fails if a panel area includes pixels lost in sleep.
+    pub(crate) fn check_buffer_intact(&self, area: &Area) -> Result<()> {
+        if self
+            .power
+            .lost
+            .iter()
+            .any(|lost| lost.intersect(area).is_some())
+        {
+            return Err(Error::Display(
+                "image buffer was lost in sleep; load the area before refreshing it".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Records that a display update was started.
+    pub(crate) fn refresh_started(&mut self) {
+        self.power.refresh_pending = true;
+        self.power.refreshed = true;
+        self.power.idle_since = None;
+    }
+
+    /// Records that the LUT engines were seen idle.
+    pub(crate) fn refresh_completed(&mut self) {
+        if self.power.refresh_pending {
+            self.power.refresh_pending = false;
+            self.power.idle_since = Some(Instant::now());
+        }
+    }
+
+    /// Powers down right after a refresh when the idle timeout is zero.
+    pub(crate) fn idle_after_refresh(&mut self) -> Result<()> {
+        // Some updates already wait for the LUT engines themselves
+        let refreshed = std::mem::take(&mut self.power.refreshed);
+        match self.power.idle {
+            Some(policy) if policy.timeout.is_zero() && refreshed => {
+                self.wait_display_ready()?;
+                self.enter_power_state(policy.state)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Splits the part of `area` outside `cut` into up to four areas.
+fn subtract(area: &Area, cut: &Area) -> Vec<Area> {
+    let Some(inner) = area.intersect(cut) else {
+        return vec![*area];
+    };
+    [
+        Area::new(area.x, area.y, area.width, inner.y - area.y),
+        Area::new(
+            area.x,
+            inner.bottom(),
+            area.width,
+            area.bottom() - inner.bottom(),
+        ),
+        Area::new(area.x, inner.y, inner.x - area.x, inner.height),
+        Area::new(
+            inner.right(),
+            inner.y,
+            area.right() - inner.right(),
+            inner.height,
+        ),
+    ]
+    .into_iter()
+    .filter(|part| part.width > 0 && part.height > 0)
+    .collect()
+}
+
+/// Power bookkeeping kept by the device.
+#[derive(Debug)]
+pub(crate) struct PowerTracker {
+    state: PowerState,
+    idle: Option<IdlePolicy>,
+    refresh_pending: bool,
+    refreshed: bool,
+    idle_since: Option<Instant>,
+    /// Panel areas of the image buffer lost in sleep and not written since
+    lost: Vec<Area>,
+}
+
+impl PowerTracker {
+    /// Creates a tracker for a running controller.
+    pub(crate) fn new() -> Self {
+        Self {
+            state: PowerState::Active,
+            idle: None,
+            refresh_pending: false,
+            refreshed: false,
+            idle_since: None,
+            lost: Vec::new(),
+        }
+    }
+
+    /// Records that the controller was reset or attached and is running.
+    pub(crate) fn reset(&mut self) {
+        self.state = PowerState::Active;
+        self.refresh_pending = false;
+        self.refreshed = false;
+        self.idle_since = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::mock::{mock_device, MockController};
+    use crate::protocol::decode::{decode, Operation};
+    use crate::protocol::Register;
+    use crate::types::{Area, DisplayMode};
+
+    fn commands(controller: &MockController) -> Vec<Command> {
+        decode(&controller.get_transfers())
+            .into_iter()
+            .filter_map(|op| match op {
+                Operation::Command { command, .. } => Some(command),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_power_state_tracking() {
+        let (_, mut device) = mock_device();
+        assert_eq!(device.power_state(), PowerState::Active);
+
+        device.standby().unwrap();
+        assert_eq!(device.power_state(), PowerState::Standby);
+        device.sleep().unwrap();
+        assert_eq!(device.power_state(), PowerState::Sleep);
+        device.run().unwrap();
+        assert_eq!(device.power_state(), PowerState::Active);
+    }
+
+    #[test]
+    fn test_operations_wake_the_controller() {
+        let (controller, mut device) = mock_device();
+        device.standby().unwrap();
+        controller.clear_transfers();
+
+        device
+            .refresh_area(&Area::new(0, 0, 16, 16), DisplayMode::Du)
+            .unwrap();
+
+        assert_eq!(commands(&controller).first(), Some(&Command::SysRun));
+        assert_eq!(device.power_state(), PowerState::Active);
+    }
+
+    #[test]
+    fn test_queries_wake_the_controller() {
+        let (controller, mut device) = mock_device();
+
+        device.standby().unwrap();
+        device.read_temperature().unwrap();
+        assert_eq!(device.power_state(), PowerState::Active);
+
+        device.sleep().unwrap();
+        device.read_memory(0, 2).unwrap();
+        assert_eq!(device.power_state(), PowerState::Active);
+
+        device.standby().unwrap();
+        controller.clear_transfers();
+        device.wait_display_ready().unwrap();
+        assert_eq!(commands(&controller).first(), Some(&Command::SysRun));
+    }
+
+    #[test]
+    fn test_refresh_after_sleep_needs_reload() {
+        let (_, mut device) = mock_device();
+        device.sleep().unwrap();
+
+        let loaded = Area::new(0, 0, 16, 16);
+        let packed = vec![0xFF; 16 * 16];
+        device
+            .load_image(&packed, &loaded, crate::types::PixelFormat::Bpp8)
+            .unwrap();
+        device.refresh_area(&loaded, DisplayMode::Du).unwrap();
+        assert!(matches!(
+            device.refresh_area(&Area::new(0, 0, 32, 16), DisplayMode::Du),
+            Err(Error::Display(_))
+        ));
+
+        device.clear(0xFF).unwrap();
+        device.refresh(DisplayMode::Gc16).unwrap();
+    }
+
+    #[test]
+    fn test_pending_fill_refreshed_after_sleep() {
+        let (_, mut device) = mock_device();
+        device.clear(0xFF).unwrap();
+        device.sleep().unwrap();
+
+        // The fill engine draws the update without reading the buffer
+        device.refresh(DisplayMode::Gc16).unwrap();
+        assert_eq!(device.hardware_fill_supported(), Some(true));
+    }
+
+    #[test]
+    fn test_standby_keeps_the_buffer() {
+        let (_, mut device) = mock_device();
+        device.standby().unwrap();
+        device.refresh(DisplayMode::Du).unwrap();
+    }
+
+    #[test]
+    fn test_subtract() {
+        let area = Area::new(0, 0, 10, 10);
+        let parts = subtract(&area, &Area::new(2, 3, 4, 5));
+        assert_eq!(
+            parts,
+            [
+                Area::new(0, 0, 10, 3),
+                Area::new(0, 8, 10, 2),
+                Area::new(0, 3, 2, 5),
+                Area::new(6, 3, 4, 5),
+            ]
+        );
+        let covered: usize = parts.iter().map(Area::pixel_count).sum();
+        assert_eq!(covered, 100 - 20);
+        assert_eq!(subtract(&area, &Area::new(20, 20, 1, 1)), [area]);
+        assert!(subtract(&area, &area).is_empty());
+    }
+
+    #[test]
+    fn test_zero_idle_timeout_powers_down_after_refresh() {
+        let (controller, mut device) = mock_device();
+        device
+            .set_idle_policy(Some(IdlePolicy::new(Duration::ZERO, PowerState::Standby)))
+            .unwrap();
+
+        device.clear(0xFF).unwrap();
+        assert_eq!(device.power_state(), PowerState::Active);
+
+        device.refresh(DisplayMode::Gc16).unwrap();
+        assert_eq!(device.power_state(), PowerState::Standby);
+        assert_eq!(commands(&controller).last(), Some(&Command::Standby));
+    }
+
+    #[test]
+    fn test_failed_power_down_keeps_the_refresh() {
+        let (controller, mut device) = mock_device();
+        device
+            .set_idle_policy(Some(IdlePolicy::new(Duration::ZERO, PowerState::Standby)))
+            .unwrap();
+        device.transport.set_timeout(Duration::from_millis(1));
+        controller.set_register(Register::LUTAFSR, 0x0001);
+
+        // The update was started; only waiting to power down times out
+        device.refresh(DisplayMode::Du).unwrap();
+        assert_eq!(device.power_state(), PowerState::Active);
+    }
+
+    #[test]
+    fn test_check_idle_waits_for_timeout() {
+        let (_, mut device) = mock_device();
+        let policy = IdlePolicy::new(Duration::from_millis(30), PowerState::Sleep);
+        device.set_idle_policy(Some(policy)).unwrap();
+
+        device.refresh(DisplayMode::Du).unwrap();
+        assert_eq!(device.check_idle().unwrap(), None);
+
+        std::thread::sleep(Duration::from_millis(40));
+        assert_eq!(device.check_idle().unwrap(), Some(PowerState::Sleep));
+        assert_eq!(device.check_idle().unwrap(), None);
+    }
+
+    #[test]
+    fn test_idle_policy_rejects_active() {
+        let (_, mut device) = mock_device();
+        let policy = IdlePolicy::new(Duration::ZERO, PowerState::Active);
+        assert!(matches!(
+            device.set_idle_policy(Some(policy)),
+            Err(Error::InvalidParameter(_))
+        ));
+    }
+}
@@ -0,0 +1,393 @@
+//! Initialized devices with their device information known statically.
+
+use crate::device::IT8951;
+use crate::error::{Error, Result};
+use crate::graphics::Framebuffer;
+use crate::hal::{InputPin, OutputPin, SpiTransfer};
+use crate::types::{Area, DeviceInfo, DisplayMode, Endian, PixelFormat, Rotation};
+use crate::waveform::ModeName;
+use std::io::Read;
+use std::ops::Deref;
+
+/// An initialized IT8951 device.
+///
+/// Created by [`IT8951::into_ready`] or [`IT8951::into_attached`], so the
+/// device information has always been read and the panel geometry
+/// accessors cannot panic. `Ready` keeps its own copy of the information,
+/// updated whenever a forwarded operation initializes the device again or
+/// recovers it.
+///
+/// The device is only borrowed mutably through the operations forwarded
+/// here, none of which can fail with `Error::Init`. Read-only methods are
+/// available through `Deref`. For anything else, take the device out with
+/// [`into_inner`](Self::into_inner) and wrap it again with
+/// [`Ready::try_from`].
+///
+/// # Examples
+///
+/// ```ignore
+/// let mut display = IT8951::builder().build()?.into_ready()?;
+/// println!("Panel: {}x{}", display.width(), display.height());
+/// display.clear(0xFF)?;
+/// display.refresh(DisplayMode::Gc16)?;
+/// ```
+#[derive(Debug)]
+pub struct Ready<SPI, HRDY, CS, RESET> {
+    device: IT8951<SPI, HRDY, CS, RESET>,
+    info: DeviceInfo,
+}
+
+impl<SPI, HRDY, CS, RESET> IT8951<SPI, HRDY, CS, RESET>
+where
+    SPI: SpiTransfer,
+    HRDY: InputPin,
+    CS: OutputPin,
+    RESET: OutputPin,
+{
+    /// Resets and initializes the device, returning it as [`Ready`].
+    ///
+    /// See [`init`](Self::init).
+    pub fn into_ready(mut self) -> Result<Ready<SPI, HRDY, CS, RESET>> {
+        self.init()?;
+        Ready::try_from(self)
+    }
+
+    /// Attaches to a running controller without a reset, returning the
+    /// device as [`Ready`].
+    ///
+    /// See [`attach`](Self::attach).
+    pub fn into_attached(mut self) -> Result<Ready<SPI, HRDY, CS, RESET>> {
+        self.attach()?;
+        Ready::try_from(self)
+    }
+}
+
+impl<SPI, HRDY, CS, RESET> TryFrom<IT8951<SPI, HRDY, CS, RESET>> for Ready<SPI, HRDY, CS, RESET> {
+    type Error = Error;
+
+    /// Wraps a device whose device information has been read, without
+    /// talking to the controller.
+    fn try_from(device: IT8951<SPI, HRDY, CS, RESET>) -> Result<Self> {
+        let info = device
+            .device_info
+            .clone()
+            .ok_or_else(|| Error::Init("Device not initialized".to_string()))?;
+        Ok(Self { device, info })
+    }
+}
+
+impl<SPI, HRDY, CS, RESET> Ready<SPI, HRDY, CS, RESET> {
+    /// Returns the device information read during initialization.
+    pub fn device_info(&self) -> &DeviceInfo {
+        &self.info
+    }
+
+    /// Returns the display width in pixels, as seen with the current rotation.
+    pub fn width(&self) -> u16 {
+        if self.device.rotation.is_portrait() {
+            self.panel_height()
+        } else {
+            self.panel_width()
+        }
+    }
+
+    /// Returns the display height in pixels, as seen with the current rotation.
+    pub fn height(&self) -> u16 {
+        if self.device.rotation.is_portrait() {
+            self.panel_width()
+        } else {
+            self.panel_height()
+        }
+    }
+
+    /// Returns the physical panel width in pixels.
+    pub fn panel_width(&self) -> u16 {
+        self.info.panel_width
+    }
+
+    /// Returns the physical panel height in pixels.
+    pub fn panel_height(&self) -> u16 {
+        self.info.panel_height
+    }
+
+    /// Returns the image buffer base address.
+    pub fn img_buf_addr(&self) -> u32 {
+        self.info.img_buf_addr
+    }
+
+    /// Returns the underlying device.
+    pub fn into_inner(self) -> IT8951<SPI, HRDY, CS, RESET> {
+        self.device
+    }
+}
+
+impl<SPI, HRDY, CS, RESET> Ready<SPI, HRDY, CS, RESET>
+where
+    SPI: SpiTransfer,
+    HRDY: InputPin,
+    CS: OutputPin,
+    RESET: OutputPin,
+{
+    /// Runs an operation on the device and picks up device information
+    /// read again during it, such as by a recovery.
+    fn forward<T>(
+        &mut self,
+        op: impl FnOnce(&mut IT8951<SPI, HRDY, CS, RESET>) -> Result<T>,
+    ) -> Result<T> {
+        let result = op(&mut self.device);
+        if let Some(info) = &self.device.device_info {
+            if *info != self.info {
+                self.info = info.clone();
+            }
+        }
+        result
+    }
+
+    /// Sets the display rotation. See [`IT8951::set_rotation`].
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        self.device.set_rotation(rotation);
+    }
+
+    /// Sets the host byte order of image data. See [`IT8951::set_endian`].
+    pub fn set_endian(&mut self, endian: Endian) {
+        self.device.set_endian(endian);
+    }
+
+    /// Resets and initializes the device again. See [`IT8951::init`].
+    pub fn init(&mut self) -> Result<()> {
+        self.forward(|device| device.init())
+    }
+
+    /// Attaches to the running controller again. See [`IT8951::attach`].
+    pub fn attach(&mut self) -> Result<()> {
+        self.forward(|device| device.attach())
+    }
+
+    /// Recovers the controller. See [`IT8951::recover`].
+    pub fn recover(&mut self) -> Result<()> {
+        self.forward(|device| device.recover())
+    }
+
+    /// Clears the image buffer. See [`IT8951::clear`].
+    pub fn clear(&mut self, value: u8) -> Result<()> {
+        self.forward(|device| device.clear(value))
+    }
+
+    /// Fills an area of the image buffer. See [`IT8951::fill_area`].
+    pub fn fill_area(&mut self, area: &Area, value: u8) -> Result<()> {
+        self.forward(|device| device.fill_area(area, value))
+    }
+
+    /// Refreshes the whole display. See [`IT8951::refresh`].
+    pub fn refresh(&mut self, mode: DisplayMode) -> Result<()> {
+        self.forward(|device| device.refresh(mode))
+    }
+
+    /// Refreshes an area of the display. See [`IT8951::refresh_area`].
+    pub fn refresh_area(&mut self, area: &Area, mode: DisplayMode) -> Result<()> {
+        self.forward(|device| device.refresh_area(area, mode))
+    }
+
+    /// Refreshes an area with a named waveform mode. See
+    /// [`IT8951::refresh_area_named`].
+    pub fn refresh_area_named(&mut self, area: &Area, mode: ModeName) -> Result<()> {
+        self.forward(|device| device.refresh_area_named(area, mode))
+    }
+
+    /// Refreshes an area in 1bpp mode. See [`IT8951::refresh_area_1bpp`].
+    pub fn refresh_area_1bpp(
+        &mut self,
+        area: &Area,
+        mode: DisplayMode,
+        foreground: u8,
+        background: u8,
+    ) -> Result<()> {
+        self.forward(|device| device.refresh_area_1bpp(area, mode, foreground, background))
+    }
+
+    /// Fills and refreshes an area. See [`IT8951::fill_and_refresh_area`].
+    pub fn fill_and_refresh_area(
+        &mut self,
+        area: &Area,
+        value: u8,
+        mode: DisplayMode,
+    ) -> Result<()> {
+        self.forward(|device| device.fill_and_refresh_area(area, value, mode))
+    }
+
+    /// Clears and refreshes the whole display. See
+    /// [`IT8951::clear_and_refresh`].
+    pub fn clear_and_refresh(&mut self, value: u8, mode: DisplayMode) -> Result<()> {
+        self.forward(|device| device.clear_and_refresh(value, mode))
+    }
+
+    /// Loads packed image data. See [`IT8951::load_image`].
+    pub fn load_image(&mut self, data: &[u8], area: &Area, format: PixelFormat) -> Result<()> {
+        self.forward(|device| device.load_image(data, area, format))
+    }
+
+    /// Loads an image row by row. See [`IT8951::load_image_stream`].
+    pub fn load_image_stream<'a, I>(
+        &mut self,
+        area: &Area,
+        format: PixelFormat,
+        rows: I,
+    ) -> Result<()>
+    where
+        I: IntoIterator<Item = &'a [u8]>,
+    {
+        self.forward(|device| device.load_image_stream(area, format, rows))
+    }
+
+    /// Loads an image from a reader. See [`IT8951::load_image_reader`].
+    pub fn load_image_reader<R: Read>(
+        &mut self,
+        area: &Area,
+        format: PixelFormat,
+        reader: R,
+    ) -> Result<()> {
+        self.forward(|device| device.load_image_reader(area, format, reader))
+    }
+
+    /// Loads a region of a larger image. See [`IT8951::load_image_strided`].
+    pub fn load_image_strided(
+        &mut self,
+        data: &[u8],
+        stride: usize,
+        src_rect: &Area,
+        dst_area: &Area,
+        format: PixelFormat,
+    ) -> Result<()> {
+        self.forward(|device| device.load_image_strided(data, stride, src_rect, dst_area, format))
+    }
+
+    /// Draws an area of a framebuffer. See [`IT8951::draw_framebuffer`].
+    pub fn draw_framebuffer(
+        &mut self,
+        framebuffer: &Framebuffer,
+        area: &Area,
+        refresh: bool,
+        mode: DisplayMode,
+    ) -> Result<()> {
+        self.forward(|device| device.draw_framebuffer(framebuffer, area, refresh, mode))
+    }
+
+    /// Draws a framebuffer region at another position. See
+    /// [`IT8951::draw_framebuffer_region`].
+    pub fn draw_framebuffer_region(
+        &mut self,
+        framebuffer: &Framebuffer,
+        src_rect: &Area,
+        dst: (u16, u16),
+        refresh: bool,
+        mode: DisplayMode,
+    ) -> Result<()> {
+        self.forward(|device| {
+            device.draw_framebuffer_region(framebuffer, src_rect, dst, refresh, mode)
+        })
+    }
+
+    /// Draws a whole framebuffer and refreshes the display. See
+    /// [`IT8951::draw_framebuffer_full`].
+    pub fn draw_framebuffer_full(
+        &mut self,
+        framebuffer: &Framebuffer,
+        mode: DisplayMode,
+    ) -> Result<()> {
+        self.forward(|device| device.draw_framebuffer_full(framebuffer, mode))
+    }
+
+    /// Waits until the display is ready. See [`IT8951::wait_display_ready`].
+    pub fn wait_display_ready(&mut self) -> Result<()> {
+        self.forward(|device| device.wait_display_ready())
+    }
+
+    /// Puts the controller into normal operation. See [`IT8951::run`].
+    pub fn run(&mut self) -> Result<()> {
+        self.forward(|device| device.run())
+    }
+
+    /// Puts the controller into standby. See [`IT8951::standby`].
+    pub fn standby(&mut self) -> Result<()> {
+        self.forward(|device| device.standby())
+    }
+
+    /// Puts the controller to sleep. See [`IT8951::sleep`].
+    pub fn sleep(&mut self) -> Result<()> {
+        self.forward(|device| device.sleep())
+    }
+}
+
+impl<SPI, HRDY, CS, RESET> Deref for Ready<SPI, HRDY, CS, RESET> {
+    type Target = IT8951<SPI, HRDY, CS, RESET>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.device
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::mock::{MockController, MockInputPin, MockOutputPin};
+    use crate::hal::PinState;
+    use crate::types::{DisplayMode, Rotation};
+
+    fn device(
+        controller: &MockController,
+    ) -> IT8951<MockController, MockInputPin, MockOutputPin, MockOutputPin> {
+        IT8951::new(
+            controller.clone(),
+            MockInputPin::new(PinState::High),
+            MockOutputPin::new(PinState::High),
+            MockOutputPin::new(PinState::High),
+            1500,
+        )
+    }
+
+    #[test]
+    fn test_into_ready() {
+        let controller = MockController::new();
+        let mut display = device(&controller).into_ready().unwrap();
+
+        assert_eq!((display.width(), display.height()), (800, 600));
+        display.set_rotation(Rotation::Rotate90);
+        assert_eq!((display.width(), display.height()), (600, 800));
+        assert_eq!(display.img_buf_addr(), display.device_info().img_buf_addr);
+
+        display.clear(0xFF).unwrap();
+        display.refresh(DisplayMode::Gc16).unwrap();
+        assert!(display.into_inner().device_info().is_some());
+    }
+
+    #[test]
+    fn test_info_follows_reinit() {
+        let controller = MockController::new();
+        let mut display = device(&controller).into_ready().unwrap();
+
+        controller.set_device_info(1872, 1404, 0x001236E0, "SWv_0.2.1", "M841_TFA5210");
+        display.init().unwrap();
+        assert_eq!((display.width(), display.height()), (1872, 1404));
+        assert_eq!(display.device_info().panel_width, 1872);
+    }
+
+    #[test]
+    fn test_try_from_requires_device_info() {
+        let controller = MockController::new();
+        assert!(matches!(
+            Ready::try_from(device(&controller)),
+            Err(Error::Init(_))
+        ));
+
+        let display = device(&controller).into_ready().unwrap();
+        let display = Ready::try_from(display.into_inner()).unwrap();
+        assert_eq!(display.panel_width(), 800);
+    }
+
+    #[test]
+    fn test_into_attached() {
+        let controller = MockController::new();
+        let display = device(&controller).into_attached().unwrap();
+        assert_eq!(display.panel_width(), 800);
+        assert_eq!(display.panel_height(), 600);
+    }
+}
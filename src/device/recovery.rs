@@ -0,0 +1,104 @@
+//! Recovery from transient transport failures.
+
+use std::time::Duration;
+
+/// Policy for recovering from transient transport failures.
+///
+/// When an operation fails with a retryable error (see
+/// [`Error::is_retryable`](crate::Error::is_retryable)), the device is
+/// recovered (see [`IT8951::recover`](crate::IT8951::recover)), then the
+/// operation is retried after a backoff delay. The delay doubles on every
+/// attempt, up to `max_backoff`.
+///
+/// The default policy performs no retries.
+///
+/// # Examples
+///
+/// ```
+/// use it8951::RecoveryPolicy;
+/// use std::time::Duration;
+///
+/// let policy = RecoveryPolicy::new(3)
+///     .initial_backoff(Duration::from_millis(200))
+///     .max_backoff(Duration::from_secs(5));
+///
+/// assert_eq!(policy.backoff(0), Duration::from_millis(200));
+/// assert_eq!(policy.backoff(1), Duration::from_millis(400));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecoveryPolicy {
+    /// Maximum number of retries after the initial failure
+    pub max_retries: u32,
+
+    /// Delay before the first retry
+    pub initial_backoff: Duration,
+
+    /// Upper bound for the delay between retries
+    pub max_backoff: Duration,
+}
+
+impl RecoveryPolicy {
+    /// Creates a policy that retries up to `max_retries` times.
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+        }
+    }
+
+    /// Creates a policy that never retries.
+    pub fn disabled() -> Self {
+        Self::new(0)
+    }
+
+    /// Sets the delay before the first retry.
+    pub fn initial_backoff(mut self, delay: Duration) -> Self {
+        self.initial_backoff = delay;
+        self
+    }
+
+    /// Sets the upper bound for the delay between retries.
+    pub fn max_backoff(mut self, delay: Duration) -> Self {
+        self.max_backoff = delay;
+        self
+    }
+
+    /// Returns the delay before the given retry attempt (starting at 0).
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempt).unwrap_or(u32::MAX);
+        self.initial_backoff
+            .checked_mul(factor)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+    }
+}
+
+impl Default for RecoveryPolicy {
+    fn default() -> Self {
+        Self::disabled()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_is_disabled() {
+        assert_eq!(RecoveryPolicy::default().max_retries, 0);
+    }
+
+    #[test]
+    fn test_backoff_doubles_and_caps() {
+        let policy = RecoveryPolicy::new(10)
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_millis(500));
+
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff(2), Duration::from_millis(400));
+        assert_eq!(policy.backoff(3), Duration::from_millis(500));
+        assert_eq!(policy.backoff(40), Duration::from_millis(500));
+    }
+}
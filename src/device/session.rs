@@ -0,0 +1,220 @@
+//! Powering the panel down on shutdown, including on panic.
+
+use crate::device::{PowerState, IT8951};
+use crate::error::{Error, Result};
+use crate::hal::{InputPin, OutputPin, SpiTransfer};
+use std::ops::{Deref, DerefMut};
+
+/// Guard that powers the controller down when dropped.
+///
+/// Created by [`IT8951::session`]. The guard dereferences to the device, so
+/// it can be used in its place. When it goes out of scope, including while
+/// unwinding from a panic, it waits for running updates to finish (bounded
+/// by the ready timeout) and then enters standby or sleep, so the panel's
+/// high voltage is not left on. Errors during the drop are logged; call
+/// [`finish`](Self::finish) to observe them.
+///
+/// # Examples
+///
+/// ```ignore
+/// let mut display = display.session(PowerState::Sleep)?;
+/// display.clear(0xFF)?;
+/// display.refresh(DisplayMode::Gc16)?;
+/// // Powered down here, even if the code above panicked
+/// ```
+#[derive(Debug)]
+pub struct Session<'a, SPI, HRDY, CS, RESET>
+where
+    SPI: SpiTransfer,
+    HRDY: InputPin,
+    CS: OutputPin,
+    RESET: OutputPin,
+{
+    device: &'a mut IT8951<SPI, HRDY, CS, RESET>,
+    state: PowerState,
+    finished: bool,
+}
+
+impl<SPI, HRDY, CS, RESET> Session<'_, SPI, HRDY, CS, RESET>
+where
+    SPI: SpiTransfer,
+    HRDY: InputPin,
+    CS: OutputPin,
+    RESET: OutputPin,
+{
+    /// Ends the session, powering the controller down and returning any
+    /// error.
+    pub fn finish(mut self) -> Result<()> {
+        self.finished = true;
+        self.device.power_down(self.state)
+    }
+}
+
+impl<SPI, HRDY, CS, RESET> Deref for Session<'_, SPI, HRDY, CS, RESET>
+where
+    SPI: SpiTransfer,
+    HRDY: InputPin,
+    CS: OutputPin,
+    RESET: OutputPin,
+{
+    type Target = IT8951<SPI, HRDY, CS, RESET>;
+
+    fn deref(&self) -> &Self::Target {
+        self.device
+    }
+}
+
+impl<SPI, HRDY, CS, RESET> DerefMut for Session<'_, SPI, HRDY, CS, RESET>
+where
+    SPI: SpiTransfer,
+    HRDY: InputPin,
+    CS: OutputPin,
+    RESET: OutputPin,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.device
+    }
+}
+
+impl<SPI, HRDY, CS, RESET> Drop for Session<'_, SPI, HRDY, CS, RESET>
+where
+    SPI: SpiTransfer,
+    HRDY: InputPin,
+    CS: OutputPin,
+    RESET: OutputPin,
+{
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        if let Err(e) = self.device.power_down(self.state) {
+            log::warn!("IT8951 power down on drop failed: {}", e);
+        }
+    }
+}
+
+impl<SPI, HRDY, CS, RESET> IT8951<SPI, HRDY, CS, RESET>
+where
+    SPI: SpiTransfer,
+    HRDY: InputPin,
+    CS: OutputPin,
+    RESET: OutputPin,
+{
+    /// Starts a session that enters `state` when it ends.
+    ///
+    /// See [`Session`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidParameter` if `state` is `Active`.
+    pub fn session(&mut self, state: PowerState) -> Result<Session<'_, SPI, HRDY, CS, RESET>> {
+        if state == PowerState::Active {
+            return Err(Error::InvalidParameter(
+                "session must end in Standby or Sleep",
+            ));
+        }
+        Ok(Session {
+            device: self,
+            state,
+            finished: false,
+        })
+    }
+
+    /// Waits for running updates to finish, then puts the controller to
+    /// sleep.
+    ///
+    /// The wait is bounded by the ready timeout. The controller is put to
+    /// sleep even if the wait fails, and the first error is returned.
+    pub fn shutdown(&mut self) -> Result<()> {
+        self.power_down(PowerState::Sleep)
+    }
+
+    /// Waits for the LUT engines, then enters `state`.
+    fn power_down(&mut self, state: PowerState) -> Result<()> {
+        let current = self.power_state();
+        if current == state {
+            return Ok(());
+        }
+        if current != PowerState::Active {
+            // No update can be running while powered down
+            return self.enter_power_state(state);
+        }
+        let waited = self.wait_display_ready();
+        let entered = self.enter_power_state(state);
+        waited.and(entered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::mock::{MockController, MockInputPin, MockOutputPin};
+    use crate::hal::PinState;
+    use crate::protocol::decode::{decode, Operation};
+    use crate::protocol::{Command, Register};
+
+    fn setup() -> (
+        MockController,
+        IT8951<MockController, MockInputPin, MockOutputPin, MockOutputPin>,
+    ) {
+        let controller = MockController::new();
+        let device = IT8951::new(
+            controller.clone(),
+            MockInputPin::new(PinState::High),
+            MockOutputPin::new(PinState::High),
+            MockOutputPin::new(PinState::High),
+            1500,
+        );
+        (controller, device)
+    }
+
+    fn last_command(controller: &MockController) -> Option<Command> {
+        decode(&controller.get_transfers())
+            .into_iter()
+            .rev()
+            .find_map(|op| match op {
+                Operation::Command { command, .. } => Some(command),
+                _ => None,
+            })
+    }
+
+    #[test]
+    fn test_session_powers_down_on_panic() {
+        let (controller, mut device) = setup();
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _session = device.session(PowerState::Standby).unwrap();
+            panic!("refresh failed");
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(last_command(&controller), Some(Command::Standby));
+        assert_eq!(device.power_state(), PowerState::Standby);
+    }
+
+    #[test]
+    fn test_session_finish() {
+        let (controller, mut device) = setup();
+
+        let mut session = device.session(PowerState::Sleep).unwrap();
+        session.run().unwrap();
+        session.finish().unwrap();
+
+        assert_eq!(last_command(&controller), Some(Command::Sleep));
+        assert!(matches!(
+            device.session(PowerState::Active),
+            Err(Error::InvalidParameter(_))
+        ));
+    }
+
+    #[test]
+    fn test_shutdown_sleeps_after_lut_timeout() {
+        let (controller, mut device) = setup();
+        controller.set_register(Register::LUTAFSR, 0x0001);
+        device.transport.set_timeout(std::time::Duration::from_millis(5));
+
+        assert!(matches!(device.shutdown(), Err(Error::Timeout(5))));
+        assert_eq!(last_command(&controller), Some(Command::Sleep));
+        assert_eq!(device.power_state(), PowerState::Sleep);
+    }
+}
@@ -0,0 +1,350 @@
+//! Expanding areas to the controller's pixel alignment.
+//!
+//! Some panels and firmware only update whole groups of pixels: the x
+//! coordinate and width of loads and updates must be multiples of
+//! [`Capabilities::pixel_alignment`](crate::Capabilities::pixel_alignment),
+//! or of `pixel_alignment_1bpp` for 1bpp updates, or the edges of the area
+//! come out corrupted. With alignment enabled, areas are widened to the
+//! surrounding aligned panel columns. Refreshing extra columns only redraws
+//! what is already in the image buffer, but loads have to supply pixels
+//! for them, which are taken from the [`AlignPadding`] source. The
+//! alignment applies to the panel's x axis, so with 90 and 270 degree
+//! rotations a load gains extra rows of the rotated view instead.
+
+use crate::device::IT8951;
+use crate::error::Result;
+use crate::hal::{InputPin, OutputPin, SpiTransfer};
+use crate::pixel;
+use crate::types::{Area, PixelFormat};
+
+/// Source of the pixels added when a load is widened for alignment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlignPadding {
+    /// Read the extra columns back from the controller's image buffer, so
+    /// they keep their current content
+    Shadow,
+
+    /// Fill the extra columns with a gray value (0x00 = black, 0xFF = white)
+    Fill(u8),
+}
+
+/// Widens the span `[start, start + len)` to multiples of `alignment`,
+/// without passing `limit`.
+fn align_span(start: u16, len: u16, alignment: u16, limit: u16) -> (u16, u16) {
+    if alignment <= 1 {
+        return (start, len);
+    }
+    let alignment = alignment as u32;
+    let first = start as u32 / alignment * alignment;
+    let end = (start as u32 + len as u32 + alignment - 1) / alignment * alignment;
+    let end = end.min(limit as u32);
+    (first as u16, (end - first) as u16)
+}
+
+/// Padding rows and columns of a widened load.
+pub(crate) struct RowPadding {
+    format: PixelFormat,
+    top: Vec<Vec<u8>>,
+    left: Vec<Vec<u8>>,
+    right: Vec<Vec<u8>>,
+    bottom: Vec<Vec<u8>>,
+}
+
+impl RowPadding {
+    /// Returns the source row shown in row `y` of the widened area, or
+    /// `None` for a padding row.
+    pub(crate) fn source_row(&self, y: usize) -> Option<usize> {
+        y.checked_sub(self.top.len())
+            .filter(|&row| row < self.left.len())
+    }
+
+    /// Packs row `y` of the widened area into `dst`, taking the pixels of
+    /// a source row from `src`, which holds `width` pixels.
+    pub(crate) fn pad_row(&self, y: usize, src: &[u8], width: u16, dst: &mut [u8]) {
+        let slots = match self.source_row(y) {
+            Some(row) => {
+                let mut slots = self.left[row].clone();
+                slots.extend(pixel::row_slots(src, width, self.format));
+                slots.extend_from_slice(&self.right[row]);
+                slots
+            }
+            None if y < self.top.len() => self.top[y].clone(),
+            None => self.bottom[y - self.top.len() - self.left.len()].clone(),
+        };
+        pixel::pack_slots_into(&slots, self.format, dst);
+    }
+}
+
+impl<SPI, HRDY, CS, RESET> IT8951<SPI, HRDY, CS, RESET>
+where
+    SPI: SpiTransfer,
+    HRDY: InputPin,
+    CS: OutputPin,
+    RESET: OutputPin,
+{
+    /// Enables or disables automatic area alignment.
+    ///
+    /// When enabled, refreshes and loads whose x or width is not a multiple
+    /// of the required alignment (see [`capabilities`](Self::capabilities))
+    /// are widened, and loaded images are padded from `padding`. Pixels
+    /// outside the requested area are not changed with
+    /// [`AlignPadding::Shadow`]. Alignment is disabled by default.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// display.set_area_alignment(Some(AlignPadding::Shadow));
+    /// // Loads columns 0..8 and refreshes columns 0..32
+    /// display.load_image(&packed, &Area::new(3, 0, 5, 10), PixelFormat::Bpp8)?;
+    /// display.refresh_area_1bpp(&Area::new(3, 0, 5, 10), DisplayMode::A2, 0x00, 0xFF)?;
+    /// ```
+    pub fn set_area_alignment(&mut self, padding: Option<AlignPadding>) {
+        self.align = padding;
+    }
+
+    /// Gets the padding used for automatic area alignment, if enabled.
+    pub fn area_alignment(&self) -> Option<AlignPadding> {
+        self.align
+    }
+
+    /// Widens a refresh area in panel coordinates if alignment is enabled.
+    pub(crate) fn align_refresh_area(&self, area: Area, one_bpp: bool) -> Area {
+        if self.align.is_none() {
+            return area;
+        }
+        let caps = self.capabilities();
+        let alignment = if one_bpp {
+            caps.pixel_alignment_1bpp
+        } else {
+            caps.pixel_alignment
+        };
+        let (x, width) = align_span(area.x, area.width, alignment, self.info().panel_width);
+        Area::new(x, area.y, width, area.height)
+    }
+
+    /// Returns the widened area for a load in rotated coordinates, or
+    /// `None` if the area needs no widening.
+    ///
+    /// The area is aligned in panel coordinates and mapped back.
+    pub(crate) fn aligned_load_area(&self, area: &Area) -> Result<Option<Area>> {
+        if self.align.is_none() {
+            return Ok(None);
+        }
+        let physical = self.physical_area(area)?;
+        let info = self.info();
+        let (panel_w, panel_h) = (info.panel_width, info.panel_height);
+        let alignment = self.capabilities().pixel_alignment;
+        let (x, width) = align_span(physical.x, physical.width, alignment, panel_w);
+        if (x, width) == (physical.x, physical.width) {
+            return Ok(None);
+        }
+
+        let aligned = Area::new(x, physical.y, width, physical.height);
+        let logical = aligned.to_logical(self.rotation, panel_w, panel_h);
+        let (width, height) = self.display_size()?;
+        Ok(Some(self.mirror_area(&logical, width, height)))
+    }
+
+    /// Collects the padding for widening `area` to `aligned`.
+    pub(crate) fn load_padding(
+        &mut self,
+        area: &Area,
+        aligned: &Area,
+        format: PixelFormat,
+    ) -> Result<RowPadding> {
+        let top = Area::new(aligned.x, aligned.y, aligned.width, area.y - aligned.y);
+        let left = Area::new(aligned.x, area.y, area.x - aligned.x, area.height);
+        let right = Area::new(
+            area.right(),
+            area.y,
+            aligned.right() - area.right(),
+            area.height,
+        );
+        let bottom = Area::new(
+            aligned.x,
+            area.bottom(),
+            aligned.width,
+            aligned.bottom() - area.bottom(),
+        );
+
+        let mut columns = |strip: Area| -> Result<Vec<Vec<u8>>> {
+            match self.align {
+                _ if strip.width == 0 || strip.height == 0 => {
+                    Ok(vec![Vec::new(); strip.height as usize])
+                }
+                Some(AlignPadding::Fill(value)) => {
+                    let slot = pixel::quantize(value, format);
+                    Ok(vec![vec![slot; strip.width as usize]; strip.height as usize])
+                }
+                _ => self.read_back(&strip, format),
+            }
+        };
+
+        Ok(RowPadding {
+            format,
+            top: columns(top)?,
+            left: columns(left)?,
+            right: columns(right)?,
+            bottom: columns(bottom)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::mock::{mock_device, MockController, IMG_BUF_ADDR};
+    use crate::protocol::decode::{decode, Operation};
+    use crate::types::{DisplayMode, Rotation};
+
+    /// Returns the areas of the image loads sent to the controller.
+    fn loads(controller: &MockController) -> Vec<Area> {
+        decode(&controller.get_transfers())
+            .into_iter()
+            .filter_map(|op| match op {
+                Operation::LoadImageArea { area, .. } => Some(area),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Reads the first 8 pixels of a panel row.
+    fn row(controller: &MockController, y: u32) -> Vec<u8> {
+        controller.memory(IMG_BUF_ADDR + y * 800, 8)
+    }
+
+    #[test]
+    fn test_align_span() {
+        assert_eq!(align_span(3, 5, 4, 800), (0, 8));
+        assert_eq!(align_span(4, 4, 4, 800), (4, 4));
+        assert_eq!(align_span(33, 1, 32, 800), (32, 32));
+        assert_eq!(align_span(790, 9, 32, 800), (768, 32));
+        assert_eq!(align_span(3, 5, 1, 800), (3, 5));
+    }
+
+    #[test]
+    fn test_load_padded_from_shadow() {
+        let (controller, mut device) = mock_device();
+        let background: Vec<u8> = (1..=16).collect();
+        let packed = pixel::pack(&background, 8, 2, PixelFormat::Bpp8).unwrap();
+        device
+            .load_image(&packed, &Area::new(0, 0, 8, 2), PixelFormat::Bpp8)
+            .unwrap();
+
+        device.set_area_alignment(Some(AlignPadding::Shadow));
+        let packed = pixel::pack(&[0xA0; 10], 5, 2, PixelFormat::Bpp8).unwrap();
+        device
+            .load_image(&packed, &Area::new(3, 0, 5, 2), PixelFormat::Bpp8)
+            .unwrap();
+
+        assert_eq!(row(&controller, 0), [1, 2, 3, 0xA0, 0xA0, 0xA0, 0xA0, 0xA0]);
+        assert_eq!(row(&controller, 1), [9, 10, 11, 0xA0, 0xA0, 0xA0, 0xA0, 0xA0]);
+    }
+
+    #[test]
+    fn test_rotated_load_aligned_on_panel() {
+        let (controller, mut device) = mock_device();
+        device.set_rotation(Rotation::Rotate270);
+
+        // Panel columns 0..8 of rows 597 and 598
+        let background: Vec<u8> = (1..=16).collect();
+        let packed = pixel::pack(&background, 2, 8, PixelFormat::Bpp8).unwrap();
+        device
+            .load_image(&packed, &Area::new(1, 0, 2, 8), PixelFormat::Bpp8)
+            .unwrap();
+
+        // Logical rows 3..5 are panel columns 3..5, widened to 0..8
+        device.set_area_alignment(Some(AlignPadding::Shadow));
+        assert_eq!(
+            device.aligned_load_area(&Area::new(1, 3, 2, 2)).unwrap(),
+            Some(Area::new(1, 0, 2, 8))
+        );
+        let packed = pixel::pack(&[0xA0; 4], 2, 2, PixelFormat::Bpp8).unwrap();
+        device
+            .load_image(&packed, &Area::new(1, 3, 2, 2), PixelFormat::Bpp8)
+            .unwrap();
+
+        assert_eq!(row(&controller, 597), [2, 4, 6, 0xA0, 0xA0, 12, 14, 16]);
+        assert_eq!(row(&controller, 598), [1, 3, 5, 0xA0, 0xA0, 11, 13, 15]);
+    }
+
+    #[test]
+    fn test_rotated_stream_padded_with_fill() {
+        let (controller, mut device) = mock_device();
+        device.set_rotation(Rotation::Rotate90);
+        device.set_area_alignment(Some(AlignPadding::Fill(0xFF)));
+
+        // Logical rows 790..795 are panel columns 5..10
+        let rows = [[0x10u8; 3]; 5];
+        let area = Area::new(2, 790, 3, 5);
+        device
+            .load_image_stream(&area, PixelFormat::Bpp8, rows.iter().map(|r| &r[..]))
+            .unwrap();
+
+        let expected = [0xFF, 0x10, 0x10, 0x10, 0x10, 0x10, 0xFF, 0xFF];
+        assert_eq!(controller.memory(IMG_BUF_ADDR + 2 * 800 + 4, 8), expected);
+        assert_eq!(controller.memory(IMG_BUF_ADDR + 4 * 800 + 4, 8), expected);
+    }
+
+    #[test]
+    fn test_stream_padded_with_fill() {
+        let (controller, mut device) = mock_device();
+        device.set_area_alignment(Some(AlignPadding::Fill(0xFF)));
+
+        let rows = [[0x00u8; 2], [0x10; 2]];
+        let area = Area::new(1, 0, 2, 2);
+        device
+            .load_image_stream(&area, PixelFormat::Bpp4, rows.iter().map(|r| &r[..]))
+            .unwrap();
+
+        assert_eq!(row(&controller, 0)[..4], [0xF0, 0x00, 0x00, 0xF0]);
+        assert_eq!(row(&controller, 1)[..4], [0xF0, 0x10, 0x10, 0xF0]);
+    }
+
+    #[test]
+    fn test_refresh_area_widened() {
+        let (controller, mut device) = mock_device();
+        device.set_area_alignment(Some(AlignPadding::Shadow));
+
+        device
+            .refresh_area(&Area::new(3, 10, 5, 5), DisplayMode::Du)
+            .unwrap();
+        device
+            .refresh_area_1bpp(&Area::new(3, 10, 5, 5), DisplayMode::A2, 0x00, 0xFF)
+            .unwrap();
+
+        let ops: Vec<String> = decode(&controller.get_transfers())
+            .iter()
+            .map(|op| op.to_string())
+            .filter(|op| op.starts_with("DisplayArea"))
+            .collect();
+        assert_eq!(ops[0], "DisplayArea x=0 y=10 w=8 h=5 mode=1");
+        assert!(ops[1].contains("x=0 y=10 w=32 h=5"), "{}", ops[1]);
+    }
+
+    #[test]
+    fn test_hardware_fill_skipped_for_unaligned_area() {
+        let (controller, mut device) = mock_device();
+        device.set_area_alignment(Some(AlignPadding::Shadow));
+
+        device
+            .fill_and_refresh_area(&Area::new(3, 0, 5, 2), 0x80, DisplayMode::Du)
+            .unwrap();
+
+        assert_eq!(device.hardware_fill_supported(), None);
+        assert_eq!(loads(&controller), [Area::new(0, 0, 8, 2)]);
+        assert_eq!(row(&controller, 0), [0, 0, 0, 0x80, 0x80, 0x80, 0x80, 0x80]);
+    }
+
+    #[test]
+    fn test_pending_fill_widened_when_written() {
+        let (controller, mut device) = mock_device();
+        device.fill_area(&Area::new(3, 0, 5, 2), 0x80).unwrap();
+        device.set_area_alignment(Some(AlignPadding::Fill(0xFF)));
+
+        device.read_memory(IMG_BUF_ADDR, 8).unwrap();
+
+        assert_eq!(loads(&controller), [Area::new(0, 0, 8, 2)]);
+        assert_eq!(row(&controller, 1), [0xFF, 0xFF, 0xFF, 0x80, 0x80, 0x80, 0x80, 0x80]);
+    }
+}
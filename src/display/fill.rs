@@ -0,0 +1,294 @@
+//! Hardware-accelerated fills.
+//!
+//! The fill rectangle engine paints a solid value during a display update,
+//! without reading the image buffer and without writing to it. A fill is
+//! therefore recorded instead of uploaded: a later refresh that lies inside
+//! the filled area is drawn by the engine, and the image buffer is only
+//! written once an operation depends on its contents, such as a refresh
+//! reaching past the filled area, a load that partly overlaps it or a read
+//! of the buffer memory. A load that covers a pending fill replaces it, so
+//! clearing the panel before drawing a full frame sends no fill pixels.
+
+use super::{UP1SR_FILL_ENABLE, UP1SR_HIGH};
+use crate::device::IT8951;
+use crate::error::Result;
+use crate::hal::{InputPin, OutputPin, SpiTransfer};
+use crate::pixel;
+use crate::protocol::{Command, Register, UserCommand};
+use crate::types::{Area, Endian, LoadImageInfo, PixelFormat, Rotation};
+
+/// A fill not yet written to the image buffer, in panel coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PendingFill {
+    area: Area,
+    value: u8,
+}
+
+/// Returns whether `outer` contains all of `inner`.
+fn covers(outer: &Area, inner: &Area) -> bool {
+    outer.intersect(inner) == Some(*inner)
+}
+
+impl<SPI, HRDY, CS, RESET> IT8951<SPI, HRDY, CS, RESET>
+where
+    SPI: SpiTransfer,
+    HRDY: InputPin,
+    CS: OutputPin,
+    RESET: OutputPin,
+{
+    /// Records a fill of a validated area in rotated coordinates, returning
+    /// `false` if the firmware cannot draw it with the fill engine.
+    ///
+    /// Areas that alignment would widen are not recorded, as the widened
+    /// refresh would reach past the fill.
+    pub(crate) fn defer_fill(&mut self, area: &Area, value: u8) -> Result<bool> {
+        if !self.capabilities().hardware_fill || self.hardware_fill == Some(false) {
+            return Ok(false);
+        }
+        if self.aligned_load_area(area)?.is_some() {
+            return Ok(false);
+        }
+
+        let area = self.physical_area(area)?;
+        self.pending_fills.retain(|fill| !covers(&area, &fill.area));
+        self.pending_fills.push(PendingFill { area, value });
+        self.buffer_written(&area);
+        Ok(true)
+    }
+
+    /// Draws a refresh of a panel area with the fill engine if a pending
+    /// fill covers it, returning `false` if the refresh must use the image
+    /// buffer.
+    ///
+    /// Pending fills that overlap the area without covering it are written
+    /// to the image buffer first.
+    pub(crate) fn refresh_pending_fill(&mut self, area: &Area, mode: u16) -> Result<bool> {
+        // Later fills paint over earlier ones
+        let latest = self
+            .pending_fills
+            .iter()
+            .rev()
+            .find(|fill| fill.area.intersect(area).is_some())
+            .copied();
+        let Some(fill) = latest else {
+            return Ok(false);
+        };
+
+        if covers(&fill.area, area) && self.hardware_fill_update(area, fill.value, mode)? {
+            return Ok(true);
+        }
+        self.flush_fills()?;
+        Ok(false)
+    }
+
+    /// Prepares the pending fills for a load of a panel area.
+    ///
+    /// Fills the load covers are dropped, since their pixels are about to
+    /// be replaced. If any others overlap the area, all pending fills are
+    /// written, as the load only replaces part of them.
+    pub(crate) fn settle_fills_for_load(&mut self, area: &Area) -> Result<()> {
+        self.pending_fills.retain(|fill| !covers(area, &fill.area));
+        self.flush_fills_overlapping(area)
+    }
+
+    /// Writes the pending fills if any of them overlaps a panel area.
+    pub(crate) fn flush_fills_overlapping(&mut self, area: &Area) -> Result<()> {
+        if self
+            .pending_fills
+            .iter()
+            .any(|fill| fill.area.intersect(area).is_some())
+        {
+            self.flush_fills()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the pending fills if a memory range overlaps the image buffer.
+    pub(crate) fn flush_fills_in_memory(&mut self, addr: u32, len: usize) -> Result<()> {
+        let Some(info) = self
+            .device_info
+            .as_ref()
+            .filter(|_| !self.pending_fills.is_empty())
+        else {
+            return Ok(());
+        };
+        let start = info.img_buf_addr as usize;
+        let end = start + info.panel_width as usize * info.panel_height as usize;
+        if (addr as usize) < end && start < addr as usize + len {
+            self.flush_fills()?;
+        }
+        Ok(())
+    }
+
+    /// Writes all pending fills to the image buffer, oldest first.
+    ///
+    /// Fills recorded before alignment was enabled are widened like loads.
+    pub(crate) fn flush_fills(&mut self) -> Result<()> {
+        // Taken out first, since a widened fill is written as a load, which
+        // settles the pending fills itself
+        let fills = std::mem::take(&mut self.pending_fills);
+        for (i, fill) in fills.iter().enumerate() {
+            let info = self.info();
+            let area = fill
+                .area
+                .to_logical(self.rotation, info.panel_width, info.panel_height);
+            let (width, height) = self.display_size()?;
+            let area = self.mirror_area(&area, width, height);
+            if let Err(e) = self.fill_area_once(&area, fill.value) {
+                self.pending_fills = fills[i..].to_vec();
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Uploads a solid value into a panel area of the image buffer.
+    pub(crate) fn write_fill(&mut self, area: &Area, value: u8) -> Result<()> {
+        // The fill data is generated here, so neither the host byte order
+        // nor the rotation apply
+        let load_info = LoadImageInfo {
+            endian: Endian::Little,
+            pixel_format: PixelFormat::Bpp8,
+            rotate: Rotation::Rotate0,
+            start_fb_addr: 0, // Not used for fill
+            img_buf_base_addr: self.info().img_buf_addr,
+        };
+
+        // A running update may still be reading the image buffer; waiting
+        // inside the load would interrupt the transfer
+        self.wait_display_ready()?;
+
+        // Load image area command
+        self.load_image_area_start(&load_info, area)?;
+        self.buffer_written(area);
+
+        // Write the fill data; every row is padded to a whole word, and the
+        // padding pixels are discarded by the controller
+        let num_words = pixel::packed_size(area.width, area.height, PixelFormat::Bpp8) / 2;
+        let packed_value = ((value as u16) << 8) | (value as u16);
+        let words = vec![packed_value; num_words];
+
+        self.transport.write_data_batch(&words)?;
+
+        // End load image
+        self.transport.write_command(Command::LoadImageEnd)
+    }
+
+    /// Runs a fill update of a panel area, returning `false` if the
+    /// firmware lacks support.
+    fn hardware_fill_update(&mut self, area: &Area, value: u8, mode: u16) -> Result<bool> {
+        // The LUT engine latches the update parameters when it starts
+        self.wait_display_ready()?;
+
+        self.with_update_flag(UP1SR_FILL_ENABLE, |display| {
+            if display.transport.read_register(UP1SR_HIGH)? & UP1SR_FILL_ENABLE == 0 {
+                log::debug!("Hardware fill not supported, falling back to pixel upload");
+                display.hardware_fill = Some(false);
+                return Ok(false);
+            }
+            display.hardware_fill = Some(true);
+
+            display
+                .transport
+                .write_register(Register::LUT0ABFRV, value as u16)?;
+
+            let args = [area.x, area.y, area.width, area.height, mode];
+            display
+                .transport
+                .write_user_command_with_args(UserCommand::DisplayArea, &args)?;
+            display.refresh_started();
+            display.wait_display_ready()?;
+            Ok(true)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::mock::{mock_device, MockController, IMG_BUF_ADDR};
+    use crate::protocol::decode::{decode, Operation};
+    use crate::types::DisplayMode;
+
+    fn loads(controller: &MockController) -> usize {
+        decode(&controller.get_transfers())
+            .iter()
+            .filter(|op| matches!(op, Operation::LoadImageArea { .. }))
+            .count()
+    }
+
+    #[test]
+    fn test_clear_then_refresh_sends_no_pixels() {
+        let (controller, mut device) = mock_device();
+
+        device.clear(0xF0).unwrap();
+        device.refresh(DisplayMode::Gc16).unwrap();
+        device
+            .refresh_area(&Area::new(10, 10, 20, 20), DisplayMode::Du)
+            .unwrap();
+
+        assert_eq!(loads(&controller), 0);
+        assert_eq!(device.hardware_fill_supported(), Some(true));
+        assert_eq!(controller.register(Register::LUT0ABFRV), 0xF0);
+        assert_eq!(controller.memory(IMG_BUF_ADDR, 4), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_refresh_past_fill_writes_buffer() {
+        let (controller, mut device) = mock_device();
+
+        device.fill_area(&Area::new(0, 0, 4, 2), 0x80).unwrap();
+        device.fill_area(&Area::new(2, 0, 4, 2), 0x40).unwrap();
+        assert_eq!(loads(&controller), 0);
+
+        device
+            .refresh_area(&Area::new(0, 0, 8, 2), DisplayMode::Du)
+            .unwrap();
+
+        assert_eq!(loads(&controller), 2);
+        assert_eq!(
+            controller.memory(IMG_BUF_ADDR + 800, 8),
+            [0x80, 0x80, 0x40, 0x40, 0x40, 0x40, 0, 0]
+        );
+        assert!(device.pending_fills.is_empty());
+    }
+
+    #[test]
+    fn test_partial_load_writes_fill_first() {
+        let (controller, mut device) = mock_device();
+
+        device.fill_area(&Area::new(0, 0, 8, 1), 0xFF).unwrap();
+        let packed = pixel::pack(&[0x10; 2], 2, 1, PixelFormat::Bpp8).unwrap();
+        device
+            .load_image(&packed, &Area::new(2, 0, 2, 1), PixelFormat::Bpp8)
+            .unwrap();
+
+        assert_eq!(
+            controller.memory(IMG_BUF_ADDR, 8),
+            [0xFF, 0xFF, 0x10, 0x10, 0xFF, 0xFF, 0xFF, 0xFF]
+        );
+    }
+
+    #[test]
+    fn test_covering_load_replaces_fill() {
+        let (controller, mut device) = mock_device();
+
+        device.fill_area(&Area::new(0, 0, 4, 1), 0xFF).unwrap();
+        let packed = pixel::pack(&[0x10; 8], 8, 1, PixelFormat::Bpp8).unwrap();
+        device
+            .load_image(&packed, &Area::new(0, 0, 8, 1), PixelFormat::Bpp8)
+            .unwrap();
+
+        assert_eq!(loads(&controller), 1);
+        assert!(device.pending_fills.is_empty());
+        assert_eq!(controller.memory(IMG_BUF_ADDR, 8), [0x10; 8]);
+    }
+
+    #[test]
+    fn test_memory_read_writes_fill() {
+        let (_controller, mut device) = mock_device();
+
+        device.fill_area(&Area::new(0, 1, 4, 1), 0xA0).unwrap();
+        assert_eq!(device.read_memory(IMG_BUF_ADDR + 800, 4).unwrap(), [0xA0; 4]);
+    }
+}
@@ -6,8 +6,31 @@
 use crate::device::IT8951;
 use crate::error::{Error, Result};
 use crate::hal::{InputPin, OutputPin, SpiTransfer};
-use crate::protocol::{Command, UserCommand};
-use crate::types::{Area, DisplayMode, Endian, LoadImageInfo, PixelFormat, Rotation};
+use crate::pixel;
+use crate::protocol::{Command, Register, UserCommand};
+use crate::types::{Area, DisplayMode, Endian, LoadImageInfo, PixelFormat};
+use crate::waveform::ModeName;
+use std::io::Read;
+
+mod align;
+mod fill;
+mod verify;
+
+pub use align::AlignPadding;
+pub(crate) use fill::PendingFill;
+pub use verify::{VerifyMode, VerifyPolicy, VerifyStats};
+
+/// Upper word of the Update Parameter 1 Setting Register.
+const UP1SR_HIGH: Register = Register::new(Register::UP1SR.addr() + 2);
+
+/// Fill rectangle enable (UP1SR bit 19). While set, display updates write
+/// the LUT0ABFRV fill value into the updated area instead of using the
+/// image buffer contents.
+const UP1SR_FILL_ENABLE: u16 = 1 << 3;
+
+/// 1bpp mode enable (UP1SR bit 18). While set, display updates render each
+/// image buffer bit with the BGVR foreground or background gray level.
+const UP1SR_1BPP_ENABLE: u16 = 1 << 2;
 
 impl<SPI, HRDY, CS, RESET> IT8951<SPI, HRDY, CS, RESET>
 where
@@ -18,8 +41,8 @@ where
 {
     /// Clears the entire display to the specified grayscale value.
     ///
-    /// This fills the frame buffer with the given value and optionally
-    /// refreshes the display.
+    /// This fills the frame buffer with the given value (see
+    /// [`fill_area`](Self::fill_area)); refresh the display to show it.
     ///
     /// # Arguments
     ///
@@ -32,71 +55,49 @@ where
     /// display.refresh(DisplayMode::Init)?;
     /// ```
     pub fn clear(&mut self, value: u8) -> Result<()> {
-        let device_info = self
-            .device_info
-            .as_ref()
-            .ok_or_else(|| Error::Init("Device not initialized".to_string()))?;
-
-        let area = Area::new(0, 0, device_info.panel_width, device_info.panel_height);
+        let (width, height) = self.display_size()?;
+        let area = Area::new(0, 0, width, height);
 
         self.fill_area(&area, value)
     }
 
     /// Fills a rectangular area with a solid grayscale value.
     ///
+    /// When the firmware supports hardware fill, no pixels are sent: the
+    /// fill is recorded, and refreshes inside the area are drawn by the
+    /// controller's fill rectangle engine. The image buffer is written only
+    /// when another operation depends on it, such as a refresh reaching
+    /// past the area, a load partly overlapping it or a memory read.
+    /// Firmware without fill support is detected on the first refresh,
+    /// after which the pixels are uploaded right away.
+    ///
     /// # Arguments
     ///
-    /// * `area` - The area to fill
+    /// * `area` - The area to fill, in rotated coordinates
     /// * `value` - Grayscale value to fill with
     pub fn fill_area(&mut self, area: &Area, value: u8) -> Result<()> {
-        let device_info = self
-            .device_info
-            .as_ref()
-            .ok_or_else(|| Error::Init("Device not initialized".to_string()))?;
+        if self.defer_fill(area, value)? {
+            return Ok(());
+        }
+        self.with_recovery(|display| display.fill_area_once(area, value))
+    }
+
+    fn fill_area_once(&mut self, area: &Area, value: u8) -> Result<()> {
+        let (width, height) = self.display_size()?;
 
         // Validate area
-        if !area.is_valid(device_info.panel_width, device_info.panel_height) {
+        if !area.is_valid(width, height) {
             return Err(Error::InvalidArea(*area));
         }
-
-        // Create load image info
-        let load_info = LoadImageInfo {
-            endian: Endian::Little,
-            pixel_format: PixelFormat::Bpp8,
-            rotate: Rotation::Rotate0,
-            start_fb_addr: 0, // Not used for fill
-            img_buf_base_addr: device_info.img_buf_addr,
-        };
-
-        // Load image area command
-        self.load_image_area_start(&load_info, area)?;
-
-        // Wait for device to be ready before sending pixel data
-        self.wait_display_ready()?;
-
-        // Write the fill data (packed pixels)
-        let pixels_per_word = 2; // 8bpp = 2 pixels per 16-bit word
-        let total_pixels = area.pixel_count();
-        let num_words = (total_pixels + pixels_per_word - 1) / pixels_per_word;
-
-        // Pack two pixels into each 16-bit word
-        let packed_value = ((value as u16) << 8) | (value as u16);
-
-        let mut words = vec![packed_value; num_words];
-
-        // Handle odd pixel count
-        if total_pixels % 2 == 1 {
-            if let Some(last) = words.last_mut() {
-                *last = value as u16; // Only one pixel in last word
-            }
+        if self.aligned_load_area(area)?.is_some() {
+            // The padding columns need their own pixels
+            let data = vec![value; pixel::packed_size(area.width, area.height, PixelFormat::Bpp8)];
+            return self.load_image_once(&data, area, PixelFormat::Bpp8, Endian::Little);
         }
 
-        self.transport.write_data_batch(&words)?;
-
-        // End load image
-        self.transport.write_command(Command::LoadImageEnd)?;
-
-        Ok(())
+        let area = self.physical_area(area)?;
+        self.settle_fills_for_load(&area)?;
+        self.write_fill(&area, value)
     }
 
     /// Starts loading an image area.
@@ -138,12 +139,8 @@ where
     /// display.refresh(DisplayMode::Du)?;   // Fast monochrome
     /// ```
     pub fn refresh(&mut self, mode: DisplayMode) -> Result<()> {
-        let device_info = self
-            .device_info
-            .as_ref()
-            .ok_or_else(|| Error::Init("Device not initialized".to_string()))?;
-
-        let area = Area::new(0, 0, device_info.panel_width, device_info.panel_height);
+        let (width, height) = self.display_size()?;
+        let area = Area::new(0, 0, width, height);
 
         self.refresh_area(&area, mode)
     }
@@ -152,7 +149,7 @@ where
     ///
     /// # Arguments
     ///
-    /// * `area` - The area to refresh
+    /// * `area` - The area to refresh, in rotated coordinates
     /// * `mode` - Display refresh mode
     ///
     /// # Examples
@@ -162,106 +159,592 @@ where
     /// display.refresh_area(&area, DisplayMode::Du)?;
     /// ```
     pub fn refresh_area(&mut self, area: &Area, mode: DisplayMode) -> Result<()> {
-        let device_info = self
-            .device_info
-            .as_ref()
-            .ok_or_else(|| Error::Init("Device not initialized".to_string()))?;
+        self.with_recovery(|display| display.refresh_area_once(area, mode))
+    }
 
-        // Validate area
-        if !area.is_valid(device_info.panel_width, device_info.panel_height) {
-            return Err(Error::InvalidArea(*area));
+    /// Refreshes an area using a named waveform mode.
+    ///
+    /// The mode number is resolved with [`mode_number`](Self::mode_number),
+    /// so modes beyond the standard five (such as GLR16 or DU4) can be used
+    /// once the panel's waveform is known.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidParameter` if the panel has no such mode.
+    pub fn refresh_area_named(&mut self, area: &Area, mode: ModeName) -> Result<()> {
+        let number = self
+            .mode_number(mode)
+            .ok_or(Error::InvalidParameter("mode not available on this panel"))?;
+        self.with_recovery(|display| display.refresh_area_number_once(area, number))
+    }
+
+    fn refresh_area_once(&mut self, area: &Area, mode: DisplayMode) -> Result<()> {
+        self.refresh_area_number_once(area, mode.as_u16())
+    }
+
+    fn refresh_area_number_once(&mut self, area: &Area, mode: u16) -> Result<()> {
+        let area = self.physical_area(area)?;
+        let area = self.align_refresh_area(area, false);
+        if self.refresh_pending_fill(&area, mode)? {
+            return Ok(());
         }
+        // Only an update from the image buffer depends on its contents
+        self.check_buffer_intact(&area)?;
 
         // Send display area command
-        let args = [area.x, area.y, area.width, area.height, mode.as_u16()];
+        let args = [area.x, area.y, area.width, area.height, mode];
 
         self.transport
             .write_user_command_with_args(UserCommand::DisplayArea, &args)?;
+        self.refresh_started();
 
         Ok(())
     }
 
+    /// Refreshes a specific area of the display in 1bpp mode.
+    ///
+    /// Each pixel in the image buffer is treated as a bit and rendered as
+    /// either the foreground or background gray level.
+    ///
+    /// # Arguments
+    ///
+    /// * `area` - The area to refresh, in rotated coordinates
+    /// * `mode` - Display refresh mode
+    /// * `foreground` - Gray level for set bits
+    /// * `background` - Gray level for clear bits
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Unsupported` if the firmware lacks 1bpp mode.
+    pub fn refresh_area_1bpp(
+        &mut self,
+        area: &Area,
+        mode: DisplayMode,
+        foreground: u8,
+        background: u8,
+    ) -> Result<()> {
+        self.require(self.capabilities().one_bpp, "1bpp mode")?;
+        self.with_recovery(|display| {
+            display.refresh_area_1bpp_once(area, mode, foreground, background)
+        })
+    }
+
+    fn refresh_area_1bpp_once(
+        &mut self,
+        area: &Area,
+        mode: DisplayMode,
+        foreground: u8,
+        background: u8,
+    ) -> Result<()> {
+        let area = self.physical_area(area)?;
+        let area = self.align_refresh_area(area, true);
+        self.check_buffer_intact(&area)?;
+        self.flush_fills_overlapping(&area)?;
+
+        // The LUT engine latches the update parameters when it starts
+        self.wait_display_ready()?;
+
+        self.with_update_flag(UP1SR_1BPP_ENABLE, |display| {
+            // Bitmap color table: bits 15:8 = foreground, bits 7:0 = background
+            let colors = ((foreground as u16) << 8) | (background as u16);
+            display.transport.write_register(Register::BGVR, colors)?;
+
+            let args = [area.x, area.y, area.width, area.height, mode.as_u16()];
+            display
+                .transport
+                .write_user_command_with_args(UserCommand::DisplayArea, &args)?;
+            display.refresh_started();
+            display.wait_display_ready()
+        })
+    }
+
+    /// Runs an update with a UP1SR mode bit set.
+    ///
+    /// The bit is cleared again on every exit path, since it would
+    /// otherwise apply to all later updates.
+    fn with_update_flag<T, F>(&mut self, flag: u16, op: F) -> Result<T>
+    where
+        F: FnOnce(&mut Self) -> Result<T>,
+    {
+        let flags = self.transport.read_register(UP1SR_HIGH)?;
+        let result = self
+            .transport
+            .write_register(UP1SR_HIGH, flags | flag)
+            .and_then(|()| op(self));
+        let cleared = self
+            .transport
+            .write_register(UP1SR_HIGH, flags & !flag);
+
+        let value = result?;
+        cleared?;
+        Ok(value)
+    }
+
+    /// Fills an area with a solid grayscale value and refreshes it.
+    ///
+    /// Same as [`fill_area`](Self::fill_area) followed by
+    /// [`refresh_area`](Self::refresh_area), so no pixel data is sent over
+    /// SPI when the controller's fill rectangle engine is available.
+    ///
+    /// # Arguments
+    ///
+    /// * `area` - The area to fill, in rotated coordinates
+    /// * `value` - Grayscale value (0x00 = black, 0xFF = white)
+    /// * `mode` - Display refresh mode
+    pub fn fill_and_refresh_area(
+        &mut self,
+        area: &Area,
+        value: u8,
+        mode: DisplayMode,
+    ) -> Result<()> {
+        self.fill_area(area, value)?;
+        self.refresh_area(area, mode)
+    }
+
+    /// Clears the entire display to a value and refreshes it.
+    ///
+    /// See [`fill_and_refresh_area`](Self::fill_and_refresh_area).
+    pub fn clear_and_refresh(&mut self, value: u8, mode: DisplayMode) -> Result<()> {
+        let (width, height) = self.display_size()?;
+        let area = Area::new(0, 0, width, height);
+
+        self.fill_and_refresh_area(&area, value, mode)
+    }
+
+    /// Returns whether the firmware supports hardware fill, or `None` if it
+    /// has not been probed yet.
+    pub fn hardware_fill_supported(&self) -> Option<bool> {
+        self.hardware_fill
+    }
+
+    /// Validates an area in rotated coordinates and maps it to the panel.
+    fn physical_area(&self, area: &Area) -> Result<Area> {
+        let (width, height) = self.display_size()?;
+
+        // Validate area
+        if !area.is_valid(width, height) {
+            return Err(Error::InvalidArea(*area));
+        }
+
+        let area = self.mirror_area(area, width, height);
+        let info = self.info();
+        Ok(area.to_physical(self.rotation, info.panel_width, info.panel_height))
+    }
+
+    /// Mirrors a validated area in rotated coordinates for mirrored panels.
+    ///
+    /// The panel's x axis runs backwards, which is a horizontal flip of the
+    /// rotated view, or a vertical one for 90 and 270 degree rotations.
+    pub(crate) fn mirror_area(&self, area: &Area, width: u16, height: u16) -> Area {
+        if !self.mirrored() {
+            *area
+        } else if self.rotation.is_portrait() {
+            Area::new(area.x, height - area.bottom(), area.width, area.height)
+        } else {
+            Area::new(width - area.right(), area.y, area.width, area.height)
+        }
+    }
+
+    /// Mirrors packed image data to match [`mirror_area`](Self::mirror_area).
+    fn mirror_image(
+        &self,
+        data: &[u8],
+        area: &Area,
+        format: PixelFormat,
+        endian: Endian,
+    ) -> Vec<u8> {
+        let stride = pixel::row_bytes(area.width, format);
+        if self.rotation.is_portrait() {
+            return data.chunks(stride).rev().flatten().copied().collect();
+        }
+
+        let mut mirrored = data.to_vec();
+        for row in mirrored.chunks_mut(stride) {
+            if endian == Endian::Big {
+                row.chunks_exact_mut(2).for_each(|pair| pair.swap(0, 1));
+            }
+            pixel::mirror_row(row, area.width, format);
+            if endian == Endian::Big {
+                row.chunks_exact_mut(2).for_each(|pair| pair.swap(0, 1));
+            }
+        }
+        mirrored
+    }
+
     /// Loads image data into a specific area of the display buffer.
     ///
-    /// The image data should be in the specified pixel format, with pixels
-    /// packed according to the format (2 pixels per word for 8bpp).
+    /// The image data should be in the wire layout for the specified pixel
+    /// format, with every row padded to a whole word (see [`pixel::pack`]
+    /// and [`pixel::packed_size`]). With
+    /// the default little-endian byte order each pair of bytes is swapped
+    /// into a word; with `Endian::Big` (see `set_endian`) the buffer is
+    /// sent untouched. The controller applies the display rotation while
+    /// loading. If upload verification is enabled (see
+    /// [`set_upload_verification`](Self::set_upload_verification)), the
+    /// region is read back afterwards and mismatched rows are resent.
     ///
     /// # Arguments
     ///
     /// * `data` - Image pixel data (grayscale, packed)
-    /// * `area` - Destination area on display
+    /// * `area` - Destination area on display, in rotated coordinates
     /// * `format` - Pixel format of the image data
     ///
     /// # Examples
     ///
     /// ```ignore
-    /// let mut image_data = vec![0x80; 400]; // 20x20 image
-    /// let area = Area::new(0, 0, 20, 20);
-    /// display.load_image(&image_data, &area, PixelFormat::Bpp8)?;
+    /// let image_data = vec![0x80; 21 * 20]; // 21x20 image, one byte per pixel
+    /// let packed = pixel::pack(&image_data, 21, 20, PixelFormat::Bpp4)?;
+    /// let area = Area::new(0, 0, 21, 20);
+    /// display.load_image(&packed, &area, PixelFormat::Bpp4)?;
     /// ```
     pub fn load_image(&mut self, data: &[u8], area: &Area, format: PixelFormat) -> Result<()> {
-        let device_info = self
-            .device_info
-            .as_ref()
-            .ok_or_else(|| Error::Init("Device not initialized".to_string()))?;
+        let endian = self.endian;
+        self.with_recovery(|display| display.load_image_once(data, area, format, endian))?;
+        self.verify_upload(data, area, format, endian)
+    }
 
-        // Validate area
-        if !area.is_valid(device_info.panel_width, device_info.panel_height) {
-            return Err(Error::InvalidArea(*area));
+    /// Loads a buffer produced by the [`pixel`] packers.
+    ///
+    /// Unlike `load_image` this ignores the host byte order setting, since
+    /// the packers always produce little-endian words.
+    pub(crate) fn load_packed(&mut self, data: &[u8], area: &Area, format: PixelFormat) -> Result<()> {
+        self.with_recovery(|display| display.load_image_once(data, area, format, Endian::Little))?;
+        self.verify_upload(data, area, format, Endian::Little)
+    }
+
+    /// Loads an image row by row from an iterator of 8bpp rows.
+    ///
+    /// Each row holds one byte per pixel and must be at least `area.width`
+    /// bytes long; extra bytes are ignored. Rows are packed into `format`
+    /// and transmitted as they arrive, so only one packed row is held in
+    /// memory.
+    ///
+    /// The rows are consumed as they are sent, so unlike `load_image` a
+    /// failed stream is not retried by the recovery policy.
+    ///
+    /// # Arguments
+    ///
+    /// * `area` - Destination area on display, in rotated coordinates
+    /// * `format` - Pixel format to transmit
+    /// * `rows` - Source rows, top to bottom
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let canvas = vec![0xFF; 1872 * 1404];
+    /// let area = Area::new(0, 0, 1872, 1404);
+    /// display.load_image_stream(&area, PixelFormat::Bpp4, canvas.chunks(1872))?;
+    /// ```
+    pub fn load_image_stream<'a, I>(
+        &mut self,
+        area: &Area,
+        format: PixelFormat,
+        rows: I,
+    ) -> Result<()>
+    where
+        I: IntoIterator<Item = &'a [u8]>,
+    {
+        let width = area.width as usize;
+        let mut rows = rows.into_iter();
+        self.stream_rows(area, format, |y, packed| match rows.next() {
+            Some(row) if row.len() >= width => {
+                pixel::pack_row_into(&row[..width], format, packed);
+                Ok(())
+            }
+            Some(row) => Err(Error::BufferSize {
+                expected: width,
+                actual: row.len(),
+            }),
+            None => Err(Error::BufferSize {
+                expected: area.pixel_count(),
+                actual: y * width,
+            }),
+        })
+    }
+
+    /// Loads an image row by row from a reader of 8bpp pixels.
+    ///
+    /// Reads exactly `area.width * area.height` bytes, one byte per pixel,
+    /// row by row. See [`load_image_stream`](Self::load_image_stream).
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let file = std::fs::File::open("frame.gray")?;
+    /// display.load_image_reader(&area, PixelFormat::Bpp4, std::io::BufReader::new(file))?;
+    /// ```
+    pub fn load_image_reader<R: Read>(
+        &mut self,
+        area: &Area,
+        format: PixelFormat,
+        mut reader: R,
+    ) -> Result<()> {
+        let mut row = vec![0; area.width as usize];
+        self.stream_rows(area, format, |_, packed| {
+            reader.read_exact(&mut row)?;
+            pixel::pack_row_into(&row, format, packed);
+            Ok(())
+        })
+    }
+
+    /// Loads a sub-rectangle of a larger 8bpp buffer.
+    ///
+    /// Rows are read directly from `data`, which holds `stride` bytes per
+    /// row with one byte per pixel, so the region does not need to be
+    /// copied out first. `src_rect` selects the pixels within `data` and
+    /// must be the same size as `dst_area`.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Source pixels, one byte each
+    /// * `stride` - Bytes per source row
+    /// * `src_rect` - Region of `data` to upload
+    /// * `dst_area` - Destination area on display, in rotated coordinates
+    /// * `format` - Pixel format to transmit
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// // Upload a 100x50 patch at (200, 300) of a 1872-wide canvas
+    /// let src = Area::new(200, 300, 100, 50);
+    /// display.load_image_strided(&canvas, 1872, &src, &src, PixelFormat::Bpp4)?;
+    /// ```
+    pub fn load_image_strided(
+        &mut self,
+        data: &[u8],
+        stride: usize,
+        src_rect: &Area,
+        dst_area: &Area,
+        format: PixelFormat,
+    ) -> Result<()> {
+        if src_rect.width != dst_area.width || src_rect.height != dst_area.height {
+            return Err(Error::InvalidParameter(
+                "source and destination sizes differ",
+            ));
+        }
+        if stride == 0 {
+            return Err(Error::InvalidParameter("stride must not be zero"));
+        }
+        if src_rect.x as usize + src_rect.width as usize > stride {
+            return Err(Error::InvalidParameter("source rectangle exceeds stride"));
         }
 
-        // Validate data size
-        let expected_size = match format {
-            PixelFormat::Bpp8 => area.pixel_count(),
-            PixelFormat::Bpp4 => (area.pixel_count() + 1) / 2,
-            PixelFormat::Bpp3 => (area.pixel_count() * 3 + 7) / 8,
-            PixelFormat::Bpp2 => (area.pixel_count() + 3) / 4,
+        let (x, width) = (src_rect.x as usize, src_rect.width as usize);
+        let first = src_rect.y as usize * stride;
+        let needed = match src_rect.height as usize {
+            0 => first,
+            height => first + (height - 1) * stride + x + width,
         };
+        if data.len() < needed {
+            return Err(Error::BufferSize {
+                expected: needed,
+                actual: data.len(),
+            });
+        }
+
+        self.with_recovery(|display| {
+            let rows = data[first..]
+                .chunks(stride)
+                .take(src_rect.height as usize)
+                .map(|row| &row[x..x + width]);
+            display.load_image_stream(dst_area, format, rows)
+        })
+    }
+
+    /// Streams packed rows produced by `next_row` into the image buffer.
+    ///
+    /// `next_row` receives the row index and a buffer of
+    /// [`pixel::row_bytes`] bytes to fill.
+    fn stream_rows<F>(&mut self, area: &Area, format: PixelFormat, mut next_row: F) -> Result<()>
+    where
+        F: FnMut(usize, &mut [u8]) -> Result<()>,
+    {
+        self.ensure_awake()?;
+
+        let Some(aligned) = self.aligned_load_area(area)? else {
+            return self.stream_area(area, format, next_row);
+        };
+        let padding = self.load_padding(area, &aligned, format)?;
+        let mut src = vec![0; pixel::row_bytes(area.width, format)];
+        self.stream_area(&aligned, format, |y, packed| {
+            if let Some(row) = padding.source_row(y) {
+                next_row(row, &mut src)?;
+            }
+            padding.pad_row(y, &src, area.width, packed);
+            Ok(())
+        })
+    }
+
+    /// Loads an area from rows produced by `next_row`.
+    fn stream_area<F>(&mut self, area: &Area, format: PixelFormat, next_row: F) -> Result<()>
+    where
+        F: FnMut(usize, &mut [u8]) -> Result<()>,
+    {
+        self.start_image_load(area, format, Endian::Little)?;
+
+        if let Err(e) = self.stream_row_data(area, format, next_row) {
+            // Leave the controller ready for the next command
+            let _ = self.transport.write_command(Command::LoadImageEnd);
+            return Err(e);
+        }
+
+        self.transport.write_command(Command::LoadImageEnd)
+    }
 
+    /// Sends the rows of a started load, mirroring them for mirrored
+    /// panels.
+    ///
+    /// Rows that have to be sent in reverse order are held until the last
+    /// one has been produced.
+    fn stream_row_data<F>(
+        &mut self,
+        area: &Area,
+        format: PixelFormat,
+        mut next_row: F,
+    ) -> Result<()>
+    where
+        F: FnMut(usize, &mut [u8]) -> Result<()>,
+    {
+        let mirrored = self.mirrored();
+        let reverse_rows = mirrored && self.rotation.is_portrait();
+
+        let mut packed = vec![0; pixel::row_bytes(area.width, format)];
+        let mut held = Vec::new();
+        for y in 0..area.height as usize {
+            next_row(y, &mut packed)?;
+            if reverse_rows {
+                held.extend_from_slice(&packed);
+                continue;
+            }
+            if mirrored {
+                pixel::mirror_row(&mut packed, area.width, format);
+            }
+            self.write_image_data(&packed, Endian::Little)?;
+        }
+
+        for row in held.chunks(packed.len()).rev() {
+            self.write_image_data(row, Endian::Little)?;
+        }
+        Ok(())
+    }
+
+    fn load_image_once(
+        &mut self,
+        data: &[u8],
+        area: &Area,
+        format: PixelFormat,
+        endian: Endian,
+    ) -> Result<()> {
+        // Validate data size
+        let expected_size = pixel::packed_size(area.width, area.height, format);
         if data.len() < expected_size {
-            return Err(Error::InvalidDimensions(
-                data.len() as u16,
-                expected_size as u16,
-            ));
+            return Err(Error::BufferSize {
+                expected: expected_size,
+                actual: data.len(),
+            });
+        }
+
+        let data = &data[..expected_size];
+        if let Some(aligned) = self.aligned_load_area(area)? {
+            let padded = self.pad_image(data, area, &aligned, format, endian)?;
+            return self.load_image_once(&padded, &aligned, format, Endian::Little);
         }
 
+        let mirrored;
+        let data = if self.mirrored() {
+            mirrored = self.mirror_image(data, area, format, endian);
+            &mirrored
+        } else {
+            data
+        };
+
+        self.start_image_load(area, format, endian)?;
+        self.write_image_data(data, endian)?;
+
+        // End load image
+        self.transport.write_command(Command::LoadImageEnd)?;
+
+        Ok(())
+    }
+
+    /// Widens packed image data to an aligned area, returning it in
+    /// little-endian byte order.
+    fn pad_image(
+        &mut self,
+        data: &[u8],
+        area: &Area,
+        aligned: &Area,
+        format: PixelFormat,
+        endian: Endian,
+    ) -> Result<Vec<u8>> {
+        let padding = self.load_padding(area, aligned, format)?;
+        let stride = pixel::row_bytes(area.width, format);
+        let aligned_stride = pixel::row_bytes(aligned.width, format);
+
+        let mut padded = vec![0; aligned_stride * aligned.height as usize];
+        let mut src = vec![0; stride];
+        for (y, dst) in padded.chunks_mut(aligned_stride).enumerate() {
+            if let Some(row) = padding.source_row(y) {
+                src.copy_from_slice(&data[row * stride..(row + 1) * stride]);
+                if endian == Endian::Big {
+                    src.chunks_exact_mut(2).for_each(|pair| pair.swap(0, 1));
+                }
+            }
+            padding.pad_row(y, &src, area.width, dst);
+        }
+        Ok(padded)
+    }
+
+    /// Validates an area in rotated coordinates and starts loading it.
+    fn start_image_load(&mut self, area: &Area, format: PixelFormat, endian: Endian) -> Result<()> {
+        let (width, height) = self.display_size()?;
+
+        // Validate area
+        if !area.is_valid(width, height) {
+            return Err(Error::InvalidArea(*area));
+        }
+        let area = &self.mirror_area(area, width, height);
+        let info = self.info();
+        let physical = area.to_physical(self.rotation, info.panel_width, info.panel_height);
+        self.settle_fills_for_load(&physical)?;
+        self.buffer_written(&physical);
+
         // Create load image info
         let load_info = LoadImageInfo {
-            endian: Endian::Little,
+            endian,
             pixel_format: format,
-            rotate: Rotation::Rotate0,
+            rotate: self.rotation,
             start_fb_addr: 0,
-            img_buf_base_addr: device_info.img_buf_addr,
+            img_buf_base_addr: self.info().img_buf_addr,
         };
 
-        // Start load image area
-        self.load_image_area_start(&load_info, area)?;
+        self.load_image_area_start(&load_info, area)
+    }
 
-        // Convert bytes to 16-bit words for transfer
-        let mut words = Vec::with_capacity((data.len() + 1) / 2);
-        for chunk in data.chunks(2) {
-            let word = if chunk.len() == 2 {
-                ((chunk[1] as u16) << 8) | (chunk[0] as u16)
-            } else {
-                chunk[0] as u16
-            };
-            words.push(word);
+    /// Writes packed image bytes in the given host byte order.
+    fn write_image_data(&mut self, data: &[u8], endian: Endian) -> Result<()> {
+        if endian == Endian::Big {
+            // Host buffer is already in wire order
+            return self.transport.write_data_bytes(data);
         }
 
-        self.transport.write_data_batch(&words)?;
+        // Packed rows are word aligned, so the buffer splits into whole words
+        let words: Vec<u16> = data
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
 
-        // End load image
-        self.transport.write_command(Command::LoadImageEnd)?;
-
-        Ok(())
+        self.transport.write_data_batch(&words)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::hal::mock::{MockInputPin, MockOutputPin, MockSpi};
+    use crate::hal::mock::{MockController, MockInputPin, MockOutputPin, MockSpi};
     use crate::hal::PinState;
+    use crate::panel::PanelProfile;
+    use crate::types::Rotation;
 
     fn setup_initialized_device() -> IT8951<MockSpi, MockInputPin, MockOutputPin, MockOutputPin> {
         let spi = MockSpi::new();
@@ -335,6 +818,129 @@ mod tests {
         assert!(device.refresh_area(&area, DisplayMode::Du).is_ok());
     }
 
+    #[test]
+    fn test_refresh_area_1bpp() {
+        let mut device = setup_initialized_device();
+        let area = Area::new(0, 0, 64, 64);
+
+        assert!(device
+            .refresh_area_1bpp(&area, DisplayMode::A2, 0x00, 0xF0)
+            .is_ok());
+        assert!(matches!(
+            device.refresh_area_1bpp(&Area::new(790, 0, 64, 64), DisplayMode::A2, 0x00, 0xF0),
+            Err(Error::InvalidArea(_))
+        ));
+    }
+
+    #[test]
+    fn test_refresh_area_retries_after_spi_failure() {
+        let spi = MockController::new();
+        let hrdy = MockInputPin::new(PinState::High);
+        let cs = MockOutputPin::new(PinState::High);
+        let reset = MockOutputPin::new(PinState::High);
+
+        let mut device = IT8951::new(spi.clone(), hrdy, cs, reset.clone(), 1500);
+        device.device_info = Some(crate::types::DeviceInfo {
+            panel_width: 800,
+            panel_height: 600,
+            img_buf_addr: 0x001236E0,
+            fw_version: "test".to_string(),
+            lut_version: "test".to_string(),
+        });
+
+        // Without a policy the failure propagates
+        spi.fail_next(1);
+        let area = Area::new(0, 0, 100, 100);
+        assert!(matches!(
+            device.refresh_area(&area, DisplayMode::Du),
+            Err(Error::Spi(_))
+        ));
+
+        device.set_recovery_policy(
+            crate::RecoveryPolicy::new(2).initial_backoff(std::time::Duration::from_millis(1)),
+        );
+        spi.fail_next(1);
+        assert!(device.refresh_area(&area, DisplayMode::Du).is_ok());
+
+        // The controller answered again, so it was re-attached without a
+        // reset that would clear the image buffer
+        assert!(!reset.get_history().contains(&PinState::Low));
+    }
+
+    #[test]
+    fn test_refresh_area_rotated() {
+        let spi = MockSpi::new();
+        let mut device = setup_initialized_device();
+        device.transport = crate::protocol::Transport::new(
+            spi.clone(),
+            MockInputPin::new(PinState::High),
+            MockOutputPin::new(PinState::High),
+        );
+        device.set_rotation(Rotation::Rotate90);
+
+        // Logical display is 600x800
+        let area = Area::new(30, 40, 10, 20);
+        device.refresh_area(&area, DisplayMode::Du).unwrap();
+        assert!(matches!(
+            device.refresh_area(&Area::new(0, 0, 800, 600), DisplayMode::Du),
+            Err(Error::InvalidArea(_))
+        ));
+
+        let ops = crate::protocol::decode::decode(&spi.get_transfers());
+        assert_eq!(ops[0].to_string(), "DisplayArea x=740 y=30 w=20 h=10 mode=1");
+    }
+
+    #[test]
+    fn test_load_image_rotated_passes_rotation() {
+        let spi = MockSpi::new();
+        let mut device = setup_initialized_device();
+        device.transport = crate::protocol::Transport::new(
+            spi.clone(),
+            MockInputPin::new(PinState::High),
+            MockOutputPin::new(PinState::High),
+        );
+        device.set_rotation(Rotation::Rotate270);
+
+        let area = Area::new(0, 0, 600, 2);
+        device
+            .load_image(&[0x80; 1200], &area, PixelFormat::Bpp8)
+            .unwrap();
+
+        let ops = crate::protocol::decode::decode(&spi.get_transfers());
+        assert!(ops.contains(&crate::protocol::Operation::LoadImageArea {
+            endian: Endian::Little,
+            format: PixelFormat::Bpp8,
+            rotate: Rotation::Rotate270,
+            area,
+        }));
+    }
+
+    #[test]
+    fn test_load_image_big_endian_untouched() {
+        let spi = MockSpi::new();
+        let mut device = setup_initialized_device();
+        device.transport = crate::protocol::Transport::new(
+            spi.clone(),
+            MockInputPin::new(PinState::High),
+            MockOutputPin::new(PinState::High),
+        );
+
+        let area = Area::new(0, 0, 2, 2);
+        let data = [0x01, 0x02, 0x03, 0x04];
+
+        device.load_image(&data, &area, PixelFormat::Bpp8).unwrap();
+        device.set_endian(Endian::Big);
+        device.load_image(&data, &area, PixelFormat::Bpp8).unwrap();
+
+        let pixel_transfers: Vec<Vec<u8>> = spi
+            .get_transfers()
+            .into_iter()
+            .filter(|t| t.len() == 6 && t[..2] == [0x00, 0x00])
+            .collect();
+        assert_eq!(pixel_transfers[0], vec![0x00, 0x00, 0x02, 0x01, 0x04, 0x03]);
+        assert_eq!(pixel_transfers[1], vec![0x00, 0x00, 0x01, 0x02, 0x03, 0x04]);
+    }
+
     #[test]
     fn test_load_image() {
         let mut device = setup_initialized_device();
@@ -352,7 +958,353 @@ mod tests {
 
         assert!(matches!(
             device.load_image(&data, &area, PixelFormat::Bpp8),
-            Err(Error::InvalidDimensions(_, _))
+            Err(Error::BufferSize {
+                expected: 400,
+                actual: 100
+            })
+        ));
+    }
+
+    #[test]
+    fn test_load_image_odd_width_requires_row_padding() {
+        let mut device = setup_initialized_device();
+        let area = Area::new(0, 0, 3, 2);
+
+        // 3 pixels per row are padded to 4 bytes
+        assert!(matches!(
+            device.load_image(&[0x80; 6], &area, PixelFormat::Bpp8),
+            Err(Error::BufferSize {
+                expected: 8,
+                actual: 6
+            })
+        ));
+        assert!(device
+            .load_image(&[0x80; 8], &area, PixelFormat::Bpp8)
+            .is_ok());
+    }
+
+    fn device_with_spi(spi: &MockSpi) -> IT8951<MockSpi, MockInputPin, MockOutputPin, MockOutputPin> {
+        let mut device = setup_initialized_device();
+        device.transport = crate::protocol::Transport::new(
+            spi.clone(),
+            MockInputPin::new(PinState::High),
+            MockOutputPin::new(PinState::High),
+        );
+        device
+    }
+
+    fn pixel_data(spi: &MockSpi) -> Vec<u8> {
+        spi.get_transfers()
+            .into_iter()
+            .filter(|t| t.len() > 2 && t[..2] == [0x00, 0x00])
+            .flat_map(|t| t[2..].to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn test_load_image_stream_matches_load_image() {
+        let src: Vec<u8> = (0..5 * 3).map(|i| (i * 17) as u8).collect();
+        let area = Area::new(0, 0, 5, 3);
+
+        let buffered = MockSpi::new();
+        let packed = pixel::pack(&src, 5, 3, PixelFormat::Bpp4).unwrap();
+        device_with_spi(&buffered)
+            .load_image(&packed, &area, PixelFormat::Bpp4)
+            .unwrap();
+
+        let streamed = MockSpi::new();
+        device_with_spi(&streamed)
+            .load_image_stream(&area, PixelFormat::Bpp4, src.chunks(5))
+            .unwrap();
+
+        let read = MockSpi::new();
+        device_with_spi(&read)
+            .load_image_reader(&area, PixelFormat::Bpp4, &src[..])
+            .unwrap();
+
+        assert_eq!(pixel_data(&streamed), pixel_data(&buffered));
+        assert_eq!(pixel_data(&read), pixel_data(&buffered));
+    }
+
+    #[test]
+    fn test_load_image_stream_short_input_ends_load() {
+        let spi = MockSpi::new();
+        let mut device = device_with_spi(&spi);
+        let area = Area::new(0, 0, 4, 3);
+        let rows = [[0u8; 4], [0u8; 4]];
+
+        assert!(matches!(
+            device.load_image_stream(&area, PixelFormat::Bpp8, rows.iter().map(|r| &r[..])),
+            Err(Error::BufferSize {
+                expected: 12,
+                actual: 8
+            })
+        ));
+        assert!(matches!(
+            device.load_image_stream(&area, PixelFormat::Bpp8, [&[0u8; 3][..]]),
+            Err(Error::BufferSize {
+                expected: 4,
+                actual: 3
+            })
+        ));
+        assert!(matches!(
+            device.load_image_reader(&area, PixelFormat::Bpp8, &[0u8; 5][..]),
+            Err(Error::Io(_))
+        ));
+
+        let ops = crate::protocol::decode::decode(&spi.get_transfers());
+        let ends = ops
+            .iter()
+            .filter(|op| {
+                matches!(
+                    op,
+                    crate::protocol::Operation::Command {
+                        command: Command::LoadImageEnd,
+                        ..
+                    }
+                )
+            })
+            .count();
+        assert_eq!(ends, 3);
+    }
+
+    #[test]
+    fn test_load_image_strided_reads_sub_rectangle() {
+        // 6x4 canvas with a distinct value per pixel
+        let canvas: Vec<u8> = (0..24).collect();
+        let src = Area::new(1, 2, 3, 2);
+        let dst = Area::new(10, 20, 3, 2);
+
+        let strided = MockSpi::new();
+        device_with_spi(&strided)
+            .load_image_strided(&canvas, 6, &src, &dst, PixelFormat::Bpp8)
+            .unwrap();
+
+        let copied = MockSpi::new();
+        let region = [13, 14, 15, 19, 20, 21];
+        device_with_spi(&copied)
+            .load_image_stream(&dst, PixelFormat::Bpp8, region.chunks(3))
+            .unwrap();
+
+        assert_eq!(pixel_data(&strided), pixel_data(&copied));
+    }
+
+    #[test]
+    fn test_load_image_strided_validation() {
+        let mut device = setup_initialized_device();
+        let canvas = [0u8; 24];
+        let dst = Area::new(0, 0, 3, 2);
+
+        assert!(matches!(
+            device.load_image_strided(&canvas, 6, &Area::new(4, 0, 3, 2), &dst, PixelFormat::Bpp8),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            device.load_image_strided(&canvas, 6, &Area::new(0, 0, 2, 2), &dst, PixelFormat::Bpp8),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            device.load_image_strided(&canvas, 6, &Area::new(3, 3, 3, 2), &dst, PixelFormat::Bpp8),
+            Err(Error::BufferSize {
+                expected: 30,
+                actual: 24
+            })
+        ));
+
+        // Neither of these may panic before validation
+        let narrow = Area::new(0, 0, 0, 2);
+        assert!(matches!(
+            device.load_image_strided(&canvas, 0, &narrow, &narrow, PixelFormat::Bpp8),
+            Err(Error::InvalidParameter("stride must not be zero"))
+        ));
+        let empty = Area::new(0, 10, 3, 0);
+        assert!(matches!(
+            device.load_image_strided(&canvas, 6, &empty, &empty, PixelFormat::Bpp8),
+            Err(Error::BufferSize {
+                expected: 60,
+                actual: 24
+            })
+        ));
+    }
+
+    fn controller_device() -> (
+        MockController,
+        IT8951<MockController, MockInputPin, MockOutputPin, MockOutputPin>,
+    ) {
+        let controller = MockController::new();
+        let mut device = IT8951::new(
+            controller.clone(),
+            MockInputPin::new(PinState::High),
+            MockOutputPin::new(PinState::High),
+            MockOutputPin::new(PinState::High),
+            1500,
+        );
+        device.device_info = setup_initialized_device().device_info;
+        (controller, device)
+    }
+
+    fn has_load_image(transfers: &[Vec<u8>]) -> bool {
+        crate::protocol::decode::decode(transfers)
+            .iter()
+            .any(|op| matches!(op, crate::protocol::Operation::LoadImageArea { .. }))
+    }
+
+    #[test]
+    fn test_fill_and_refresh_area_uses_hardware_fill() {
+        let (controller, mut device) = controller_device();
+        let area = Area::new(0, 0, 800, 600);
+
+        device
+            .fill_and_refresh_area(&area, 0xF0, DisplayMode::Gc16)
+            .unwrap();
+
+        assert_eq!(device.hardware_fill_supported(), Some(true));
+        assert_eq!(controller.register(Register::LUT0ABFRV), 0xF0);
+        assert_eq!(controller.register(UP1SR_HIGH) & UP1SR_FILL_ENABLE, 0);
+        assert!(!has_load_image(&controller.get_transfers()));
+    }
+
+    #[test]
+    fn test_refresh_area_1bpp_sequence() {
+        let (controller, mut device) = controller_device();
+
+        device
+            .refresh_area_1bpp(&Area::new(0, 0, 64, 64), DisplayMode::A2, 0xF0, 0x00)
+            .unwrap();
+
+        assert_eq!(controller.register(Register::BGVR), 0xF000);
+        assert_eq!(controller.register(UP1SR_HIGH) & UP1SR_1BPP_ENABLE, 0);
+        let ops: Vec<String> = crate::protocol::decode::decode(&controller.get_transfers())
+            .into_iter()
+            .filter(|op| match op {
+                crate::protocol::Operation::RegisterWrite { register, .. } => {
+                    *register == UP1SR_HIGH
+                }
+                _ => op.to_string().starts_with("DisplayArea"),
+            })
+            .map(|op| op.to_string())
+            .collect();
+        assert_eq!(ops.len(), 3, "{:?}", ops);
+        assert!(ops[0].ends_with("= 0x0004"), "{:?}", ops);
+        assert_eq!(ops[1], "DisplayArea x=0 y=0 w=64 h=64 mode=4");
+        assert!(ops[2].ends_with("= 0x0000"), "{:?}", ops);
+    }
+
+    #[test]
+    fn test_update_flag_cleared_on_error() {
+        let (controller, mut device) = controller_device();
+
+        let result: Result<()> = device.with_update_flag(UP1SR_FILL_ENABLE, |display| {
+            assert_ne!(controller.register(UP1SR_HIGH) & UP1SR_FILL_ENABLE, 0);
+            display.transport.write_register(Register::LUT0ABFRV, 0xF0)?;
+            Err(Error::Timeout(10))
+        });
+
+        assert!(matches!(result, Err(Error::Timeout(10))));
+        assert_eq!(controller.register(UP1SR_HIGH) & UP1SR_FILL_ENABLE, 0);
+    }
+
+    #[test]
+    fn test_fill_and_refresh_area_falls_back_without_support() {
+        let (controller, mut device) = controller_device();
+        controller.set_read_only(UP1SR_HIGH);
+        let area = Area::new(0, 0, 8, 8);
+
+        device.clear_and_refresh(0xFF, DisplayMode::Init).unwrap();
+        assert_eq!(device.hardware_fill_supported(), Some(false));
+        assert!(has_load_image(&controller.get_transfers()));
+
+        // Support is not probed again
+        controller.clear_transfers();
+        device
+            .fill_and_refresh_area(&area, 0x00, DisplayMode::Du)
+            .unwrap();
+        let probes = crate::protocol::decode::decode(&controller.get_transfers())
+            .into_iter()
+            .filter(|op| {
+                matches!(
+                    op,
+                    crate::protocol::Operation::RegisterWrite { register, .. }
+                        if *register == UP1SR_HIGH
+                )
+            })
+            .count();
+        assert_eq!(probes, 0);
+    }
+
+    #[test]
+    fn test_refresh_area_named() {
+        let spi = MockSpi::new();
+        let mut device = device_with_spi(&spi);
+        let area = Area::new(0, 0, 16, 16);
+
+        device.refresh_area_named(&area, ModeName::A2).unwrap();
+        assert!(matches!(
+            device.refresh_area_named(&area, ModeName::Du4),
+            Err(Error::InvalidParameter(_))
         ));
+
+        let ops = crate::protocol::decode::decode(&spi.get_transfers());
+        assert_eq!(ops[0].to_string(), "DisplayArea x=0 y=0 w=16 h=16 mode=4");
+    }
+
+    #[test]
+    fn test_panel_profile_mode_numbers() {
+        let spi = MockSpi::new();
+        let mut device = device_with_spi(&spi);
+        device.set_panel(PanelProfile::by_name("10.3"));
+        let area = Area::new(0, 0, 16, 16);
+
+        // The numbering follows the LUT version
+        device.device_info.as_mut().unwrap().lut_version = "M841_TFA5210".to_string();
+        device.refresh_area_named(&area, ModeName::A2).unwrap();
+        device.refresh_area_named(&area, ModeName::Du4).unwrap();
+
+        device.set_panel(PanelProfile::by_name("6hd"));
+        device.device_info.as_mut().unwrap().lut_version = "M641".to_string();
+        device.refresh_area_named(&area, ModeName::A2).unwrap();
+        assert!(device.refresh_area_named(&area, ModeName::Du4).is_err());
+
+        // A profile can fix the mode count
+        device.set_panel(Some(PanelProfile {
+            mode_count: Some(8),
+            ..PanelProfile::by_name("6hd").unwrap()
+        }));
+        device.refresh_area_named(&area, ModeName::A2).unwrap();
+
+        let ops = crate::protocol::decode::decode(&spi.get_transfers());
+        assert!(ops[0].to_string().ends_with("mode=6"));
+        assert!(ops[1].to_string().ends_with("mode=7"));
+        assert!(ops[2].to_string().ends_with("mode=4"));
+        assert!(ops[3].to_string().ends_with("mode=6"));
+    }
+
+    #[test]
+    fn test_mirrored_panel_flips_physical_x() {
+        for rotation in [Rotation::Rotate0, Rotation::Rotate90] {
+            let (controller, mut device) = controller_device();
+            let panel = PanelProfile::by_name("6").unwrap();
+            device.set_panel(Some(PanelProfile {
+                mirrored: true,
+                ..panel
+            }));
+            device.set_rotation(rotation);
+            device.set_upload_verification(Some(VerifyPolicy::new(VerifyMode::Full)));
+
+            let pixels = [0x10, 0x20, 0x30, 0x40];
+            let packed = pixel::pack(&pixels, 4, 1, PixelFormat::Bpp8).unwrap();
+            device
+                .load_image(&packed, &Area::new(0, 0, 4, 1), PixelFormat::Bpp8)
+                .unwrap();
+            assert_eq!(device.verify_stats().rows_mismatched, 0);
+
+            // The logical origin lands at the other end of the physical x axis
+            let base = device.info().img_buf_addr;
+            let stored: Vec<u8> = match rotation {
+                Rotation::Rotate0 => controller.memory(base + 796, 4).into_iter().rev().collect(),
+                _ => (0..4).map(|i| controller.memory(base + i * 800, 1)[0]).collect(),
+            };
+            assert_eq!(stored, pixels, "{:?}", rotation);
+        }
     }
 }
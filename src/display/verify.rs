@@ -0,0 +1,341 @@
+//! Optional read-back verification of image uploads.
+//!
+//! After an image is loaded, the destination region is read back from the
+//! image buffer with memory bursts and compared against the packed source.
+//! Rows that differ are sent again. The controller keeps one byte per
+//! pixel in its image buffer, with the transmitted value in the most
+//! significant bits and a pitch of the panel width.
+
+use crate::device::IT8951;
+use crate::error::{Error, Result};
+use crate::hal::{InputPin, OutputPin, SpiTransfer};
+use crate::pixel;
+use crate::types::{Area, Endian, PixelFormat, Rotation};
+
+/// How uploaded rows are compared with the source.
+///
+/// The controller has no checksum command, so every row is read back in
+/// full over SPI and compared on the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyMode {
+    /// Compare every pixel, counting the mismatched ones
+    Full,
+}
+
+/// Policy for verifying image uploads.
+///
+/// # Examples
+///
+/// ```
+/// use it8951::{VerifyMode, VerifyPolicy};
+///
+/// let policy = VerifyPolicy::new(VerifyMode::Full).max_resends(3);
+/// assert_eq!(policy.max_resends, 3);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerifyPolicy {
+    /// How rows are compared
+    pub mode: VerifyMode,
+
+    /// Maximum number of times mismatched rows are sent again
+    pub max_resends: u32,
+}
+
+impl VerifyPolicy {
+    /// Creates a policy that resends mismatched rows up to twice.
+    pub fn new(mode: VerifyMode) -> Self {
+        Self {
+            mode,
+            max_resends: 2,
+        }
+    }
+
+    /// Sets the maximum number of resend rounds.
+    pub fn max_resends(mut self, resends: u32) -> Self {
+        self.max_resends = resends;
+        self
+    }
+}
+
+/// Counters collected while verifying uploads.
+///
+/// A rising `rows_mismatched` count points at a failing cable or an SPI
+/// clock that is too fast for the wiring.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VerifyStats {
+    /// Uploads verified
+    pub uploads: u64,
+
+    /// Rows compared, including re-checks after resending
+    pub rows_checked: u64,
+
+    /// Rows found to differ from the source
+    pub rows_mismatched: u64,
+
+    /// Pixels found to differ (only counted in [`VerifyMode::Full`])
+    pub pixels_mismatched: u64,
+
+    /// Rows sent again
+    pub rows_resent: u64,
+
+    /// Uploads that still differed once resends were exhausted
+    pub failures: u64,
+}
+
+/// Returns the slot bits the controller keeps for a format.
+fn significant_mask(format: PixelFormat) -> u8 {
+    match format {
+        // The least significant bit of a 3bpp slot is ignored
+        PixelFormat::Bpp3 => 0xFE,
+        _ => 0xFF,
+    }
+}
+
+impl<SPI, HRDY, CS, RESET> IT8951<SPI, HRDY, CS, RESET>
+where
+    SPI: SpiTransfer,
+    HRDY: InputPin,
+    CS: OutputPin,
+    RESET: OutputPin,
+{
+    /// Enables or disables read-back verification of image uploads.
+    ///
+    /// When enabled, `load_image`, `draw_framebuffer`,
+    /// `draw_framebuffer_with_format` and `draw_framebuffer_full` read the
+    /// destination region back after loading it and resend rows that
+    /// differ. Streamed uploads, including `load_image_strided` and
+    /// `draw_framebuffer_region`, are not verified, since their source is
+    /// not kept. Verification is disabled by default.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// display.set_upload_verification(Some(VerifyPolicy::new(VerifyMode::Full)));
+    /// ```
+    pub fn set_upload_verification(&mut self, policy: Option<VerifyPolicy>) {
+        self.verify = policy;
+    }
+
+    /// Gets the upload verification policy, if verification is enabled.
+    pub fn upload_verification(&self) -> Option<VerifyPolicy> {
+        self.verify
+    }
+
+    /// Gets the counters collected by upload verification.
+    pub fn verify_stats(&self) -> VerifyStats {
+        self.verify_stats
+    }
+
+    /// Resets the upload verification counters.
+    pub fn reset_verify_stats(&mut self) {
+        self.verify_stats = VerifyStats::default();
+    }
+
+    /// Verifies a completed upload if verification is enabled.
+    ///
+    /// `data` is the packed source in `endian` byte order, as passed to the
+    /// load, and `area` is in rotated coordinates.
+    pub(crate) fn verify_upload(
+        &mut self,
+        data: &[u8],
+        area: &Area,
+        format: PixelFormat,
+        endian: Endian,
+    ) -> Result<()> {
+        let Some(policy) = self.verify else {
+            return Ok(());
+        };
+        if area.width == 0 || area.height == 0 {
+            return Ok(());
+        }
+
+        let stride = pixel::row_bytes(area.width, format);
+        let mut packed = data[..stride * area.height as usize].to_vec();
+        if endian == Endian::Big {
+            for pair in packed.chunks_exact_mut(2) {
+                pair.swap(0, 1);
+            }
+        }
+        let mask = significant_mask(format);
+        let expected: Vec<Vec<u8>> = packed
+            .chunks(stride)
+            .map(|row| {
+                let mut slots = pixel::row_slots(row, area.width, format);
+                slots.iter_mut().for_each(|slot| *slot &= mask);
+                slots
+            })
+            .collect();
+
+        self.verify_stats.uploads += 1;
+        let mut rows: Vec<usize> = (0..area.height as usize).collect();
+        for round in 0..=policy.max_resends {
+            let actual = self.read_back(area, format)?;
+            self.verify_stats.rows_checked += rows.len() as u64;
+
+            rows.retain(|&y| match policy.mode {
+                VerifyMode::Full => {
+                    let diff = actual[y]
+                        .iter()
+                        .zip(&expected[y])
+                        .filter(|(a, e)| a != e)
+                        .count();
+                    self.verify_stats.pixels_mismatched += diff as u64;
+                    diff > 0
+                }
+            });
+            self.verify_stats.rows_mismatched += rows.len() as u64;
+
+            if rows.is_empty() {
+                return Ok(());
+            }
+            if round == policy.max_resends {
+                break;
+            }
+
+            log::warn!("Upload verification: resending {} rows", rows.len());
+            for &y in &rows {
+                let row_area = Area::new(area.x, area.y + y as u16, area.width, 1);
+                let row = &data[y * stride..(y + 1) * stride];
+                self.load_image_once(row, &row_area, format, endian)?;
+                self.verify_stats.rows_resent += 1;
+            }
+        }
+
+        self.verify_stats.failures += 1;
+        Err(Error::VerifyFailed { rows: rows.len() })
+    }
+
+    /// Reads the slot values of an area back from the image buffer, one
+    /// vector per row in rotated coordinates.
+    pub(crate) fn read_back(&mut self, area: &Area, format: PixelFormat) -> Result<Vec<Vec<u8>>> {
+        let physical = self.physical_area(area)?;
+        self.flush_fills_overlapping(&physical)?;
+        let info = self.info();
+        let (panel_w, panel_h) = (info.panel_width as usize, info.panel_height as usize);
+        let base = info.img_buf_addr as usize;
+
+        // Memory is read in words, so start each row on an even address
+        let mut region = Vec::with_capacity(physical.pixel_count());
+        for py in physical.y as usize..physical.bottom() as usize {
+            let start = base + py * panel_w + physical.x as usize;
+            let aligned = start & !1;
+            let len = start - aligned + physical.width as usize;
+            let bytes = self.read_memory(aligned as u32, len)?;
+            region.extend_from_slice(&bytes[start - aligned..]);
+        }
+
+        let shift = 8 - pixel::slot_bits(format);
+        let mask = significant_mask(format);
+        let (rx, ry, rw) = (physical.x as usize, physical.y as usize, physical.width as usize);
+        let (width, height) = self.display_size()?;
+        let (width, height) = (width as usize, height as usize);
+        let mirrored = self.mirrored();
+        let rows = (area.y as usize..area.bottom() as usize)
+            .map(|ly| {
+                (area.x as usize..area.right() as usize)
+                    .map(|lx| {
+                        let (lx, ly) = match (mirrored, self.rotation.is_portrait()) {
+                            (false, _) => (lx, ly),
+                            (true, false) => (width - 1 - lx, ly),
+                            (true, true) => (lx, height - 1 - ly),
+                        };
+                        let (px, py) = match self.rotation {
+                            Rotation::Rotate0 => (lx, ly),
+                            Rotation::Rotate90 => (panel_w - 1 - ly, lx),
+                            Rotation::Rotate180 => (panel_w - 1 - lx, panel_h - 1 - ly),
+                            Rotation::Rotate270 => (ly, panel_h - 1 - lx),
+                        };
+                        (region[(py - ry) * rw + px - rx] >> shift) & mask
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::mock::mock_device;
+    use crate::protocol::decode::{decode, Operation};
+    use crate::protocol::Command;
+
+    fn image(width: u16, height: u16) -> Vec<u8> {
+        let pixels: Vec<u8> = (0..width as usize * height as usize)
+            .map(|i| (i * 37) as u8)
+            .collect();
+        pixel::pack(&pixels, width, height, PixelFormat::Bpp4).unwrap()
+    }
+
+    #[test]
+    fn test_verified_upload_passes() {
+        for rotation in [Rotation::Rotate0, Rotation::Rotate90, Rotation::Rotate270] {
+            let (_, mut device) = mock_device();
+            device.set_rotation(rotation);
+            device.set_upload_verification(Some(VerifyPolicy::new(VerifyMode::Full)));
+
+            let area = Area::new(3, 5, 21, 7);
+            device.load_image(&image(21, 7), &area, PixelFormat::Bpp4).unwrap();
+
+            let stats = device.verify_stats();
+            assert_eq!(stats.uploads, 1);
+            assert_eq!(stats.rows_checked, 7);
+            assert_eq!(stats.rows_mismatched, 0, "{:?}", rotation);
+        }
+    }
+
+    #[test]
+    fn test_verified_upload_resends_corrupted_rows() {
+        let (controller, mut device) = mock_device();
+        device.set_upload_verification(Some(VerifyPolicy::new(VerifyMode::Full)));
+
+        // Corrupt the first word of the image data only
+        controller.corrupt_next_words(1);
+        let area = Area::new(0, 0, 16, 4);
+        device.load_image(&image(16, 4), &area, PixelFormat::Bpp4).unwrap();
+
+        let stats = device.verify_stats();
+        assert_eq!(stats.rows_mismatched, 1);
+        assert_eq!(stats.rows_resent, 1);
+        assert_eq!(stats.rows_checked, 5);
+        assert_eq!(stats.failures, 0);
+    }
+
+    #[test]
+    fn test_verified_upload_gives_up() {
+        let (controller, mut device) = mock_device();
+        device.set_upload_verification(Some(VerifyPolicy::new(VerifyMode::Full).max_resends(1)));
+        device.transport.set_speeds(1_000_000, 24_000_000);
+        controller.set_max_reliable_hz(20_000_000);
+
+        let area = Area::new(0, 0, 8, 2);
+        assert!(matches!(
+            device.load_image(&image(8, 2), &area, PixelFormat::Bpp4),
+            Err(Error::VerifyFailed { rows: 2 })
+        ));
+
+        let stats = device.verify_stats();
+        assert_eq!(stats.rows_resent, 2);
+        assert_eq!(stats.failures, 1);
+        assert!(stats.pixels_mismatched > 0);
+    }
+
+    #[test]
+    fn test_verification_disabled_by_default() {
+        let (controller, mut device) = mock_device();
+        let area = Area::new(0, 0, 8, 2);
+        device.load_image(&image(8, 2), &area, PixelFormat::Bpp4).unwrap();
+
+        assert_eq!(device.verify_stats(), VerifyStats::default());
+        let ops = decode(&controller.get_transfers());
+        assert!(!ops.iter().any(|op| matches!(
+            op,
+            Operation::Command {
+                command: Command::MemBurstReadStart,
+                ..
+            }
+        )));
+    }
+}
@@ -31,19 +31,65 @@ pub enum Error {
     #[error("Invalid VCOM value: {0} (must be between 0 and 5000)")]
     InvalidVcom(u16),
 
+    /// Invalid temperature value
+    #[error("Invalid temperature: {0}°C (must be between -20 and 60)")]
+    InvalidTemperature(i16),
+
     /// Invalid image dimensions
     #[error("Invalid image dimensions: {0}x{1}")]
     InvalidDimensions(u16, u16),
 
+    /// Image buffer does not hold enough data for the target area
+    #[error("Image buffer too small: {actual} bytes, expected {expected}")]
+    BufferSize {
+        /// Number of bytes required
+        expected: usize,
+        /// Number of bytes provided
+        actual: usize,
+    },
+
     /// Image format error
     #[cfg(feature = "image-support")]
     #[error("Image error: {0}")]
     Image(#[from] image::ImageError),
 
+    /// Configuration file error
+    #[cfg(feature = "config")]
+    #[error("Configuration error: {0}")]
+    Config(String),
+
     /// IO error
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
+    /// Bus returned data that points at a wiring or configuration fault
+    #[error("Bus fault: {0}")]
+    BusFault(&'static str),
+
+    /// Device reported implausible information
+    #[error("Invalid device info: {0}")]
+    InvalidDeviceInfo(String),
+
+    /// Register read back a different value than was written
+    #[error("Register 0x{register:04X} read back 0x{actual:04X} after writing 0x{expected:04X}")]
+    RegisterMismatch {
+        /// Register address
+        register: u16,
+        /// Value written
+        expected: u16,
+        /// Value read back
+        actual: u16,
+    },
+
+    /// VCOM read back a different value than was written
+    #[error("VCOM read back {actual}mV after writing {expected}mV")]
+    VcomMismatch {
+        /// Magnitude written, in millivolts
+        expected: u16,
+        /// Magnitude read back, in millivolts
+        actual: u16,
+    },
+
     /// Device error with description
     #[error("Device error: {0}")]
     Device(String),
@@ -56,6 +102,25 @@ pub enum Error {
     #[error("Protocol error: {0}")]
     Protocol(String),
 
+    /// Feature not supported by the controller firmware
+    #[error("Not supported by this firmware: {0}")]
+    Unsupported(&'static str),
+
+    /// Waveform file could not be parsed
+    #[error("Invalid waveform file: {0}")]
+    InvalidWaveform(String),
+
+    /// Uploaded image data still differed after resending
+    #[error("Upload verification failed: {rows} rows differ after resending")]
+    VerifyFailed {
+        /// Number of rows that still differ
+        rows: usize,
+    },
+
+    /// SPI flash operation error
+    #[error("Flash error: {0}")]
+    Flash(String),
+
     /// Memory operation error
     #[error("Memory operation error: {0}")]
     Memory(String),
@@ -65,6 +130,23 @@ pub enum Error {
     Display(String),
 }
 
+impl Error {
+    /// Returns true if the error may be transient.
+    ///
+    /// Retryable errors come from the transport (timeouts, SPI and IO
+    /// failures, garbled responses) and may clear after a reset.
+    /// Everything else, such as invalid parameters, missing initialization
+    /// or wiring faults that show up as implausible device information or
+    /// registers that do not hold their value, will fail the same way on
+    /// every attempt.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Error::Spi(_) | Error::Timeout(_) | Error::NotReady | Error::Io(_) | Error::Protocol(_)
+        )
+    }
+}
+
 /// Result type alias for IT8951 operations.
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -84,6 +166,36 @@ mod tests {
         assert_eq!(err.to_string(), "Invalid parameter: test");
     }
 
+    #[test]
+    fn test_register_mismatch_display() {
+        let err = Error::RegisterMismatch {
+            register: 0x0004,
+            expected: 0x0001,
+            actual: 0xFFFF,
+        };
+        assert_eq!(
+            err.to_string(),
+            "Register 0x0004 read back 0xFFFF after writing 0x0001"
+        );
+    }
+
+    #[test]
+    fn test_error_is_retryable() {
+        assert!(Error::Timeout(1000).is_retryable());
+        assert!(Error::Spi("bus error".to_string()).is_retryable());
+        assert!(Error::NotReady.is_retryable());
+
+        assert!(!Error::InvalidParameter("test").is_retryable());
+        assert!(!Error::InvalidVcom(6000).is_retryable());
+        assert!(!Error::Init("not initialized".to_string()).is_retryable());
+        assert!(!Error::BusFault("all ones").is_retryable());
+        assert!(!Error::VcomMismatch {
+            expected: 1500,
+            actual: 0
+        }
+        .is_retryable());
+    }
+
     #[test]
     fn test_error_from_io() {
         let io_err = std::io::Error::new(std::io::ErrorKind::NotFound, "file not found");
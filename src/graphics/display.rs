@@ -7,22 +7,9 @@ use crate::device::IT8951;
 use crate::error::Result;
 use crate::graphics::Framebuffer;
 use crate::hal::{InputPin, OutputPin, SpiTransfer};
+use crate::pixel;
 use crate::types::{Area, DisplayMode, PixelFormat};
 
-/// Pack 8bpp pixel data into 4bpp format.
-///
-/// Each 8bpp pixel's top 4 bits become a nibble. Two pixels are packed per byte:
-/// high nibble = first pixel, low nibble = second pixel.
-fn pack_8bpp_to_4bpp(data: &[u8]) -> Vec<u8> {
-    let mut packed = Vec::with_capacity((data.len() + 1) / 2);
-    for pair in data.chunks(2) {
-        let hi = pair[0] >> 4;
-        let lo = if pair.len() == 2 { pair[1] >> 4 } else { 0 };
-        packed.push((hi << 4) | lo);
-    }
-    packed
-}
-
 impl<SPI, HRDY, CS, RESET> IT8951<SPI, HRDY, CS, RESET>
 where
     SPI: SpiTransfer,
@@ -35,6 +22,12 @@ where
     /// Loads the framebuffer content to the specified area on the display
     /// and optionally refreshes it with the given display mode.
     ///
+    /// The framebuffer is uploaded in the smallest pixel format that
+    /// carries every gray level `mode` can show (see
+    /// [`DisplayMode::pixel_format`]). Use
+    /// [`draw_framebuffer_with_format`](Self::draw_framebuffer_with_format)
+    /// to choose the format explicitly.
+    ///
     /// # Arguments
     ///
     /// * `framebuffer` - The framebuffer to draw
@@ -64,6 +57,29 @@ where
         area: &Area,
         refresh: bool,
         mode: DisplayMode,
+    ) -> Result<()> {
+        self.draw_framebuffer_with_format(framebuffer, area, refresh, mode, mode.pixel_format())
+    }
+
+    /// Draw a framebuffer to the display using a specific pixel format
+    ///
+    /// Like [`draw_framebuffer`](Self::draw_framebuffer), but packs the
+    /// framebuffer into `format` instead of deriving it from the mode.
+    /// Formats with fewer bits keep the most significant bits of each pixel.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// // Keep all 256 levels in the image buffer
+    /// display.draw_framebuffer_with_format(&fb, &area, true, DisplayMode::Gc16, PixelFormat::Bpp8)?;
+    /// ```
+    pub fn draw_framebuffer_with_format(
+        &mut self,
+        framebuffer: &Framebuffer,
+        area: &Area,
+        refresh: bool,
+        mode: DisplayMode,
+        format: PixelFormat,
     ) -> Result<()> {
         // Verify framebuffer dimensions match area
         if framebuffer.width() != area.width || framebuffer.height() != area.height {
@@ -76,15 +92,77 @@ where
             )));
         }
 
-        // Load the framebuffer data to the display
-        self.load_image(framebuffer.data(), area, PixelFormat::Bpp8)?;
+        let packed = pixel::pack(framebuffer.data(), area.width, area.height, format)?;
+
+        // A recovery may reset the image buffer, so a failed refresh has to
+        // load the framebuffer again
+        self.with_recovery(|display| {
+            // Load the framebuffer data to the display
+            display.load_packed(&packed, area, format)?;
 
-        // Optionally refresh the display
-        if refresh {
-            self.refresh_area(area, mode)?;
+            // Optionally refresh the display
+            if refresh {
+                display.refresh_area(area, mode)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Draw part of a framebuffer to the display
+    ///
+    /// Uploads the `src_rect` region of the framebuffer so that its top-left
+    /// corner lands at `dst`, reading rows directly from the framebuffer,
+    /// and optionally refreshes the destination area. The pixel format is
+    /// chosen from `mode` as in [`draw_framebuffer`](Self::draw_framebuffer).
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let mut fb = display.create_framebuffer()?;
+    /// fb.fill_rect(100, 100, 50, 20, 0x00);
+    ///
+    /// // Only send the changed region
+    /// let dirty = Area::new(100, 100, 50, 20);
+    /// display.draw_framebuffer_region(&fb, &dirty, (100, 100), true, DisplayMode::Du)?;
+    /// ```
+    pub fn draw_framebuffer_region(
+        &mut self,
+        framebuffer: &Framebuffer,
+        src_rect: &Area,
+        dst: (u16, u16),
+        refresh: bool,
+        mode: DisplayMode,
+    ) -> Result<()> {
+        if !src_rect.is_valid(framebuffer.width(), framebuffer.height()) {
+            return Err(crate::error::Error::Device(format!(
+                "Region {}x{} at ({}, {}) exceeds framebuffer size ({}x{})",
+                src_rect.width,
+                src_rect.height,
+                src_rect.x,
+                src_rect.y,
+                framebuffer.width(),
+                framebuffer.height()
+            )));
         }
 
-        Ok(())
+        let area = Area::new(dst.0, dst.1, src_rect.width, src_rect.height);
+        self.with_recovery(|display| {
+            display.load_image_strided(
+                framebuffer.data(),
+                framebuffer.width() as usize,
+                src_rect,
+                &area,
+                mode.pixel_format(),
+            )?;
+
+            // Optionally refresh the display
+            if refresh {
+                display.refresh_area(&area, mode)?;
+            }
+
+            Ok(())
+        })
     }
 
     /// Draw a framebuffer to the entire display
@@ -112,32 +190,27 @@ where
         framebuffer: &Framebuffer,
         mode: DisplayMode,
     ) -> Result<()> {
-        let device_info = self
-            .device_info
-            .as_ref()
-            .ok_or_else(|| crate::error::Error::Init("Device not initialized".to_string()))?;
-
-        // Verify framebuffer matches panel size
-        if framebuffer.width() != device_info.panel_width
-            || framebuffer.height() != device_info.panel_height
-        {
+        let (width, height) = self.display_size()?;
+
+        // Verify framebuffer matches the (rotated) display size
+        if framebuffer.width() != width || framebuffer.height() != height {
             return Err(crate::error::Error::Device(format!(
-                "Framebuffer size ({}x{}) doesn't match panel size ({}x{})",
+                "Framebuffer size ({}x{}) doesn't match display size ({}x{})",
                 framebuffer.width(),
                 framebuffer.height(),
-                device_info.panel_width,
-                device_info.panel_height
+                width,
+                height
             )));
         }
 
-        let area = Area::new(0, 0, device_info.panel_width, device_info.panel_height);
+        let area = Area::new(0, 0, width, height);
         self.draw_framebuffer(framebuffer, &area, true, mode)
     }
 
     /// Create a framebuffer matching the display panel size
     ///
     /// Convenience method to create a framebuffer with dimensions
-    /// matching the connected display panel.
+    /// matching the connected display panel, in rotated coordinates.
     ///
     /// # Examples
     ///
@@ -152,15 +225,9 @@ where
     /// assert_eq!(fb.height(), 600);
     /// ```
     pub fn create_framebuffer(&self) -> Result<Framebuffer> {
-        let device_info = self
-            .device_info
-            .as_ref()
-            .ok_or_else(|| crate::error::Error::Init("Device not initialized".to_string()))?;
-
-        Ok(Framebuffer::new(
-            device_info.panel_width,
-            device_info.panel_height,
-        ))
+        let (width, height) = self.display_size()?;
+
+        Ok(Framebuffer::new(width, height))
     }
 }
 
@@ -168,8 +235,39 @@ where
 mod tests {
     use super::*;
     use crate::device::IT8951Builder;
+    use crate::hal::mock::{MockInputPin, MockOutputPin, MockSpi};
+    use crate::hal::PinState;
+    use crate::protocol::{decode, Operation, Transport};
     use crate::types::DeviceInfo;
 
+    fn upload_ops(mode: DisplayMode, format: Option<PixelFormat>) -> Vec<Operation> {
+        let spi = MockSpi::new();
+        let mut device = IT8951Builder::new().build_mock().unwrap();
+        device.transport = Transport::new(
+            spi.clone(),
+            MockInputPin::new(PinState::High),
+            MockOutputPin::new(PinState::High),
+        );
+        device.device_info = Some(DeviceInfo {
+            panel_width: 800,
+            panel_height: 600,
+            img_buf_addr: 0x00100000,
+            fw_version: "1.0".to_string(),
+            lut_version: "1.0".to_string(),
+        });
+
+        let fb = Framebuffer::new(16, 2);
+        let area = Area::new(0, 0, 16, 2);
+        match format {
+            Some(format) => device
+                .draw_framebuffer_with_format(&fb, &area, false, mode, format)
+                .unwrap(),
+            None => device.draw_framebuffer(&fb, &area, false, mode).unwrap(),
+        }
+
+        decode::decode(&spi.get_transfers())
+    }
+
     #[test]
     fn test_draw_framebuffer() {
         let mut device = IT8951Builder::new().build_mock().unwrap();
@@ -192,6 +290,37 @@ mod tests {
             .unwrap();
     }
 
+    #[test]
+    fn test_draw_framebuffer_picks_format_for_mode() {
+        for (mode, format, words) in [
+            (DisplayMode::Gc16, PixelFormat::Bpp4, 8),
+            (DisplayMode::Du, PixelFormat::Bpp2, 4),
+        ] {
+            let ops = upload_ops(mode, None);
+            assert!(ops.iter().any(|op| matches!(
+                op,
+                Operation::LoadImageArea { format: f, .. } if *f == format
+            )));
+            assert!(ops.iter().any(|op| matches!(
+                op,
+                Operation::DataBatch { words: w } if *w == words
+            )));
+        }
+    }
+
+    #[test]
+    fn test_draw_framebuffer_with_explicit_format() {
+        let ops = upload_ops(DisplayMode::A2, Some(PixelFormat::Bpp8));
+        assert!(ops.iter().any(|op| matches!(
+            op,
+            Operation::LoadImageArea {
+                format: PixelFormat::Bpp8,
+                ..
+            }
+        )));
+        assert!(ops.contains(&Operation::DataBatch { words: 16 }));
+    }
+
     #[test]
     fn test_draw_framebuffer_dimension_mismatch() {
         let mut device = IT8951Builder::new().build_mock().unwrap();
@@ -268,4 +397,35 @@ mod tests {
         let fb = Framebuffer::new(100, 100);
         assert!(device.draw_framebuffer_full(&fb, DisplayMode::Gc16).is_err());
     }
+
+    #[test]
+    fn test_draw_framebuffer_region() {
+        let mut device = IT8951Builder::new().build_mock().unwrap();
+
+        device.device_info = Some(DeviceInfo {
+            panel_width: 800,
+            panel_height: 600,
+            img_buf_addr: 0x00100000,
+            fw_version: "1.0".to_string(),
+            lut_version: "1.0".to_string(),
+        });
+
+        let fb = Framebuffer::new(200, 100);
+        let region = Area::new(50, 50, 20, 10);
+        device
+            .draw_framebuffer_region(&fb, &region, (700, 500), true, DisplayMode::Du)
+            .unwrap();
+
+        // Region outside the framebuffer
+        let outside = Area::new(190, 0, 20, 10);
+        assert!(device
+            .draw_framebuffer_region(&fb, &outside, (0, 0), false, DisplayMode::Du)
+            .is_err());
+
+        // Destination outside the panel
+        assert!(matches!(
+            device.draw_framebuffer_region(&fb, &region, (790, 0), false, DisplayMode::Du),
+            Err(crate::error::Error::InvalidArea(_))
+        ));
+    }
 }
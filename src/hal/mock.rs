@@ -1,7 +1,13 @@
 //! Mock HAL implementations for testing.
 
-use crate::error::Result;
+use crate::device::IT8951;
+use crate::error::{Error, Result};
 use crate::hal::{BitOrder, InputPin, OutputPin, PinState, SpiInterface, SpiMode, SpiTransfer};
+use crate::protocol::decode::arg_count;
+use crate::protocol::transport::{PREAMBLE_READ_DATA, PREAMBLE_WRITE_CMD, PREAMBLE_WRITE_DATA};
+use crate::protocol::{Command, Register, UserCommand};
+use crate::types::DeviceInfo;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 
 /// Mock SPI interface for testing.
@@ -14,6 +20,8 @@ pub struct MockSpi {
     pub transfers: Arc<Mutex<Vec<Vec<u8>>>>,
     /// Responses to return for transfers
     pub responses: Arc<Mutex<Vec<Vec<u8>>>>,
+    /// Number of upcoming transfers that should fail
+    pub failures: Arc<Mutex<usize>>,
 }
 
 impl MockSpi {
@@ -25,9 +33,25 @@ impl MockSpi {
             bit_order: BitOrder::MsbFirst,
             transfers: Arc::new(Mutex::new(Vec::new())),
             responses: Arc::new(Mutex::new(Vec::new())),
+            failures: Arc::new(Mutex::new(0)),
         }
     }
 
+    /// Makes the next `count` transfers fail with an SPI error.
+    pub fn fail_next(&mut self, count: usize) {
+        *self.failures.lock().unwrap() = count;
+    }
+
+    /// Returns an error if an injected failure is pending.
+    fn take_failure(&self) -> Result<()> {
+        let mut failures = self.failures.lock().unwrap();
+        if *failures > 0 {
+            *failures -= 1;
+            return Err(Error::Spi("injected failure".to_string()));
+        }
+        Ok(())
+    }
+
     /// Adds a response to be returned by the next transfer.
     pub fn add_response(&mut self, response: Vec<u8>) {
         self.responses.lock().unwrap().push(response);
@@ -53,6 +77,7 @@ impl Default for MockSpi {
 
 impl SpiTransfer for MockSpi {
     fn transfer_byte(&mut self, byte: u8) -> Result<u8> {
+        self.take_failure()?;
         let mut transfers = self.transfers.lock().unwrap();
         transfers.push(vec![byte]);
 
@@ -67,6 +92,7 @@ impl SpiTransfer for MockSpi {
     }
 
     fn transfer(&mut self, buffer: &[u8]) -> Result<Vec<u8>> {
+        self.take_failure()?;
         let mut transfers = self.transfers.lock().unwrap();
         transfers.push(buffer.to_vec());
 
@@ -199,6 +225,443 @@ impl OutputPin for MockOutputPin {
     }
 }
 
+/// Joins the low and high halves of a 32-bit command argument.
+fn join(low: u16, high: u16) -> usize {
+    (high as usize) << 16 | low as usize
+}
+
+/// Progress of an emulated LoadImageArea transfer.
+#[derive(Debug)]
+struct ImageLoad {
+    base: u32,
+    arg: u16,
+    area: [u16; 4],
+    /// Index of the next word within the transfer
+    word: usize,
+}
+
+/// Emulated controller state shared between a `MockController` and its clones.
+#[derive(Debug, Default)]
+struct ControllerState {
+    device_info: Vec<u16>,
+    registers: HashMap<u16, u16>,
+    read_only: HashSet<u16>,
+    memory: Vec<u8>,
+    flash: Vec<u8>,
+    vcom: u16,
+    temperature: i16,
+    forced_temperature: Option<i16>,
+    command: Option<(u16, Vec<u16>)>,
+    burst_addr: u32,
+    image_load: Option<ImageLoad>,
+    speed_hz: u32,
+    max_reliable_hz: Option<u32>,
+    corrupt_words: usize,
+    failures: usize,
+    transfers: Vec<Vec<u8>>,
+}
+
+impl ControllerState {
+    /// Applies the side effects of a command once all arguments arrived.
+    fn complete_command(&mut self) {
+        let Some((code, args)) = &self.command else {
+            return;
+        };
+        let Some(expected) = arg_count(*code, args) else {
+            return;
+        };
+        if args.len() != expected {
+            return;
+        }
+
+        match (Command::from_u16(*code), UserCommand::from_u16(*code)) {
+            (Some(Command::RegWrite), _) if !self.read_only.contains(&args[0]) => {
+                self.registers.insert(args[0], args[1]);
+            }
+            (Some(Command::MemBurstWrite | Command::MemBurstReadTrigger), _) => {
+                self.burst_addr = (args[1] as u32) << 16 | args[0] as u32;
+            }
+            (Some(Command::LoadImageArea), _) => {
+                let reg = |addr| self.registers.get(&addr).copied().unwrap_or(0) as u32;
+                self.image_load = Some(ImageLoad {
+                    base: reg(Register::LISAR.addr() + 2) << 16 | reg(Register::LISAR.addr()),
+                    arg: args[0],
+                    area: [args[1], args[2], args[3], args[4]],
+                    word: 0,
+                });
+            }
+            (_, Some(UserCommand::Vcom)) if args[0] == 1 || args[0] == 2 => {
+                self.vcom = args[1];
+            }
+            (_, Some(UserCommand::Temperature)) if args[0] == 1 => {
+                self.forced_temperature = Some(args[1] as i16);
+            }
+            (_, Some(UserCommand::SpiFlashErase)) => {
+                let (start, len) = (join(args[0], args[1]), join(args[2], args[3]));
+                if let Some(range) = self.flash.get_mut(start..start + len) {
+                    range.fill(0xFF);
+                }
+            }
+            (_, Some(UserCommand::SpiFlashRead)) => {
+                let (flash, addr) = (join(args[0], args[1]), join(args[2], args[3]));
+                let len = join(args[4], args[5]);
+                self.ensure_memory(addr + len);
+                if let Some(range) = self.flash.get(flash..flash + len) {
+                    self.memory[addr..addr + len].copy_from_slice(range);
+                }
+            }
+            (_, Some(UserCommand::SpiFlashWrite)) => {
+                let (flash, addr) = (join(args[0], args[1]), join(args[2], args[3]));
+                let len = join(args[4], args[5]);
+                self.ensure_memory(addr + len);
+                if let Some(range) = self.flash.get_mut(flash..flash + len) {
+                    // Programming can only clear bits
+                    for (cell, byte) in range.iter_mut().zip(&self.memory[addr..addr + len]) {
+                        *cell &= byte;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Handles data words that are not command arguments.
+    fn write_data(&mut self, words: &[u16]) {
+        // Above the reliable clock every word arrives with a flipped bit
+        let slow = self.max_reliable_hz.is_some_and(|max| self.speed_hz > max);
+        let words: Vec<u16> = words
+            .iter()
+            .map(|&word| {
+                let noisy = slow || self.corrupt_words > 0;
+                self.corrupt_words = self.corrupt_words.saturating_sub(1);
+                if noisy {
+                    word ^ 0x0100
+                } else {
+                    word
+                }
+            })
+            .collect();
+
+        match self.command.as_ref().and_then(|(code, _)| Command::from_u16(*code)) {
+            Some(Command::MemBurstWrite) => {
+                for word in words {
+                    let addr = self.burst_addr as usize;
+                    self.ensure_memory(addr + 2);
+                    // Memory is little-endian within each word
+                    self.memory[addr] = (word & 0xFF) as u8;
+                    self.memory[addr + 1] = (word >> 8) as u8;
+                    self.burst_addr += 2;
+                }
+            }
+            Some(Command::LoadImageArea) => {
+                for word in words {
+                    self.load_image_word(word);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Unpacks one word of a LoadImageArea transfer into the image buffer.
+    ///
+    /// The image buffer holds one byte per pixel with a pitch of the panel
+    /// width. Each pixel keeps the transmitted value in its most significant
+    /// bits, and the rotation in the load argument is applied.
+    fn load_image_word(&mut self, word: u16) {
+        let Some(load) = self.image_load.as_mut() else {
+            return;
+        };
+        let (endian, format, rotate) = (load.arg >> 8 & 1, load.arg >> 4 & 3, load.arg & 3);
+        let [x, y, width, _] = load.area.map(|v| v as usize);
+        let bits = match format {
+            0 => 2,
+            3 => 8,
+            _ => 4,
+        };
+        let per_word = 16 / bits;
+        let row_words = (width + per_word - 1) / per_word;
+        let (row, first) = (load.word / row_words, load.word % row_words * per_word);
+        load.word += 1;
+
+        let word = if endian == 1 { word.swap_bytes() } else { word };
+        let base = load.base as usize;
+        let panel_w = self.device_info.first().copied().unwrap_or(0) as usize;
+        let panel_h = self.device_info.get(1).copied().unwrap_or(0) as usize;
+
+        for i in 0..per_word {
+            let col = first + i;
+            if col >= width {
+                break;
+            }
+            let slot = (word >> (i * bits)) & ((1 << bits) - 1);
+            let (lx, ly) = (x + col, y + row);
+            let (px, py) = match rotate {
+                1 => (panel_w.wrapping_sub(1 + ly), lx),
+                2 => (panel_w.wrapping_sub(1 + lx), panel_h.wrapping_sub(1 + ly)),
+                3 => (ly, panel_h.wrapping_sub(1 + lx)),
+                _ => (lx, ly),
+            };
+            let addr = base + py * panel_w + px;
+            self.ensure_memory(addr + 1);
+            self.memory[addr] = (slot << (8 - bits)) as u8;
+        }
+    }
+
+    /// Produces the words returned by a read for the current command.
+    fn read_data(&mut self, count: usize) -> Vec<u16> {
+        let (code, args) = self.command.clone().unwrap_or_default();
+        let mut words = match (Command::from_u16(code), UserCommand::from_u16(code)) {
+            (Some(Command::RegRead), _) => {
+                vec![self.registers.get(&args[0]).copied().unwrap_or(0)]
+            }
+            (Some(Command::MemBurstReadStart), _) => {
+                let start = self.burst_addr as usize;
+                self.ensure_memory(start + count * 2);
+                self.burst_addr += (count * 2) as u32;
+                self.memory[start..start + count * 2]
+                    .chunks_exact(2)
+                    .map(|pair| (pair[1] as u16) << 8 | pair[0] as u16)
+                    .collect()
+            }
+            (_, Some(UserCommand::GetDevInfo)) => self.device_info.clone(),
+            (_, Some(UserCommand::Vcom)) => vec![self.vcom],
+            (_, Some(UserCommand::Temperature)) => vec![
+                self.temperature as u16,
+                self.forced_temperature.unwrap_or(0) as u16,
+            ],
+            _ => Vec::new(),
+        };
+        words.resize(count, 0);
+        words
+    }
+
+    fn ensure_memory(&mut self, len: usize) {
+        if self.memory.len() < len {
+            self.memory.resize(len, 0);
+        }
+    }
+}
+
+/// Mock SPI interface that emulates an IT8951 controller.
+///
+/// Unlike [`MockSpi`], which replays canned responses, this decodes the
+/// preamble protocol and keeps registers, VCOM, temperature and SDRAM
+/// contents, so register read-back and memory bursts behave like hardware.
+/// Image loads are unpacked into the image buffer in SDRAM.
+#[derive(Debug, Clone)]
+pub struct MockController {
+    state: Arc<Mutex<ControllerState>>,
+}
+
+impl MockController {
+    /// Creates a controller reporting an 800x600 panel.
+    pub fn new() -> Self {
+        let controller = Self {
+            state: Arc::new(Mutex::new(ControllerState {
+                vcom: 1500,
+                temperature: 25,
+                ..Default::default()
+            })),
+        };
+        controller.set_device_info(800, 600, IMG_BUF_ADDR, "SWv_0.1.1", "M641");
+        controller
+    }
+
+    /// Sets the device information returned by GetDevInfo.
+    pub fn set_device_info(&self, width: u16, height: u16, img_buf_addr: u32, fw: &str, lut: &str) {
+        let mut words = vec![
+            width,
+            height,
+            (img_buf_addr & 0xFFFF) as u16,
+            (img_buf_addr >> 16) as u16,
+        ];
+        for version in [fw, lut] {
+            let mut bytes = version.as_bytes().to_vec();
+            bytes.resize(16, 0);
+            words.extend(bytes.chunks(2).map(|pair| (pair[1] as u16) << 8 | pair[0] as u16));
+        }
+        self.state.lock().unwrap().device_info = words;
+    }
+
+    /// Sets the raw device information words returned by GetDevInfo.
+    pub fn set_device_info_raw(&self, words: Vec<u16>) {
+        self.state.lock().unwrap().device_info = words;
+    }
+
+    /// Returns the current value of a register.
+    pub fn register(&self, register: Register) -> u16 {
+        let state = self.state.lock().unwrap();
+        state.registers.get(&register.addr()).copied().unwrap_or(0)
+    }
+
+    /// Sets a register value.
+    pub fn set_register(&self, register: Register, value: u16) {
+        self.state
+            .lock()
+            .unwrap()
+            .registers
+            .insert(register.addr(), value);
+    }
+
+    /// Makes writes to a register have no effect, like an unimplemented bit.
+    pub fn set_read_only(&self, register: Register) {
+        self.state
+            .lock()
+            .unwrap()
+            .read_only
+            .insert(register.addr());
+    }
+
+    /// Returns a copy of `len` bytes of SDRAM starting at `addr`.
+    pub fn memory(&self, addr: u32, len: usize) -> Vec<u8> {
+        let mut state = self.state.lock().unwrap();
+        let start = addr as usize;
+        state.ensure_memory(start + len);
+        state.memory[start..start + len].to_vec()
+    }
+
+    /// Sets the contents of the emulated SPI NOR flash.
+    pub fn set_flash(&self, data: Vec<u8>) {
+        self.state.lock().unwrap().flash = data;
+    }
+
+    /// Returns a copy of the emulated SPI NOR flash.
+    pub fn flash(&self) -> Vec<u8> {
+        self.state.lock().unwrap().flash.clone()
+    }
+
+    /// Returns the current VCOM value.
+    pub fn vcom(&self) -> u16 {
+        self.state.lock().unwrap().vcom
+    }
+
+    /// Sets the temperature reported by the onboard sensor.
+    pub fn set_temperature(&self, celsius: i16) {
+        self.state.lock().unwrap().temperature = celsius;
+    }
+
+    /// Corrupts data written while the SPI clock is above `hz`, emulating
+    /// signal integrity limits.
+    pub fn set_max_reliable_hz(&self, hz: u32) {
+        self.state.lock().unwrap().max_reliable_hz = Some(hz);
+    }
+
+    /// Flips a bit in each of the next `count` data words written to memory
+    /// or the image buffer, emulating a noisy cable.
+    pub fn corrupt_next_words(&self, count: usize) {
+        self.state.lock().unwrap().corrupt_words = count;
+    }
+
+    /// Makes the next `count` transfers fail with an SPI error.
+    pub fn fail_next(&self, count: usize) {
+        self.state.lock().unwrap().failures = count;
+    }
+
+    /// Returns all recorded transfers.
+    pub fn get_transfers(&self) -> Vec<Vec<u8>> {
+        self.state.lock().unwrap().transfers.clone()
+    }
+
+    /// Clears all recorded transfers.
+    pub fn clear_transfers(&self) {
+        self.state.lock().unwrap().transfers.clear();
+    }
+}
+
+impl Default for MockController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpiTransfer for MockController {
+    fn transfer_byte(&mut self, _byte: u8) -> Result<u8> {
+        Ok(0x00)
+    }
+
+    fn set_speed(&mut self, speed_hz: u32) -> Result<()> {
+        self.state.lock().unwrap().speed_hz = speed_hz;
+        Ok(())
+    }
+
+    fn transfer(&mut self, buffer: &[u8]) -> Result<Vec<u8>> {
+        let mut state = self.state.lock().unwrap();
+        if state.failures > 0 {
+            state.failures -= 1;
+            return Err(Error::Spi("injected failure".to_string()));
+        }
+        state.transfers.push(buffer.to_vec());
+
+        let mut rx = vec![0x00; buffer.len()];
+        if buffer.len() < 4 {
+            return Ok(rx);
+        }
+
+        let preamble = (buffer[0] as u16) << 8 | buffer[1] as u16;
+        let words: Vec<u16> = buffer[2..]
+            .chunks_exact(2)
+            .map(|pair| (pair[0] as u16) << 8 | pair[1] as u16)
+            .collect();
+
+        match preamble {
+            PREAMBLE_WRITE_CMD => {
+                state.command = Some((words[0], Vec::new()));
+                state.complete_command();
+            }
+            PREAMBLE_WRITE_DATA => {
+                let collecting = state.command.as_ref().is_some_and(|(code, args)| {
+                    arg_count(*code, args).map_or(true, |expected| args.len() < expected)
+                });
+                if collecting && words.len() == 1 {
+                    if let Some((_, args)) = state.command.as_mut() {
+                        args.push(words[0]);
+                    }
+                    state.complete_command();
+                } else {
+                    state.write_data(&words);
+                }
+            }
+            PREAMBLE_READ_DATA => {
+                let data = state.read_data(words.len() - 1);
+                for (i, word) in data.iter().enumerate() {
+                    rx[4 + i * 2] = (word >> 8) as u8;
+                    rx[5 + i * 2] = (word & 0xFF) as u8;
+                }
+            }
+            _ => {}
+        }
+
+        Ok(rx)
+    }
+}
+
+/// Image buffer address of the emulated 800x600 panel.
+pub const IMG_BUF_ADDR: u32 = 0x001236E0;
+
+/// Driver connected to a [`MockController`].
+pub type MockDevice = IT8951<MockController, MockInputPin, MockOutputPin, MockOutputPin>;
+
+/// Creates a controller and a driver that already knows the 800x600 panel,
+/// without sending the commands `init()` would.
+pub fn mock_device() -> (MockController, MockDevice) {
+    let controller = MockController::new();
+    let mut device = IT8951::new(
+        controller.clone(),
+        MockInputPin::new(PinState::High),
+        MockOutputPin::new(PinState::High),
+        MockOutputPin::new(PinState::High),
+        1500,
+    );
+    device.device_info = Some(DeviceInfo {
+        panel_width: 800,
+        panel_height: 600,
+        img_buf_addr: IMG_BUF_ADDR,
+        fw_version: "test".to_string(),
+        lut_version: "test".to_string(),
+    });
+    (controller, device)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -216,6 +679,53 @@ mod tests {
         assert_eq!(transfers[0], vec![0xAB, 0xCD]);
     }
 
+    #[test]
+    fn test_mock_spi_fail_next() {
+        let mut spi = MockSpi::new();
+        spi.fail_next(1);
+
+        assert!(matches!(spi.transfer(&[0x00]), Err(Error::Spi(_))));
+        assert!(spi.transfer(&[0x00]).is_ok());
+        assert_eq!(spi.get_transfers().len(), 1);
+    }
+
+    #[test]
+    fn test_mock_controller_registers() {
+        use crate::protocol::Transport;
+
+        let controller = MockController::new();
+        let hrdy = MockInputPin::new(PinState::High);
+        let cs = MockOutputPin::new(PinState::High);
+        let mut transport = Transport::new(controller.clone(), hrdy, cs);
+
+        transport.write_register(Register::LISAR, 0x36E0).unwrap();
+        assert_eq!(controller.register(Register::LISAR), 0x36E0);
+        assert_eq!(transport.read_register(Register::LISAR).unwrap(), 0x36E0);
+    }
+
+    #[test]
+    fn test_mock_controller_memory_burst() {
+        use crate::protocol::Transport;
+
+        let controller = MockController::new();
+        let hrdy = MockInputPin::new(PinState::High);
+        let cs = MockOutputPin::new(PinState::High);
+        let mut transport = Transport::new(controller.clone(), hrdy, cs);
+
+        transport
+            .write_command_with_args(Command::MemBurstWrite, &[0x0100, 0x0000, 2, 0])
+            .unwrap();
+        transport.write_data_batch(&[0x2211, 0x4433]).unwrap();
+        transport.write_command(Command::MemBurstEnd).unwrap();
+        assert_eq!(controller.memory(0x0100, 4), vec![0x11, 0x22, 0x33, 0x44]);
+
+        transport
+            .write_command_with_args(Command::MemBurstReadTrigger, &[0x0100, 0x0000, 2, 0])
+            .unwrap();
+        transport.write_command(Command::MemBurstReadStart).unwrap();
+        assert_eq!(transport.read_data_batch(2).unwrap(), vec![0x2211, 0x4433]);
+    }
+
     #[test]
     fn test_mock_spi_config() {
         let mut spi = MockSpi::new();
@@ -21,7 +21,7 @@
 //!     let mut display = IT8951::builder()
 //!         .spi_device("/dev/spidev0.0")?
 //!         .spi_hz(24_000_000)
-//!         .vcom(1500)
+//!         .vcom_voltage("-1.50V".parse()?)
 //!         .build()?;
 //!
 //!     display.init()?;
@@ -41,7 +41,11 @@
 //! - [`protocol`] - IT8951 communication protocol
 //! - [`device`] - Device management and initialization
 //! - [`display`] - Display operations
+//! - [`panel`] - Panel profiles with geometry and quirks
+//! - [`pixel`] - Pixel packing for the controller's wire formats
 //! - [`graphics`] - Drawing primitives and framebuffer
+//! - [`waveform`] - Waveform file parsing and mode discovery
+//! - `config` - Configuration files (requires the `config` feature)
 //!
 //! # Implementation Status
 //!
@@ -90,28 +94,43 @@
 #![warn(missing_docs)]
 #![warn(missing_debug_implementations)]
 
+#[cfg(feature = "config")]
+pub mod config;
 pub mod device;
 pub mod display;
 pub mod error;
 pub mod graphics;
 pub mod hal;
+pub mod panel;
+pub mod pixel;
 pub mod protocol;
 pub mod types;
+pub mod waveform;
 
 // Re-export commonly used types
-pub use device::{IT8951, IT8951Builder};
+#[cfg(feature = "config")]
+pub use config::DisplayConfig;
+pub use device::{
+    Capabilities, DiagnosticReport, FlashBackup, IdlePolicy, IT8951, IT8951Builder, PowerState,
+    Ready, RecoveryPolicy, Session, SpiCalibration,
+};
+pub use display::{AlignPadding, VerifyMode, VerifyPolicy, VerifyStats};
 pub use error::{Error, Result};
 pub use graphics::Framebuffer;
 pub use hal::{
     BitOrder, InputPin, LinuxInputPin, LinuxOutputPin, LinuxSpi, OutputPin, PinState, SpiInterface,
     SpiMode, SpiTransfer,
 };
+pub use panel::PanelProfile;
 pub use protocol::{Command, Register, Transport, UserCommand};
-pub use types::{Area, DeviceInfo, DisplayMode, Endian, LoadImageInfo, PixelFormat, Rotation};
+pub use types::{
+    Area, DeviceInfo, DisplayMode, Endian, FirmwareVersion, LoadImageInfo, LutVersion, PixelFormat,
+    Rotation, Vcom,
+};
 
 // Re-export mock implementations for testing
 #[cfg(test)]
-pub use hal::mock::{MockInputPin, MockOutputPin, MockSpi};
+pub use hal::mock::{MockController, MockInputPin, MockOutputPin, MockSpi};
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,260 @@
+//! Panel profiles.
+//!
+//! The IT8951 drives panels of several sizes, which differ in more than
+//! their resolution: the waveforms shipped with them number their modes
+//! differently, some are wired with the source lines running backwards so
+//! the image appears mirrored, and their VCOM falls in different ranges.
+//! A [`PanelProfile`] collects these properties. The device picks one of
+//! the built-in profiles from the panel size reported in [`DeviceInfo`],
+//! or uses one chosen with
+//! [`IT8951::set_panel`](crate::IT8951::set_panel).
+//!
+//! | Name   | Panel               | Resolution | Known LUTs                 | Mirrored |
+//! |--------|---------------------|------------|----------------------------|----------|
+//! | `6`    | 6" (ED060SC7)       | 800x600    | `M641`                     | no       |
+//! | `6hd`  | 6" HD (ED060KC1)    | 1448x1072  | `M841_TFAB512`, `M641`     | no       |
+//! | `7.8`  | 7.8" (ED078KC1)     | 1872x1404  | `M841_TFA2812`             | no       |
+//! | `9.7`  | 9.7" (ED097TC2)     | 1200x825   |                            | no       |
+//! | `10.3` | 10.3" (ES103TC1)    | 1872x1404  | `M841_TFA5210`             | yes      |
+//! | `13.3` | 13.3" (ES133UT1)    | 1600x1200  |                            | no       |
+//!
+//! The mode numbering belongs to the waveform rather than the panel, so it
+//! is taken from the LUT version (see [`LutVersion::mode_count`]) unless a
+//! profile fixes it.
+//!
+//! With the `config` feature, profiles can be read from configuration
+//! files, for panels that are not built in.
+
+use crate::types::{DeviceInfo, DisplayMode, Vcom};
+
+/// Geometry and quirks of one panel type.
+///
+/// # Examples
+///
+/// ```
+/// use it8951::PanelProfile;
+///
+/// let panel = PanelProfile::by_name("10.3").unwrap();
+/// assert_eq!((panel.width, panel.height), (1872, 1404));
+/// assert!(panel.mirrored);
+///
+/// // A variant of a built-in profile
+/// let unmirrored = PanelProfile { mirrored: false, ..panel };
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "config", serde(default, deny_unknown_fields))]
+pub struct PanelProfile {
+    /// Short name used to select the profile, e.g. in configuration files
+    pub name: String,
+
+    /// Human readable description
+    pub description: String,
+
+    /// Physical panel width in pixels
+    pub width: u16,
+
+    /// Physical panel height in pixels
+    pub height: u16,
+
+    /// LUT versions shipped with the panel, used to tell apart panels of
+    /// the same size
+    pub lut_versions: Vec<String>,
+
+    /// Typical VCOM range in millivolts (magnitude, inclusive)
+    pub vcom_range: (u16, u16),
+
+    /// Whether the panel's x axis runs backwards, so images must be
+    /// mirrored on the host
+    pub mirrored: bool,
+
+    /// Required alignment of x and width, in pixels
+    pub pixel_alignment: u16,
+
+    /// Required alignment of x and width for 1bpp updates, in pixels
+    pub pixel_alignment_1bpp: u16,
+
+    /// Number of modes in the panel's waveform, which determines the mode
+    /// numbering (see [`ModeName::for_mode`](crate::waveform::ModeName::for_mode));
+    /// taken from the LUT version when unset
+    #[cfg_attr(feature = "config", serde(skip_serializing_if = "Option::is_none"))]
+    pub mode_count: Option<usize>,
+
+    /// Mode recommended for regular updates
+    pub default_mode: DisplayMode,
+}
+
+impl Default for PanelProfile {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            description: String::new(),
+            width: 0,
+            height: 0,
+            lut_versions: Vec::new(),
+            vcom_range: (1000, 2500),
+            mirrored: false,
+            pixel_alignment: 4,
+            pixel_alignment_1bpp: 32,
+            mode_count: None,
+            default_mode: DisplayMode::Gc16,
+        }
+    }
+}
+
+impl PanelProfile {
+    /// Returns the built-in profiles.
+    pub fn builtin() -> Vec<PanelProfile> {
+        vec![
+            PanelProfile {
+                name: "6".to_string(),
+                description: "6\" 800x600 (ED060SC7)".to_string(),
+                width: 800,
+                height: 600,
+                lut_versions: vec!["M641".to_string()],
+                vcom_range: (1000, 2500),
+                mirrored: false,
+                ..Default::default()
+            },
+            PanelProfile {
+                name: "6hd".to_string(),
+                description: "6\" HD 1448x1072 (ED060KC1)".to_string(),
+                width: 1448,
+                height: 1072,
+                lut_versions: vec!["M841_TFAB512".to_string(), "M641".to_string()],
+                vcom_range: (1000, 2500),
+                mirrored: false,
+                ..Default::default()
+            },
+            PanelProfile {
+                name: "7.8".to_string(),
+                description: "7.8\" 1872x1404 (ED078KC1)".to_string(),
+                width: 1872,
+                height: 1404,
+                lut_versions: vec!["M841_TFA2812".to_string()],
+                vcom_range: (1000, 2500),
+                mirrored: false,
+                ..Default::default()
+            },
+            PanelProfile {
+                name: "9.7".to_string(),
+                description: "9.7\" 1200x825 (ED097TC2)".to_string(),
+                width: 1200,
+                height: 825,
+                lut_versions: Vec::new(),
+                vcom_range: (1000, 2500),
+                mirrored: false,
+                ..Default::default()
+            },
+            PanelProfile {
+                name: "10.3".to_string(),
+                description: "10.3\" 1872x1404 (ES103TC1)".to_string(),
+                width: 1872,
+                height: 1404,
+                lut_versions: vec!["M841_TFA5210".to_string()],
+                vcom_range: (1000, 2500),
+                mirrored: true,
+                ..Default::default()
+            },
+            PanelProfile {
+                name: "13.3".to_string(),
+                description: "13.3\" 1600x1200 (ES133UT1)".to_string(),
+                width: 1600,
+                height: 1200,
+                lut_versions: Vec::new(),
+                vcom_range: (1000, 2600),
+                mirrored: false,
+                ..Default::default()
+            },
+        ]
+    }
+
+    /// Looks up a built-in profile by name.
+    ///
+    /// Names are matched ignoring case and a trailing `"` or `in`, so
+    /// `"10.3"`, `"10.3\""` and `"10.3in"` are the same.
+    pub fn by_name(name: &str) -> Option<Self> {
+        let name = name.trim().to_ascii_lowercase();
+        let name = name
+            .strip_suffix('"')
+            .or_else(|| name.strip_suffix("in"))
+            .unwrap_or(&name);
+        Self::builtin()
+            .into_iter()
+            .find(|profile| profile.name == name)
+    }
+
+    /// Finds the built-in profile for a device.
+    ///
+    /// Profiles are matched on the panel size. Where several panels share
+    /// a size, the one listing the device's LUT version is preferred,
+    /// falling back to the first.
+    pub fn for_device(info: &DeviceInfo) -> Option<Self> {
+        let candidates: Vec<_> = Self::builtin()
+            .into_iter()
+            .filter(|p| p.width == info.panel_width && p.height == info.panel_height)
+            .collect();
+        let matching = candidates
+            .iter()
+            .position(|p| p.lut_versions.contains(&info.lut_version))
+            .unwrap_or(0);
+        candidates.into_iter().nth(matching)
+    }
+
+    /// Returns whether a VCOM lies within the panel's typical range.
+    pub fn vcom_in_range(&self, vcom: Vcom) -> bool {
+        (self.vcom_range.0..=self.vcom_range.1).contains(&vcom.millivolts())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(width: u16, height: u16, lut: &str) -> DeviceInfo {
+        DeviceInfo {
+            panel_width: width,
+            panel_height: height,
+            img_buf_addr: 0x001236E0,
+            fw_version: "SWv_0.2.1T".to_string(),
+            lut_version: lut.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_by_name() {
+        assert_eq!(PanelProfile::by_name("6").unwrap().width, 800);
+        assert_eq!(PanelProfile::by_name("6HD").unwrap().width, 1448);
+        assert_eq!(PanelProfile::by_name("10.3\"").unwrap().name, "10.3");
+        assert_eq!(PanelProfile::by_name("13.3in").unwrap().height, 1200);
+        assert!(PanelProfile::by_name("5").is_none());
+    }
+
+    #[test]
+    fn test_for_device() {
+        let six = PanelProfile::for_device(&info(800, 600, "M641")).unwrap();
+        assert_eq!(six.name, "6");
+
+        let ten = PanelProfile::for_device(&info(1872, 1404, "M841_TFA5210")).unwrap();
+        assert_eq!(ten.name, "10.3");
+        assert!(ten.mirrored);
+
+        let unknown_lut = PanelProfile::for_device(&info(1872, 1404, "M841")).unwrap();
+        assert_eq!(unknown_lut.name, "7.8");
+
+        let six_hd = PanelProfile::for_device(&info(1448, 1072, "M841_TFAB512")).unwrap();
+        assert_eq!(six_hd.name, "6hd");
+
+        assert!(PanelProfile::for_device(&info(640, 480, "M641")).is_none());
+        assert!(PanelProfile::builtin()
+            .iter()
+            .all(|p| p.mode_count.is_none()));
+    }
+
+    #[test]
+    fn test_vcom_range() {
+        let panel = PanelProfile::by_name("6").unwrap();
+        assert!(panel.vcom_in_range(Vcom::from_millivolts(1530).unwrap()));
+        assert!(!panel.vcom_in_range(Vcom::from_millivolts(3000).unwrap()));
+    }
+}
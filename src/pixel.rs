@@ -0,0 +1,344 @@
+//! Pixel packing for the IT8951 wire formats.
+//!
+//! The IT8951 receives image data as 16-bit words. Pixels are packed into
+//! each word starting at the least significant bits, and every row starts
+//! on a new word, so rows whose width is not a multiple of the pixels per
+//! word are padded with zero bits.
+//!
+//! Packed buffers produced here are in little-endian word order (the low
+//! byte of each word comes first), which is what
+//! [`IT8951::load_image`](crate::IT8951::load_image) expects by default.
+//!
+//! | Format | Bits per slot | Pixels per word |
+//! |--------|---------------|-----------------|
+//! | 8bpp   | 8             | 2               |
+//! | 4bpp   | 4             | 4               |
+//! | 3bpp   | 4             | 4               |
+//! | 2bpp   | 2             | 8               |
+//!
+//! 3bpp pixels occupy 4-bit slots like 4bpp; the controller ignores the
+//! least significant bit of each slot.
+
+use crate::error::{Error, Result};
+use crate::types::PixelFormat;
+
+/// Returns the number of bits each pixel occupies on the wire.
+pub fn slot_bits(format: PixelFormat) -> usize {
+    match format {
+        PixelFormat::Bpp8 => 8,
+        PixelFormat::Bpp4 | PixelFormat::Bpp3 => 4,
+        PixelFormat::Bpp2 => 2,
+    }
+}
+
+/// Returns the number of pixels packed into each 16-bit word.
+pub fn pixels_per_word(format: PixelFormat) -> usize {
+    16 / slot_bits(format)
+}
+
+/// Returns the number of bytes in one packed row, including padding.
+pub fn row_bytes(width: u16, format: PixelFormat) -> usize {
+    let ppw = pixels_per_word(format);
+    (width as usize + ppw - 1) / ppw * 2
+}
+
+/// Returns the number of bytes in a packed image, including row padding.
+pub fn packed_size(width: u16, height: u16, format: PixelFormat) -> usize {
+    row_bytes(width, format) * height as usize
+}
+
+/// Converts an 8-bit gray value to the slot value for a format.
+///
+/// The most significant bits of the source value are kept.
+pub fn quantize(value: u8, format: PixelFormat) -> u8 {
+    match format {
+        PixelFormat::Bpp8 => value,
+        PixelFormat::Bpp4 => value >> 4,
+        PixelFormat::Bpp3 => (value >> 5) << 1,
+        PixelFormat::Bpp2 => value >> 6,
+    }
+}
+
+/// Expands a slot value back to an 8-bit gray value.
+///
+/// The slot bits are replicated so that full black and full white map to
+/// `0x00` and `0xFF`.
+pub fn expand(slot: u8, format: PixelFormat) -> u8 {
+    match format {
+        PixelFormat::Bpp8 => slot,
+        PixelFormat::Bpp4 => slot << 4 | slot,
+        PixelFormat::Bpp3 => {
+            let level = slot >> 1;
+            level << 5 | level << 2 | level >> 1
+        }
+        PixelFormat::Bpp2 => slot * 0x55,
+    }
+}
+
+/// Packs one row of 8bpp pixels into `dst`.
+///
+/// `dst` must be at least [`row_bytes`] long for `src.len()` pixels; bytes
+/// past the last pixel are zeroed.
+///
+/// # Panics
+///
+/// Panics if `dst` is too short.
+pub fn pack_row_into(src: &[u8], format: PixelFormat, dst: &mut [u8]) {
+    let len = row_bytes(src.len() as u16, format);
+    let dst = &mut dst[..len];
+
+    if format == PixelFormat::Bpp8 {
+        dst[..src.len()].copy_from_slice(src);
+        dst[src.len()..].fill(0);
+        return;
+    }
+
+    dst.fill(0);
+    let bits = slot_bits(format);
+    let per_byte = 8 / bits;
+    for (i, &value) in src.iter().enumerate() {
+        let shift = (i % per_byte) * bits;
+        dst[i / per_byte] |= quantize(value, format) << shift;
+    }
+}
+
+/// Reads the slot values of the first `width` pixels of a packed row.
+///
+/// # Panics
+///
+/// Panics if `row` is shorter than [`row_bytes`].
+pub fn row_slots(row: &[u8], width: u16, format: PixelFormat) -> Vec<u8> {
+    let bits = slot_bits(format);
+    let per_byte = 8 / bits;
+    let mask = ((1u16 << bits) - 1) as u8;
+    (0..width as usize)
+        .map(|i| (row[i / per_byte] >> ((i % per_byte) * bits)) & mask)
+        .collect()
+}
+
+/// Packs slot values into one row.
+///
+/// Unlike [`pack_row_into`] the values are not quantized. Bytes past the
+/// last pixel are zeroed.
+///
+/// # Panics
+///
+/// Panics if `dst` is shorter than [`row_bytes`] for `slots.len()` pixels.
+pub fn pack_slots_into(slots: &[u8], format: PixelFormat, dst: &mut [u8]) {
+    let bits = slot_bits(format);
+    let per_byte = 8 / bits;
+    dst[..row_bytes(slots.len() as u16, format)].fill(0);
+    for (i, &slot) in slots.iter().enumerate() {
+        dst[i / per_byte] |= slot << ((i % per_byte) * bits);
+    }
+}
+
+/// Reverses the order of the pixels in one packed row.
+///
+/// `row` holds `width` pixels in the wire layout for `format`. Padding
+/// slots past the last pixel are zeroed.
+///
+/// # Panics
+///
+/// Panics if `row` is shorter than [`row_bytes`].
+pub fn mirror_row(row: &mut [u8], width: u16, format: PixelFormat) {
+    let mut slots = row_slots(row, width, format);
+    slots.reverse();
+    pack_slots_into(&slots, format, row);
+}
+
+/// Packs an 8bpp image into the wire layout for `format`.
+///
+/// `src` holds `width * height` pixels, one byte each, row by row.
+///
+/// # Examples
+///
+/// ```
+/// use it8951::{pixel, PixelFormat};
+///
+/// // Three pixels fit in one 4bpp word; the fourth slot is padding.
+/// let packed = pixel::pack(&[0x10, 0x20, 0x30], 3, 1, PixelFormat::Bpp4).unwrap();
+/// assert_eq!(packed, vec![0x21, 0x03]);
+/// ```
+pub fn pack(src: &[u8], width: u16, height: u16, format: PixelFormat) -> Result<Vec<u8>> {
+    let pixels = width as usize * height as usize;
+    if src.len() < pixels {
+        return Err(Error::BufferSize {
+            expected: pixels,
+            actual: src.len(),
+        });
+    }
+
+    let stride = row_bytes(width, format);
+    let mut packed = vec![0; stride * height as usize];
+    if width == 0 {
+        return Ok(packed);
+    }
+
+    for (row, out) in src[..pixels]
+        .chunks(width as usize)
+        .zip(packed.chunks_mut(stride))
+    {
+        pack_row_into(row, format, out);
+    }
+
+    Ok(packed)
+}
+
+/// Unpacks a wire-layout image back into 8bpp pixels.
+///
+/// Slot values are widened with [`expand`]; row padding is dropped.
+pub fn unpack(packed: &[u8], width: u16, height: u16, format: PixelFormat) -> Result<Vec<u8>> {
+    let expected = packed_size(width, height, format);
+    if packed.len() < expected {
+        return Err(Error::BufferSize {
+            expected,
+            actual: packed.len(),
+        });
+    }
+
+    let stride = row_bytes(width, format);
+    let bits = slot_bits(format);
+    let per_byte = 8 / bits;
+    let mask = ((1u16 << bits) - 1) as u8;
+
+    let mut pixels = Vec::with_capacity(width as usize * height as usize);
+    for row in packed[..expected].chunks(stride.max(1)).take(height as usize) {
+        for i in 0..width as usize {
+            let shift = (i % per_byte) * bits;
+            let slot = (row[i / per_byte] >> shift) & mask;
+            pixels.push(expand(slot, format));
+        }
+    }
+
+    Ok(pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const FORMATS: [PixelFormat; 4] = [
+        PixelFormat::Bpp8,
+        PixelFormat::Bpp4,
+        PixelFormat::Bpp3,
+        PixelFormat::Bpp2,
+    ];
+
+    #[test]
+    fn test_row_bytes_padding() {
+        assert_eq!(row_bytes(3, PixelFormat::Bpp8), 4);
+        assert_eq!(row_bytes(4, PixelFormat::Bpp8), 4);
+        assert_eq!(row_bytes(5, PixelFormat::Bpp4), 4);
+        assert_eq!(row_bytes(5, PixelFormat::Bpp3), 4);
+        assert_eq!(row_bytes(9, PixelFormat::Bpp2), 4);
+        assert_eq!(packed_size(800, 600, PixelFormat::Bpp4), 240_000);
+    }
+
+    #[test]
+    fn test_nibble_order() {
+        let packed = pack(&[0x10, 0x20, 0x30, 0x40], 4, 1, PixelFormat::Bpp4).unwrap();
+        assert_eq!(packed, vec![0x21, 0x43]);
+
+        let packed = pack(&[0x00, 0x40, 0x80, 0xC0], 4, 1, PixelFormat::Bpp2).unwrap();
+        assert_eq!(packed, vec![0b1110_0100, 0x00]);
+
+        let packed = pack(&[0xFF, 0x20], 2, 1, PixelFormat::Bpp3).unwrap();
+        assert_eq!(packed, vec![0x2E, 0x00]);
+    }
+
+    #[test]
+    fn test_odd_width_rows_start_on_word() {
+        let src = [1, 2, 3, 4, 5, 6];
+        let packed = pack(&src, 3, 2, PixelFormat::Bpp8).unwrap();
+        assert_eq!(packed, vec![1, 2, 3, 0, 4, 5, 6, 0]);
+    }
+
+    #[test]
+    fn test_mirror_row() {
+        for format in FORMATS {
+            let src: Vec<u8> = (0..5).map(|i| i * 0x30).collect();
+            let reversed: Vec<u8> = src.iter().rev().copied().collect();
+            let mut row = pack(&src, 5, 1, format).unwrap();
+            mirror_row(&mut row, 5, format);
+            assert_eq!(row, pack(&reversed, 5, 1, format).unwrap(), "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn test_pack_rejects_short_buffer() {
+        assert!(matches!(
+            pack(&[0; 5], 3, 2, PixelFormat::Bpp8),
+            Err(Error::BufferSize {
+                expected: 6,
+                actual: 5
+            })
+        ));
+    }
+
+    #[test]
+    fn test_expand_extremes() {
+        for format in FORMATS {
+            assert_eq!(expand(quantize(0x00, format), format), 0x00);
+            assert_eq!(expand(quantize(0xFF, format), format), 0xFF);
+        }
+    }
+
+    fn image() -> impl Strategy<Value = (u16, u16, Vec<u8>)> {
+        (0u16..40, 0u16..8).prop_flat_map(|(w, h)| {
+            (
+                Just(w),
+                Just(h),
+                proptest::collection::vec(any::<u8>(), w as usize * h as usize),
+            )
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_packed_size_matches((w, h, src) in image(), f in 0usize..4) {
+            let format = FORMATS[f];
+            let packed = pack(&src, w, h, format).unwrap();
+            prop_assert_eq!(packed.len(), packed_size(w, h, format));
+            prop_assert_eq!(packed.len() % 2, 0);
+        }
+
+        #[test]
+        fn prop_roundtrip_keeps_significant_bits((w, h, src) in image(), f in 0usize..4) {
+            let format = FORMATS[f];
+            let packed = pack(&src, w, h, format).unwrap();
+            let unpacked = unpack(&packed, w, h, format).unwrap();
+            let expected: Vec<u8> = src
+                .iter()
+                .map(|&v| expand(quantize(v, format), format))
+                .collect();
+            prop_assert_eq!(unpacked, expected);
+        }
+
+        #[test]
+        fn prop_rows_are_independent((w, h, src) in image(), f in 0usize..4) {
+            prop_assume!(w > 0 && h > 0);
+            let format = FORMATS[f];
+            let packed = pack(&src, w, h, format).unwrap();
+            let stride = row_bytes(w, format);
+            for (y, row) in src.chunks(w as usize).enumerate() {
+                let single = pack(row, w, 1, format).unwrap();
+                prop_assert_eq!(&packed[y * stride..(y + 1) * stride], &single[..]);
+            }
+        }
+
+        #[test]
+        fn prop_padding_is_zero((w, h, src) in image(), f in 0usize..4) {
+            let format = FORMATS[f];
+            let packed = pack(&src, w, h, format).unwrap();
+            let stride = row_bytes(w, format);
+            let used_bits = w as usize * slot_bits(format);
+            for row in packed.chunks(stride.max(1)) {
+                for bit in used_bits..stride * 8 {
+                    prop_assert_eq!(row[bit / 8] >> (bit % 8) & 1, 0);
+                }
+            }
+        }
+    }
+}
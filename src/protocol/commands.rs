@@ -82,8 +82,28 @@ pub enum UserCommand {
     /// Display buffer area
     DisplayBufArea = 0x0037,
 
-    /// VCOM value get/set
+    /// Panel power sequence on/off
+    PowerSequence = 0x0038,
+
+    /// VCOM value get/set/write to flash
     Vcom = 0x0039,
+
+    /// Temperature get/force set
+    Temperature = 0x0040,
+
+    /// Erase a range of the SPI NOR flash
+    ///
+    /// Experimental: the flash commands use the codes of the IT8951 USB
+    /// vendor commands. No datasheet documents them for the I80/SPI host
+    /// interface, and they have only been exercised against the mock
+    /// controller.
+    SpiFlashErase = 0x0096,
+
+    /// Copy a range of the SPI NOR flash into SDRAM
+    SpiFlashRead = 0x0097,
+
+    /// Program a range of the SPI NOR flash from SDRAM
+    SpiFlashWrite = 0x0098,
 }
 
 impl UserCommand {
@@ -98,7 +118,12 @@ impl UserCommand {
             0x0034 => Some(UserCommand::DisplayArea),
             0x0302 => Some(UserCommand::GetDevInfo),
             0x0037 => Some(UserCommand::DisplayBufArea),
+            0x0038 => Some(UserCommand::PowerSequence),
             0x0039 => Some(UserCommand::Vcom),
+            0x0040 => Some(UserCommand::Temperature),
+            0x0096 => Some(UserCommand::SpiFlashErase),
+            0x0097 => Some(UserCommand::SpiFlashRead),
+            0x0098 => Some(UserCommand::SpiFlashWrite),
             _ => None,
         }
     }
@@ -127,6 +152,7 @@ mod tests {
         assert_eq!(UserCommand::DisplayArea.as_u16(), 0x0034);
         assert_eq!(UserCommand::GetDevInfo.as_u16(), 0x0302);
         assert_eq!(UserCommand::Vcom.as_u16(), 0x0039);
+        assert_eq!(UserCommand::Temperature.as_u16(), 0x0040);
     }
 
     #[test]
@@ -156,4 +182,25 @@ mod tests {
             assert_eq!(decoded, Some(cmd));
         }
     }
+
+    #[test]
+    fn test_user_command_roundtrip() {
+        let commands = [
+            UserCommand::DisplayArea,
+            UserCommand::GetDevInfo,
+            UserCommand::DisplayBufArea,
+            UserCommand::PowerSequence,
+            UserCommand::Vcom,
+            UserCommand::Temperature,
+            UserCommand::SpiFlashErase,
+            UserCommand::SpiFlashRead,
+            UserCommand::SpiFlashWrite,
+        ];
+
+        for cmd in commands {
+            let value = cmd.as_u16();
+            let decoded = UserCommand::from_u16(value);
+            assert_eq!(decoded, Some(cmd));
+        }
+    }
 }
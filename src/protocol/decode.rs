@@ -0,0 +1,690 @@
+//! IT8951 protocol disassembler.
+//!
+//! This module turns preamble-framed SPI transfers back into the
+//! operations that produced them. It is intended for debugging: feed it
+//! the transfers recorded by a mock SPI, or wrap a real SPI interface in
+//! [`TraceSpi`] to log every operation through the `log` crate.
+//!
+//! # Examples
+//!
+//! ```
+//! use it8951::protocol::decode::{decode, Operation};
+//! use it8951::Register;
+//!
+//! let transfers = vec![
+//!     vec![0x60, 0x00, 0x00, 0x11], // RegWrite
+//!     vec![0x00, 0x00, 0x00, 0x04], // I80CPCR
+//!     vec![0x00, 0x00, 0x00, 0x01], // value
+//! ];
+//!
+//! let ops = decode(&transfers);
+//! assert_eq!(
+//!     ops,
+//!     vec![Operation::RegisterWrite { register: Register::I80CPCR, value: 1 }]
+//! );
+//! assert_eq!(ops[0].to_string(), "RegWrite I80CPCR = 0x0001");
+//! ```
+
+use crate::error::Result;
+use crate::hal::{BitOrder, SpiInterface, SpiMode, SpiTransfer};
+use crate::protocol::transport::{PREAMBLE_READ_DATA, PREAMBLE_WRITE_CMD, PREAMBLE_WRITE_DATA};
+use crate::protocol::{Command, Register, UserCommand};
+use crate::types::{Area, Endian, PixelFormat, Rotation};
+use std::fmt;
+
+/// A decoded IT8951 protocol operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+    /// Built-in command with its arguments
+    Command {
+        /// The command
+        command: Command,
+        /// Arguments sent after the command
+        args: Vec<u16>,
+    },
+
+    /// User-defined I80 command with its arguments
+    UserCommand {
+        /// The command
+        command: UserCommand,
+        /// Arguments sent after the command
+        args: Vec<u16>,
+    },
+
+    /// Command code not known to this driver
+    UnknownCommand {
+        /// Raw command code
+        code: u16,
+        /// Arguments sent after the command
+        args: Vec<u16>,
+    },
+
+    /// Register read
+    RegisterRead {
+        /// Register address
+        register: Register,
+        /// Value read, if the response bytes were available
+        value: Option<u16>,
+    },
+
+    /// Register write
+    RegisterWrite {
+        /// Register address
+        register: Register,
+        /// Value written
+        value: u16,
+    },
+
+    /// Full-frame image load start
+    LoadImage {
+        /// Byte order of the pixel data
+        endian: Endian,
+        /// Pixel format of the pixel data
+        format: PixelFormat,
+        /// Rotation applied by the controller
+        rotate: Rotation,
+    },
+
+    /// Image area load start
+    LoadImageArea {
+        /// Byte order of the pixel data
+        endian: Endian,
+        /// Pixel format of the pixel data
+        format: PixelFormat,
+        /// Rotation applied by the controller
+        rotate: Rotation,
+        /// Destination area
+        area: Area,
+    },
+
+    /// Bulk data write (pixel or memory data)
+    DataBatch {
+        /// Number of 16-bit words written
+        words: usize,
+    },
+
+    /// Data read not attributed to a register read
+    DataRead {
+        /// Number of 16-bit words read
+        count: usize,
+        /// Words read, if the response bytes were available
+        words: Option<Vec<u16>>,
+    },
+
+    /// Transfer with an unrecognized preamble or length
+    Unknown {
+        /// Raw transmitted bytes
+        bytes: Vec<u8>,
+    },
+}
+
+/// Splits a LoadImage/LoadImageArea argument word into its fields.
+fn decode_image_arg(arg: u16) -> (Endian, PixelFormat, Rotation) {
+    // Masked values always decode, the defaults are never used
+    let endian = Endian::from_u16((arg >> 8) & 0x1).unwrap_or(Endian::Little);
+    let format = PixelFormat::from_u16((arg >> 4) & 0x3).unwrap_or(PixelFormat::Bpp8);
+    let rotate = Rotation::from_u16(arg & 0x3).unwrap_or(Rotation::Rotate0);
+    (endian, format, rotate)
+}
+
+/// Returns the number of arguments a command takes, if known.
+///
+/// Some user commands take a variable number of arguments depending on
+/// their first (operation) argument.
+pub(crate) fn arg_count(code: u16, args: &[u16]) -> Option<usize> {
+    if let Some(command) = Command::from_u16(code) {
+        return Some(match command {
+            Command::SysRun | Command::Standby | Command::Sleep => 0,
+            Command::RegRead => 1,
+            Command::RegWrite => 2,
+            Command::MemBurstReadTrigger | Command::MemBurstWrite => 4,
+            Command::MemBurstReadStart | Command::MemBurstEnd => 0,
+            Command::LoadImage => 1,
+            Command::LoadImageArea => 5,
+            Command::LoadImageEnd => 0,
+        });
+    }
+
+    match UserCommand::from_u16(code)? {
+        UserCommand::DisplayArea => Some(5),
+        UserCommand::GetDevInfo => Some(0),
+        UserCommand::DisplayBufArea => Some(7),
+        UserCommand::PowerSequence => Some(1),
+        UserCommand::SpiFlashErase => Some(4),
+        UserCommand::SpiFlashRead | UserCommand::SpiFlashWrite => Some(6),
+        // Operation 0 reads, anything else carries a value
+        UserCommand::Vcom => args.first().map(|&op| if op == 0 { 1 } else { 2 }),
+        // Operation 0 reads, 1 carries the forced value
+        UserCommand::Temperature => args.first().map(|&op| if op == 1 { 2 } else { 1 }),
+    }
+}
+
+/// Returns the argument names used when printing a command.
+fn arg_names(code: u16) -> &'static [&'static str] {
+    if let Some(command) = Command::from_u16(code) {
+        return match command {
+            Command::RegRead => &["addr"],
+            Command::MemBurstReadTrigger | Command::MemBurstWrite => {
+                &["addr_l", "addr_h", "count_l", "count_h"]
+            }
+            _ => &[],
+        };
+    }
+
+    match UserCommand::from_u16(code) {
+        Some(UserCommand::DisplayArea) => &["x", "y", "w", "h", "mode"],
+        Some(UserCommand::DisplayBufArea) => &["x", "y", "w", "h", "mode", "addr_l", "addr_h"],
+        Some(UserCommand::PowerSequence) => &["on"],
+        Some(UserCommand::Vcom) => &["op", "value"],
+        Some(UserCommand::Temperature) => &["op", "celsius"],
+        Some(UserCommand::SpiFlashErase) => &["flash_l", "flash_h", "len_l", "len_h"],
+        Some(UserCommand::SpiFlashRead | UserCommand::SpiFlashWrite) => {
+            &["flash_l", "flash_h", "addr_l", "addr_h", "len_l", "len_h"]
+        }
+        _ => &[],
+    }
+}
+
+/// Builds an operation from a command and the arguments seen so far.
+fn build_operation(code: u16, args: Vec<u16>) -> Operation {
+    match (Command::from_u16(code), args.as_slice()) {
+        (Some(Command::RegWrite), &[addr, value]) => Operation::RegisterWrite {
+            register: Register::new(addr),
+            value,
+        },
+        (Some(Command::RegRead), &[addr]) => Operation::RegisterRead {
+            register: Register::new(addr),
+            value: None,
+        },
+        (Some(Command::LoadImage), &[arg]) => {
+            let (endian, format, rotate) = decode_image_arg(arg);
+            Operation::LoadImage {
+                endian,
+                format,
+                rotate,
+            }
+        }
+        (Some(Command::LoadImageArea), &[arg, x, y, width, height]) => {
+            let (endian, format, rotate) = decode_image_arg(arg);
+            Operation::LoadImageArea {
+                endian,
+                format,
+                rotate,
+                area: Area::new(x, y, width, height),
+            }
+        }
+        (Some(command), _) => Operation::Command { command, args },
+        (None, _) => match UserCommand::from_u16(code) {
+            Some(command) => Operation::UserCommand { command, args },
+            None => Operation::UnknownCommand { code, args },
+        },
+    }
+}
+
+/// Writes `args` as `name=value` pairs, falling back to hex for extras.
+fn write_args(f: &mut fmt::Formatter<'_>, code: u16, args: &[u16]) -> fmt::Result {
+    let names = arg_names(code);
+    for (i, arg) in args.iter().enumerate() {
+        match names.get(i) {
+            Some(name) => write!(f, " {}={}", name, arg)?,
+            None => write!(f, " 0x{:04X}", arg)?,
+        }
+    }
+    Ok(())
+}
+
+fn write_register(f: &mut fmt::Formatter<'_>, register: Register) -> fmt::Result {
+    match register.name() {
+        Some(name) => write!(f, "{}", name),
+        None => write!(f, "0x{:04X}", register.addr()),
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::Command { command, args } => {
+                write!(f, "{:?}", command)?;
+                write_args(f, command.as_u16(), args)
+            }
+            Operation::UserCommand { command, args } => {
+                write!(f, "{:?}", command)?;
+                write_args(f, command.as_u16(), args)
+            }
+            Operation::UnknownCommand { code, args } => {
+                write!(f, "Unknown(0x{:04X})", code)?;
+                write_args(f, *code, args)
+            }
+            Operation::RegisterRead { register, value } => {
+                write!(f, "RegRead ")?;
+                write_register(f, *register)?;
+                match value {
+                    Some(value) => write!(f, " -> 0x{:04X}", value),
+                    None => write!(f, " -> ?"),
+                }
+            }
+            Operation::RegisterWrite { register, value } => {
+                write!(f, "RegWrite ")?;
+                write_register(f, *register)?;
+                write!(f, " = 0x{:04X}", value)
+            }
+            Operation::LoadImage {
+                endian,
+                format,
+                rotate,
+            } => write!(
+                f,
+                "LoadImage endian={:?} format={:?} rotate={:?}",
+                endian, format, rotate
+            ),
+            Operation::LoadImageArea {
+                endian,
+                format,
+                rotate,
+                area,
+            } => write!(
+                f,
+                "LoadImageArea endian={:?} format={:?} rotate={:?} x={} y={} w={} h={}",
+                endian, format, rotate, area.x, area.y, area.width, area.height
+            ),
+            Operation::DataBatch { words } => write!(f, "Data {} words", words),
+            Operation::DataRead { count, words } => {
+                write!(f, "Read {} words", count)?;
+                if let Some(words) = words {
+                    if words.len() <= 8 {
+                        write!(f, " {:04X?}", words)?;
+                    }
+                }
+                Ok(())
+            }
+            Operation::Unknown { bytes } => write!(f, "Unknown transfer {:02X?}", bytes),
+        }
+    }
+}
+
+/// A command whose arguments are still being collected.
+#[derive(Debug, Clone)]
+struct Pending {
+    code: u16,
+    args: Vec<u16>,
+}
+
+/// Stateful decoder for a stream of SPI transfers.
+///
+/// Each transfer is expected to be a single preamble-framed unit as sent by
+/// [`Transport`](crate::protocol::Transport). Operations are returned as
+/// soon as they are complete; commands with an unknown argument count are
+/// returned when the next command arrives or on [`finish`](Self::finish).
+#[derive(Debug, Clone, Default)]
+pub struct Decoder {
+    pending: Option<Pending>,
+}
+
+impl Decoder {
+    /// Creates a new decoder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds one transfer into the decoder.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx` - Bytes sent to the device
+    /// * `rx` - Bytes received, if available (needed to decode read values)
+    pub fn push(&mut self, tx: &[u8], rx: Option<&[u8]>) -> Vec<Operation> {
+        if tx.len() < 4 {
+            let mut ops = self.finish();
+            ops.push(Operation::Unknown { bytes: tx.to_vec() });
+            return ops;
+        }
+
+        let preamble = u16::from_be_bytes([tx[0], tx[1]]);
+        let words: Vec<u16> = tx[2..]
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+
+        match preamble {
+            PREAMBLE_WRITE_CMD => {
+                let mut ops = self.finish();
+                self.pending = Some(Pending {
+                    code: words[0],
+                    args: Vec::new(),
+                });
+                ops.extend(self.complete());
+                ops
+            }
+            PREAMBLE_WRITE_DATA => {
+                // Arguments are always sent one word per transfer
+                if words.len() == 1 {
+                    if let Some(pending) = self.pending.as_mut() {
+                        pending.args.push(words[0]);
+                        return self.complete().into_iter().collect();
+                    }
+                }
+                let mut ops = self.finish();
+                ops.push(Operation::DataBatch { words: words.len() });
+                ops
+            }
+            PREAMBLE_READ_DATA => {
+                // First word is the dummy read
+                let count = words.len() - 1;
+                let values = rx.filter(|rx| rx.len() >= tx.len()).map(|rx| {
+                    rx[4..tx.len()]
+                        .chunks_exact(2)
+                        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                        .collect::<Vec<u16>>()
+                });
+
+                if let Some(Pending { code, args }) = &self.pending {
+                    if *code == Command::RegRead.as_u16() && args.len() == 1 {
+                        let register = Register::new(args[0]);
+                        self.pending = None;
+                        return vec![Operation::RegisterRead {
+                            register,
+                            value: values.and_then(|v| v.first().copied()),
+                        }];
+                    }
+                }
+
+                let mut ops = self.finish();
+                ops.push(Operation::DataRead {
+                    count,
+                    words: values,
+                });
+                ops
+            }
+            _ => {
+                let mut ops = self.finish();
+                ops.push(Operation::Unknown { bytes: tx.to_vec() });
+                ops
+            }
+        }
+    }
+
+    /// Returns any operation still waiting for arguments or a response.
+    pub fn finish(&mut self) -> Vec<Operation> {
+        self.pending
+            .take()
+            .map(|pending| build_operation(pending.code, pending.args))
+            .into_iter()
+            .collect()
+    }
+
+    /// Emits the pending command if all of its arguments have arrived.
+    fn complete(&mut self) -> Option<Operation> {
+        let pending = self.pending.as_ref()?;
+        let expected = arg_count(pending.code, &pending.args)?;
+
+        // Register reads wait for the read transfer to attach the value
+        if pending.args.len() < expected || pending.code == Command::RegRead.as_u16() {
+            return None;
+        }
+
+        let pending = self.pending.take()?;
+        Some(build_operation(pending.code, pending.args))
+    }
+}
+
+/// Decodes a complete list of transmitted transfers.
+///
+/// Read values are not available from transmitted bytes alone, so register
+/// reads decode with `value: None`.
+///
+/// # Examples
+///
+/// ```ignore
+/// let ops = decode(&spi.get_transfers());
+/// for op in &ops {
+///     println!("{}", op);
+/// }
+/// ```
+pub fn decode<T: AsRef<[u8]>>(transfers: &[T]) -> Vec<Operation> {
+    let mut decoder = Decoder::new();
+    let mut ops = Vec::new();
+    for transfer in transfers {
+        ops.extend(decoder.push(transfer.as_ref(), None));
+    }
+    ops.extend(decoder.finish());
+    ops
+}
+
+/// SPI wrapper that logs every decoded operation.
+///
+/// Operations are logged at debug level with the `it8951::trace` target,
+/// so they can be enabled independently, e.g. with
+/// `RUST_LOG=it8951::trace=debug`.
+///
+/// # Examples
+///
+/// ```ignore
+/// use it8951::protocol::TraceSpi;
+///
+/// let spi = TraceSpi::new(LinuxSpi::new("/dev/spidev0.0", 1_000_000)?);
+/// let mut display = IT8951::new(spi, hrdy, cs, reset, 1500);
+/// ```
+#[derive(Debug)]
+pub struct TraceSpi<SPI> {
+    inner: SPI,
+    decoder: Decoder,
+}
+
+impl<SPI> TraceSpi<SPI> {
+    /// Wraps an SPI interface.
+    pub fn new(inner: SPI) -> Self {
+        Self {
+            inner,
+            decoder: Decoder::new(),
+        }
+    }
+
+    /// Logs any operation still waiting for arguments or a response.
+    pub fn flush(&mut self) {
+        for op in self.decoder.finish() {
+            log::debug!(target: "it8951::trace", "{}", op);
+        }
+    }
+
+    /// Returns the wrapped SPI interface.
+    pub fn into_inner(self) -> SPI {
+        self.inner
+    }
+}
+
+impl<SPI: SpiTransfer> SpiTransfer for TraceSpi<SPI> {
+    fn transfer_byte(&mut self, byte: u8) -> Result<u8> {
+        self.inner.transfer_byte(byte)
+    }
+
+    fn transfer(&mut self, buffer: &[u8]) -> Result<Vec<u8>> {
+        let rx = self.inner.transfer(buffer)?;
+        for op in self.decoder.push(buffer, Some(&rx)) {
+            log::debug!(target: "it8951::trace", "{}", op);
+        }
+        Ok(rx)
+    }
+
+    fn set_speed(&mut self, speed_hz: u32) -> Result<()> {
+        log::trace!(target: "it8951::trace", "SPI speed {} Hz", speed_hz);
+        self.inner.set_speed(speed_hz)
+    }
+}
+
+impl<SPI: SpiInterface> SpiInterface for TraceSpi<SPI> {
+    fn set_clock_hz(&mut self, hz: u32) -> Result<()> {
+        self.inner.set_clock_hz(hz)
+    }
+
+    fn clock_hz(&self) -> u32 {
+        self.inner.clock_hz()
+    }
+
+    fn set_mode(&mut self, mode: SpiMode) -> Result<()> {
+        self.inner.set_mode(mode)
+    }
+
+    fn set_bit_order(&mut self, order: BitOrder) -> Result<()> {
+        self.inner.set_bit_order(order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::mock::{MockInputPin, MockOutputPin, MockSpi};
+    use crate::hal::PinState;
+    use crate::protocol::Transport;
+
+    fn setup_transport() -> (MockSpi, Transport<MockSpi, MockInputPin, MockOutputPin>) {
+        let spi = MockSpi::new();
+        let hrdy = MockInputPin::new(PinState::High);
+        let cs = MockOutputPin::new(PinState::High);
+        (spi.clone(), Transport::new(spi, hrdy, cs))
+    }
+
+    #[test]
+    fn test_decode_register_write() {
+        let (spi, mut transport) = setup_transport();
+        transport.write_register(Register::LISAR, 0x36E0).unwrap();
+
+        assert_eq!(
+            decode(&spi.get_transfers()),
+            vec![Operation::RegisterWrite {
+                register: Register::LISAR,
+                value: 0x36E0
+            }]
+        );
+    }
+
+    #[test]
+    fn test_decode_register_read_with_response() {
+        let mut decoder = Decoder::new();
+        assert!(decoder.push(&[0x60, 0x00, 0x00, 0x10], None).is_empty());
+        assert!(decoder.push(&[0x00, 0x00, 0x12, 0x24], None).is_empty());
+
+        let ops = decoder.push(
+            &[0x10, 0x00, 0x00, 0x00, 0x00, 0x00],
+            Some(&[0x00, 0x00, 0x00, 0x00, 0x00, 0x01]),
+        );
+        assert_eq!(
+            ops,
+            vec![Operation::RegisterRead {
+                register: Register::LUTAFSR,
+                value: Some(1)
+            }]
+        );
+        assert_eq!(ops[0].to_string(), "RegRead LUTAFSR -> 0x0001");
+    }
+
+    #[test]
+    fn test_decode_load_image_area() {
+        let (spi, mut transport) = setup_transport();
+        let arg = (Endian::Big.as_u16() << 8)
+            | (PixelFormat::Bpp4.as_u16() << 4)
+            | Rotation::Rotate90.as_u16();
+        transport
+            .write_command_with_args(Command::LoadImageArea, &[arg, 10, 20, 30, 40])
+            .unwrap();
+        transport.write_data_batch(&[0x1111; 300]).unwrap();
+        transport.write_command(Command::LoadImageEnd).unwrap();
+
+        let ops = decode(&spi.get_transfers());
+        assert_eq!(
+            ops,
+            vec![
+                Operation::LoadImageArea {
+                    endian: Endian::Big,
+                    format: PixelFormat::Bpp4,
+                    rotate: Rotation::Rotate90,
+                    area: Area::new(10, 20, 30, 40),
+                },
+                Operation::DataBatch { words: 300 },
+                Operation::Command {
+                    command: Command::LoadImageEnd,
+                    args: vec![]
+                },
+            ]
+        );
+        assert_eq!(
+            ops[0].to_string(),
+            "LoadImageArea endian=Big format=Bpp4 rotate=Rotate90 x=10 y=20 w=30 h=40"
+        );
+    }
+
+    #[test]
+    fn test_decode_user_commands() {
+        let (spi, mut transport) = setup_transport();
+        transport
+            .write_user_command_with_args(UserCommand::DisplayArea, &[0, 0, 800, 600, 2])
+            .unwrap();
+        transport.write_user_command(UserCommand::GetDevInfo).unwrap();
+        transport.read_data_batch(20).unwrap();
+        transport
+            .write_user_command_with_args(UserCommand::Vcom, &[0])
+            .unwrap();
+        transport.read_data().unwrap();
+
+        let ops = decode(&spi.get_transfers());
+        assert_eq!(ops.len(), 5);
+        assert_eq!(ops[0].to_string(), "DisplayArea x=0 y=0 w=800 h=600 mode=2");
+        assert_eq!(
+            ops[1],
+            Operation::UserCommand {
+                command: UserCommand::GetDevInfo,
+                args: vec![]
+            }
+        );
+        assert_eq!(
+            ops[2],
+            Operation::DataRead {
+                count: 20,
+                words: None
+            }
+        );
+        assert_eq!(ops[3].to_string(), "Vcom op=0");
+        assert_eq!(ops[4].to_string(), "Read 1 words");
+    }
+
+    #[test]
+    fn test_decode_unknown_command_flushed_by_next() {
+        let ops = decode(&[
+            vec![0x60, 0x00, 0x00, 0x50],
+            vec![0x00, 0x00, 0x00, 0x01],
+            vec![0x00, 0x00, 0x00, 0x02],
+            vec![0x60, 0x00, 0x00, 0x01],
+        ]);
+        assert_eq!(
+            ops,
+            vec![
+                Operation::UnknownCommand {
+                    code: 0x0050,
+                    args: vec![1, 2]
+                },
+                Operation::Command {
+                    command: Command::SysRun,
+                    args: vec![]
+                },
+            ]
+        );
+        assert_eq!(ops[0].to_string(), "Unknown(0x0050) 0x0001 0x0002");
+    }
+
+    #[test]
+    fn test_decode_malformed_transfer() {
+        let ops = decode(&[vec![0xAB]]);
+        assert_eq!(ops, vec![Operation::Unknown { bytes: vec![0xAB] }]);
+    }
+
+    #[test]
+    fn test_trace_spi_passthrough() {
+        let mut inner = MockSpi::new();
+        inner.add_response(vec![0x12, 0x34, 0x56, 0x78]);
+        let mut spi = TraceSpi::new(inner.clone());
+
+        let rx = spi.transfer(&[0x60, 0x00, 0x00, 0x01]).unwrap();
+        assert_eq!(rx, vec![0x12, 0x34, 0x56, 0x78]);
+        assert_eq!(inner.get_transfers().len(), 1);
+        spi.flush();
+    }
+}
@@ -22,9 +22,11 @@
 //! For read operations, two dummy bytes are sent before reading the actual data.
 
 pub mod commands;
+pub mod decode;
 pub mod registers;
 pub mod transport;
 
 pub use commands::{Command, UserCommand};
+pub use decode::{Decoder, Operation, TraceSpi};
 pub use registers::Register;
 pub use transport::Transport;
@@ -17,6 +17,29 @@ impl Register {
     pub const fn addr(self) -> u16 {
         self.0
     }
+
+    /// Returns the register's datasheet name, if it is a known register.
+    pub const fn name(self) -> Option<&'static str> {
+        match self.0 {
+            0x0004 => Some("I80CPCR"),
+            0x1000 => Some("LUT0EWHR"),
+            0x1040 => Some("LUT0XYR"),
+            0x1080 => Some("LUT0BADDR"),
+            0x10C0 => Some("LUT0MFN"),
+            0x1114 => Some("LUT01AF"),
+            0x1134 => Some("UP0SR"),
+            0x1138 => Some("UP1SR"),
+            0x113C => Some("LUT0ABFRV"),
+            0x117C => Some("UPBBADDR"),
+            0x1180 => Some("LUT0IMXY"),
+            0x1224 => Some("LUTAFSR"),
+            0x1250 => Some("BGVR"),
+            0x0200 => Some("MCSR"),
+            0x0208 => Some("LISAR"),
+            0x020A => Some("LISAR+2"),
+            _ => None,
+        }
+    }
 }
 
 // System Registers (Base: 0x0000)
@@ -91,6 +114,13 @@ mod tests {
         assert_eq!(reg.addr(), 0x1234);
     }
 
+    #[test]
+    fn test_register_name() {
+        assert_eq!(Register::LISAR.name(), Some("LISAR"));
+        assert_eq!(Register::new(0x020A).name(), Some("LISAR+2"));
+        assert_eq!(Register::new(0x1234).name(), None);
+    }
+
     #[test]
     fn test_register_equality() {
         assert_eq!(Register::I80CPCR, Register::new(0x0004));
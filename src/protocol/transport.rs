@@ -10,16 +10,19 @@ use byteorder::{BigEndian, ByteOrder};
 use std::time::{Duration, Instant};
 
 /// Preamble for writing command code (0x6000)
-const PREAMBLE_WRITE_CMD: u16 = 0x6000;
+pub(crate) const PREAMBLE_WRITE_CMD: u16 = 0x6000;
 
 /// Preamble for writing data (0x0000)
-const PREAMBLE_WRITE_DATA: u16 = 0x0000;
+pub(crate) const PREAMBLE_WRITE_DATA: u16 = 0x0000;
 
 /// Preamble for reading data (0x1000)
-const PREAMBLE_READ_DATA: u16 = 0x1000;
+pub(crate) const PREAMBLE_READ_DATA: u16 = 0x1000;
+
+/// Maximum number of data words sent per CS session
+const MAX_CHUNK_WORDS: usize = 32767;
 
 /// Default timeout for waiting for hardware ready (5 seconds)
-const DEFAULT_TIMEOUT_MS: u64 = 5000;
+pub(crate) const DEFAULT_TIMEOUT_MS: u64 = 5000;
 
 /// IT8951 transport layer.
 ///
@@ -63,20 +66,40 @@ where
         self.data_speed_hz = data_speed_hz;
     }
 
+    /// Returns the SPI speeds for command and data transfers.
+    pub fn speeds(&self) -> (u32, u32) {
+        (self.command_speed_hz, self.data_speed_hz)
+    }
+
     /// Sets the timeout for hardware ready waits.
     pub fn set_timeout(&mut self, timeout: Duration) {
         self.timeout = timeout;
     }
 
+    /// Returns the timeout for hardware ready waits.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
     /// Waits for the hardware ready pin to go high.
     ///
     /// Returns an error if the timeout is exceeded.
     fn wait_ready(&self) -> Result<()> {
+        self.wait_ready_within(self.timeout)
+    }
+
+    /// Waits for HRDY with a timeout other than the configured one, e.g.
+    /// while the controller boots after a reset.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Timeout` if HRDY does not go high within `timeout`.
+    pub fn wait_ready_within(&self, timeout: Duration) -> Result<()> {
         let start = Instant::now();
 
         while !self.hrdy.is_high()? {
-            if start.elapsed() > self.timeout {
-                return Err(Error::Timeout(self.timeout.as_millis() as u64));
+            if start.elapsed() > timeout {
+                return Err(Error::Timeout(timeout.as_millis() as u64));
             }
             // Small yield to prevent busy-waiting
             std::thread::yield_now();
@@ -129,12 +152,41 @@ where
     ///
     /// Sends preamble + data in chunks, keeping each chunk in a single CS session.
     pub fn write_data_batch(&mut self, data: &[u16]) -> Result<()> {
+        self.with_data_speed(|transport| transport.write_data_batch_inner(data))
+    }
+
+    /// Writes raw bytes as data, without byte order conversion.
+    ///
+    /// Bytes are sent exactly as given, so each pair forms one word on the
+    /// wire with the first byte as its high byte. An odd trailing byte is
+    /// padded with zero.
+    pub fn write_data_bytes(&mut self, data: &[u8]) -> Result<()> {
+        self.with_data_speed(|transport| {
+            for chunk in data.chunks(MAX_CHUNK_WORDS * 2) {
+                transport.wait_ready()?;
+                let mut buf = Vec::with_capacity(chunk.len() + 3);
+                buf.extend_from_slice(&PREAMBLE_WRITE_DATA.to_be_bytes());
+                buf.extend_from_slice(chunk);
+                if chunk.len() % 2 == 1 {
+                    buf.push(0);
+                }
+                transport.spi.transfer(&buf)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Runs a bulk transfer at the data speed, restoring the command speed.
+    fn with_data_speed<F>(&mut self, op: F) -> Result<()>
+    where
+        F: FnOnce(&mut Self) -> Result<()>,
+    {
         let use_fast_speed = self.data_speed_hz > 0 && self.command_speed_hz > 0;
         if use_fast_speed {
             self.spi.set_speed(self.data_speed_hz)?;
         }
 
-        let result = self.write_data_batch_inner(data);
+        let result = op(self);
 
         if use_fast_speed {
             self.spi.set_speed(self.command_speed_hz)?;
@@ -144,8 +196,6 @@ where
     }
 
     fn write_data_batch_inner(&mut self, data: &[u16]) -> Result<()> {
-        const MAX_CHUNK_WORDS: usize = 32767;
-
         self.wait_ready()?;
 
         // First chunk includes preamble
@@ -260,6 +310,24 @@ where
         self.write_data(value)?;
         Ok(())
     }
+
+    /// Writes a register value and reads it back to confirm it took.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::RegisterMismatch` if the value read back differs.
+    pub fn write_register_verified(&mut self, reg: Register, value: u16) -> Result<()> {
+        self.write_register(reg, value)?;
+        let actual = self.read_register(reg)?;
+        if actual != value {
+            return Err(Error::RegisterMismatch {
+                register: reg.addr(),
+                expected: value,
+                actual,
+            });
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -339,6 +407,16 @@ mod tests {
         assert!(!transfers.is_empty());
     }
 
+    #[test]
+    fn test_write_data_bytes() {
+        let mut transport = setup_transport();
+
+        transport.write_data_bytes(&[0x12, 0x34, 0x56]).unwrap();
+
+        let transfers = transport.spi.get_transfers();
+        assert_eq!(transfers, vec![vec![0x00, 0x00, 0x12, 0x34, 0x56, 0x00]]);
+    }
+
     #[test]
     fn test_write_command_with_args() {
         let mut transport = setup_transport();
@@ -369,6 +447,24 @@ mod tests {
         assert_eq!(result, 0x1234);
     }
 
+    #[test]
+    fn test_write_register_verified_mismatch() {
+        let mut transport = setup_transport();
+
+        // Mock reads back zero
+        assert!(matches!(
+            transport.write_register_verified(Register::I80CPCR, 0x0001),
+            Err(Error::RegisterMismatch {
+                register: 0x0004,
+                expected: 0x0001,
+                actual: 0x0000
+            })
+        ));
+        assert!(transport
+            .write_register_verified(Register::I80CPCR, 0x0000)
+            .is_ok());
+    }
+
     #[test]
     fn test_write_register() {
         let mut transport = setup_transport();
@@ -1,6 +1,16 @@
 //! Core data types for the IT8951 driver.
 
 use crate::error::{Error, Result};
+use std::fmt;
+
+/// Largest VCOM magnitude the IT8951 accepts, in millivolts.
+const MAX_VCOM_MV: u16 = 5000;
+
+/// Largest panel dimension the IT8951 can drive.
+const MAX_PANEL_DIMENSION: u16 = 4096;
+
+/// Size of the IT8951's SDRAM (64 MB).
+pub(crate) const SDRAM_SIZE: u32 = 0x0400_0000;
 
 /// Information about the connected IT8951 device.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -68,10 +78,341 @@ impl DeviceInfo {
     pub fn pixel_count(&self) -> usize {
         self.panel_width as usize * self.panel_height as usize
     }
+
+    /// Checks that the information is plausible for a real controller.
+    ///
+    /// A floating or miswired bus typically reads back as all zeros or all
+    /// ones, which would otherwise surface later as confusing area or
+    /// memory errors.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::BusFault` for all-zero or all-one responses and
+    /// `Error::InvalidDeviceInfo` for values outside the controller's range.
+    pub fn validate(&self) -> Result<()> {
+        if self.panel_width == 0xFFFF && self.panel_height == 0xFFFF {
+            return Err(Error::BusFault(
+                "bus returned all ones; check wiring or SPI mode",
+            ));
+        }
+        if self.panel_width == 0 && self.panel_height == 0 && self.img_buf_addr == 0 {
+            return Err(Error::BusFault(
+                "bus returned all zeros; check power, reset and HRDY wiring",
+            ));
+        }
+
+        if self.panel_width == 0
+            || self.panel_height == 0
+            || self.panel_width > MAX_PANEL_DIMENSION
+            || self.panel_height > MAX_PANEL_DIMENSION
+        {
+            return Err(Error::InvalidDeviceInfo(format!(
+                "implausible panel size {}x{}",
+                self.panel_width, self.panel_height
+            )));
+        }
+
+        if self.img_buf_addr == 0 || self.img_buf_addr >= SDRAM_SIZE {
+            return Err(Error::InvalidDeviceInfo(format!(
+                "image buffer address 0x{:08X} outside SDRAM",
+                self.img_buf_addr
+            )));
+        }
+
+        for (name, version) in [("firmware", &self.fw_version), ("LUT", &self.lut_version)] {
+            let printable = version.chars().all(|c| c.is_ascii_graphic() || c == ' ');
+            if version.is_empty() || !printable {
+                return Err(Error::InvalidDeviceInfo(format!(
+                    "{} version {:?} is not a printable string",
+                    name, version
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the firmware version parsed from `fw_version`.
+    pub fn firmware(&self) -> FirmwareVersion {
+        FirmwareVersion::parse(&self.fw_version)
+    }
+
+    /// Returns the LUT version parsed from `lut_version`.
+    pub fn lut(&self) -> LutVersion {
+        LutVersion::parse(&self.lut_version)
+    }
+}
+
+/// Controller firmware version, e.g. `SWv_0.1.1`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirmwareVersion {
+    /// Version string as reported by the controller
+    pub raw: String,
+
+    /// Numeric version (major, minor, patch), if the string contains one
+    pub number: Option<(u8, u8, u8)>,
+}
+
+impl FirmwareVersion {
+    /// Parses a firmware version string.
+    ///
+    /// The numeric part is the first run of dot-separated numbers, so
+    /// `SWv_0.1.1` and `0.2.1T` parse as 0.1.1 and 0.2.1. Missing
+    /// components are zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use it8951::FirmwareVersion;
+    ///
+    /// let version = FirmwareVersion::parse("SWv_0.1.1");
+    /// assert_eq!(version.number, Some((0, 1, 1)));
+    /// assert!(version.at_least(0, 1, 0));
+    /// ```
+    pub fn parse(raw: &str) -> Self {
+        let start = raw.find(|c: char| c.is_ascii_digit());
+        let number = start.and_then(|start| {
+            let digits: String = raw[start..]
+                .chars()
+                .take_while(|c| c.is_ascii_digit() || *c == '.')
+                .collect();
+            let mut parts = digits.split('.').filter(|p| !p.is_empty());
+            let mut next = || parts.next().map_or(Some(0), |p| p.parse::<u8>().ok());
+            Some((next()?, next()?, next()?))
+        });
+
+        Self {
+            raw: raw.to_string(),
+            number,
+        }
+    }
+
+    /// Returns whether the version is known and at least `major.minor.patch`.
+    pub fn at_least(&self, major: u8, minor: u8, patch: u8) -> bool {
+        self.number.is_some_and(|n| n >= (major, minor, patch))
+    }
+}
+
+impl fmt::Display for FirmwareVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+/// Waveform LUT version, e.g. `M841_TFAB512`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LutVersion {
+    /// Version string as reported by the controller
+    pub raw: String,
+
+    /// Panel model, the part before the first underscore (e.g. `M841`)
+    pub model: String,
+
+    /// Waveform variant after the first underscore (e.g. `TFAB512`)
+    pub variant: Option<String>,
+}
+
+impl LutVersion {
+    /// Parses a LUT version string.
+    pub fn parse(raw: &str) -> Self {
+        let (model, variant) = match raw.split_once('_') {
+            Some((model, variant)) => (model, Some(variant.to_string())),
+            None => (raw, None),
+        };
+
+        Self {
+            raw: raw.to_string(),
+            model: model.to_string(),
+            variant,
+        }
+    }
+
+    /// Returns the number of modes in the waveform, which determines the
+    /// mode numbering, if the panel model is known.
+    ///
+    /// `M641` waveforms have 5 modes, with A2 at 4; `M841` waveforms have
+    /// 8, with A2 at 6.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use it8951::LutVersion;
+    ///
+    /// assert_eq!(LutVersion::parse("M841_TFAB512").mode_count(), Some(8));
+    /// assert_eq!(LutVersion::parse("custom").mode_count(), None);
+    /// ```
+    pub fn mode_count(&self) -> Option<usize> {
+        match self.model.as_str() {
+            "M641" => Some(5),
+            "M841" => Some(8),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LutVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+/// Panel VCOM voltage.
+///
+/// VCOM is always negative; the controller takes its magnitude in
+/// millivolts. Values parse from the notation printed on panel stickers,
+/// such as `-1.53V`, and print the same way.
+///
+/// # Examples
+///
+/// ```
+/// use it8951::Vcom;
+///
+/// let vcom: Vcom = "-1.53V".parse().unwrap();
+/// assert_eq!(vcom.millivolts(), 1530);
+/// assert_eq!(vcom.to_string(), "-1.53V");
+///
+/// assert_eq!("-1530mV".parse::<Vcom>().unwrap(), vcom);
+/// assert!("-7V".parse::<Vcom>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Vcom(pub(crate) u16);
+
+impl Vcom {
+    /// Creates a VCOM from its magnitude in millivolts (1530 for -1.53V).
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidVcom` if the value exceeds 5000.
+    pub fn from_millivolts(millivolts: u16) -> Result<Self> {
+        if millivolts > MAX_VCOM_MV {
+            return Err(Error::InvalidVcom(millivolts));
+        }
+        Ok(Self(millivolts))
+    }
+
+    /// Returns the magnitude in millivolts, as sent to the controller.
+    pub fn millivolts(&self) -> u16 {
+        self.0
+    }
+}
+
+impl TryFrom<u16> for Vcom {
+    type Error = Error;
+
+    fn try_from(millivolts: u16) -> Result<Self> {
+        Self::from_millivolts(millivolts)
+    }
+}
+
+impl From<Vcom> for u16 {
+    fn from(vcom: Vcom) -> u16 {
+        vcom.0
+    }
+}
+
+impl std::str::FromStr for Vcom {
+    type Err = Error;
+
+    /// Parses a voltage such as `-1.53V`, `-1.53`, `1.53 V` or `-1530mV`.
+    ///
+    /// The sign is optional, since VCOM is always negative. Values without
+    /// a unit are in volts.
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::InvalidParameter("VCOM must be a voltage such as -1.53V");
+
+        let text = s.trim().to_ascii_lowercase();
+        let text = text.strip_prefix('-').unwrap_or(&text).trim_start();
+        let (number, millivolts) = match text.strip_suffix("mv") {
+            Some(number) => (number.trim_end(), true),
+            None => (text.strip_suffix('v').unwrap_or(text).trim_end(), false),
+        };
+
+        let (int, frac) = number.split_once('.').unwrap_or((number, ""));
+        let digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if int.is_empty() && frac.is_empty() || !digits(int) || !digits(frac) || int.len() > 5 {
+            return Err(invalid());
+        }
+        let int: u32 = if int.is_empty() { 0 } else { int.parse().map_err(|_| invalid())? };
+
+        let value = if millivolts {
+            if !frac.is_empty() {
+                return Err(invalid());
+            }
+            int
+        } else {
+            if frac.len() > 3 {
+                return Err(invalid());
+            }
+            let frac: u32 = format!("{:0<3}", frac).parse().map_err(|_| invalid())?;
+            int * 1000 + frac
+        };
+
+        Self::from_millivolts(u16::try_from(value).map_err(|_| invalid())?)
+    }
+}
+
+impl fmt::Display for Vcom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 == 0 { "" } else { "-" };
+        let (volts, frac) = (self.0 / 1000, self.0 % 1000);
+        if frac % 10 == 0 {
+            write!(f, "{}{}.{:02}V", sign, volts, frac / 10)
+        } else {
+            write!(f, "{}{}.{:03}V", sign, volts, frac)
+        }
+    }
+}
+
+/// Reads VCOM from configuration files as a voltage string (`"-1.53V"`)
+/// or as millivolts (`1530`).
+#[cfg(feature = "config")]
+impl<'de> serde::Deserialize<'de> for Vcom {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = Vcom;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a voltage such as \"-1.53V\" or millivolts such as 1530")
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, value: u64) -> std::result::Result<Vcom, E> {
+                u16::try_from(value)
+                    .ok()
+                    .and_then(|mv| Vcom::from_millivolts(mv).ok())
+                    .ok_or_else(|| E::custom(format!("VCOM out of range: {}", value)))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, value: i64) -> std::result::Result<Vcom, E> {
+                self.visit_u64(value.unsigned_abs())
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> std::result::Result<Vcom, E> {
+                value.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+#[cfg(feature = "config")]
+impl serde::Serialize for Vcom {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
 }
 
 /// A rectangular area on the display.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
 pub struct Area {
     /// X coordinate (left edge)
     pub x: u16,
@@ -145,6 +486,66 @@ impl Area {
     pub fn bottom(&self) -> u16 {
         self.y + self.height
     }
+
+    /// Maps an area in rotated (logical) coordinates to panel coordinates.
+    ///
+    /// # Arguments
+    ///
+    /// * `rotation` - Clockwise rotation of the logical view
+    /// * `panel_width` - Physical panel width
+    /// * `panel_height` - Physical panel height
+    ///
+    /// The area must lie within the logical display, whose dimensions are
+    /// swapped for 90 and 270 degree rotations.
+    pub fn to_physical(&self, rotation: Rotation, panel_width: u16, panel_height: u16) -> Area {
+        match rotation {
+            Rotation::Rotate0 => *self,
+            Rotation::Rotate90 => Area::new(
+                panel_width - self.bottom(),
+                self.x,
+                self.height,
+                self.width,
+            ),
+            Rotation::Rotate180 => Area::new(
+                panel_width - self.right(),
+                panel_height - self.bottom(),
+                self.width,
+                self.height,
+            ),
+            Rotation::Rotate270 => Area::new(
+                self.y,
+                panel_height - self.right(),
+                self.height,
+                self.width,
+            ),
+        }
+    }
+
+    /// Maps an area in panel coordinates back to rotated (logical)
+    /// coordinates; the inverse of [`to_physical`](Self::to_physical).
+    pub fn to_logical(&self, rotation: Rotation, panel_width: u16, panel_height: u16) -> Area {
+        match rotation {
+            Rotation::Rotate0 => *self,
+            Rotation::Rotate90 => Area::new(
+                self.y,
+                panel_width - self.right(),
+                self.height,
+                self.width,
+            ),
+            Rotation::Rotate180 => Area::new(
+                panel_width - self.right(),
+                panel_height - self.bottom(),
+                self.width,
+                self.height,
+            ),
+            Rotation::Rotate270 => Area::new(
+                panel_height - self.bottom(),
+                self.x,
+                self.height,
+                self.width,
+            ),
+        }
+    }
 }
 
 impl From<(u16, u16, u16, u16)> for Area {
@@ -155,6 +556,8 @@ impl From<(u16, u16, u16, u16)> for Area {
 
 /// Display refresh modes.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "config", serde(rename_all = "lowercase"))]
 #[repr(u16)]
 pub enum DisplayMode {
     /// Initialization mode (clears ghosting)
@@ -178,10 +581,25 @@ impl DisplayMode {
     pub fn as_u16(&self) -> u16 {
         *self as u16
     }
+
+    /// Returns the smallest pixel format that carries every gray level the
+    /// mode can show.
+    ///
+    /// GC16 and GL16 show 16 levels, DU and A2 only black and white. INIT
+    /// ignores the image but keeps 16 levels so the loaded buffer still
+    /// looks right if it is refreshed again in another mode.
+    pub fn pixel_format(&self) -> PixelFormat {
+        match self {
+            DisplayMode::Init | DisplayMode::Gc16 | DisplayMode::Gl16 => PixelFormat::Bpp4,
+            DisplayMode::Du | DisplayMode::A2 => PixelFormat::Bpp2,
+        }
+    }
 }
 
 /// Pixel formats supported by the IT8951.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "config", serde(rename_all = "lowercase"))]
 #[repr(u16)]
 pub enum PixelFormat {
     /// 2 bits per pixel (4 gray levels)
@@ -217,10 +635,23 @@ impl PixelFormat {
     pub fn as_u16(&self) -> u16 {
         *self as u16
     }
+
+    /// Creates a pixel format from a u16 value.
+    pub fn from_u16(value: u16) -> Option<Self> {
+        match value {
+            0 => Some(PixelFormat::Bpp2),
+            1 => Some(PixelFormat::Bpp3),
+            2 => Some(PixelFormat::Bpp4),
+            3 => Some(PixelFormat::Bpp8),
+            _ => None,
+        }
+    }
 }
 
 /// Display rotation.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "config", serde(rename_all = "lowercase"))]
 #[repr(u16)]
 pub enum Rotation {
     /// No rotation
@@ -241,6 +672,22 @@ impl Rotation {
     pub fn as_u16(&self) -> u16 {
         *self as u16
     }
+
+    /// Returns true if the rotation swaps width and height.
+    pub fn is_portrait(&self) -> bool {
+        matches!(self, Rotation::Rotate90 | Rotation::Rotate270)
+    }
+
+    /// Creates a rotation from a u16 value.
+    pub fn from_u16(value: u16) -> Option<Self> {
+        match value {
+            0 => Some(Rotation::Rotate0),
+            1 => Some(Rotation::Rotate90),
+            2 => Some(Rotation::Rotate180),
+            3 => Some(Rotation::Rotate270),
+            _ => None,
+        }
+    }
 }
 
 /// Byte order for pixel data.
@@ -259,6 +706,15 @@ impl Endian {
     pub fn as_u16(&self) -> u16 {
         *self as u16
     }
+
+    /// Creates an endian value from a u16 value.
+    pub fn from_u16(value: u16) -> Option<Self> {
+        match value {
+            0 => Some(Endian::Little),
+            1 => Some(Endian::Big),
+            _ => None,
+        }
+    }
 }
 
 /// Image loading information.
@@ -312,6 +768,53 @@ mod tests {
         assert_eq!(info.pixel_count(), 480000);
     }
 
+    fn valid_device_info() -> DeviceInfo {
+        DeviceInfo {
+            panel_width: 800,
+            panel_height: 600,
+            img_buf_addr: 0x001236E0,
+            fw_version: "SWv_0.1.1".to_string(),
+            lut_version: "M641".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_device_info_validate() {
+        assert!(valid_device_info().validate().is_ok());
+    }
+
+    #[test]
+    fn test_device_info_validate_bus_faults() {
+        let info = DeviceInfo::from_raw(&[0xFFFF; 20]).unwrap();
+        assert!(matches!(info.validate(), Err(Error::BusFault(msg)) if msg.contains("all ones")));
+
+        let info = DeviceInfo::from_raw(&[0x0000; 20]).unwrap();
+        assert!(matches!(info.validate(), Err(Error::BusFault(msg)) if msg.contains("all zeros")));
+    }
+
+    #[test]
+    fn test_device_info_validate_implausible_values() {
+        let mut info = valid_device_info();
+        info.panel_width = 0;
+        assert!(matches!(info.validate(), Err(Error::InvalidDeviceInfo(_))));
+
+        let mut info = valid_device_info();
+        info.panel_height = 8000;
+        assert!(matches!(info.validate(), Err(Error::InvalidDeviceInfo(_))));
+
+        let mut info = valid_device_info();
+        info.img_buf_addr = 0x0800_0000;
+        assert!(matches!(info.validate(), Err(Error::InvalidDeviceInfo(_))));
+
+        let mut info = valid_device_info();
+        info.fw_version = String::new();
+        assert!(matches!(info.validate(), Err(Error::InvalidDeviceInfo(_))));
+
+        let mut info = valid_device_info();
+        info.lut_version = "M6\u{1}".to_string();
+        assert!(matches!(info.validate(), Err(Error::InvalidDeviceInfo(_))));
+    }
+
     #[test]
     fn test_area_validity() {
         let area = Area::new(0, 0, 100, 100);
@@ -367,6 +870,95 @@ mod tests {
         assert_eq!(DisplayMode::A2.as_u16(), 4);
     }
 
+    #[test]
+    fn test_display_mode_pixel_format() {
+        assert_eq!(DisplayMode::Gc16.pixel_format(), PixelFormat::Bpp4);
+        assert_eq!(DisplayMode::Gl16.pixel_format(), PixelFormat::Bpp4);
+        assert_eq!(DisplayMode::Du.pixel_format(), PixelFormat::Bpp2);
+        assert_eq!(DisplayMode::A2.pixel_format(), PixelFormat::Bpp2);
+    }
+
+    #[test]
+    fn test_enum_from_u16_roundtrip() {
+        for format in [
+            PixelFormat::Bpp2,
+            PixelFormat::Bpp3,
+            PixelFormat::Bpp4,
+            PixelFormat::Bpp8,
+        ] {
+            assert_eq!(PixelFormat::from_u16(format.as_u16()), Some(format));
+        }
+        for rotation in [
+            Rotation::Rotate0,
+            Rotation::Rotate90,
+            Rotation::Rotate180,
+            Rotation::Rotate270,
+        ] {
+            assert_eq!(Rotation::from_u16(rotation.as_u16()), Some(rotation));
+        }
+        for endian in [Endian::Little, Endian::Big] {
+            assert_eq!(Endian::from_u16(endian.as_u16()), Some(endian));
+        }
+        assert_eq!(PixelFormat::from_u16(4), None);
+        assert_eq!(Rotation::from_u16(4), None);
+        assert_eq!(Endian::from_u16(2), None);
+    }
+
+    #[test]
+    fn test_area_to_physical() {
+        // 800x600 panel, 10x20 area at (30, 40) in logical coordinates
+        let area = Area::new(30, 40, 10, 20);
+
+        assert_eq!(area.to_physical(Rotation::Rotate0, 800, 600), area);
+        assert_eq!(
+            area.to_physical(Rotation::Rotate90, 800, 600),
+            Area::new(740, 30, 20, 10)
+        );
+        assert_eq!(
+            area.to_physical(Rotation::Rotate180, 800, 600),
+            Area::new(760, 540, 10, 20)
+        );
+        assert_eq!(
+            area.to_physical(Rotation::Rotate270, 800, 600),
+            Area::new(40, 560, 20, 10)
+        );
+    }
+
+    #[test]
+    fn test_area_to_logical() {
+        let area = Area::new(30, 40, 10, 20);
+        for rotation in [
+            Rotation::Rotate0,
+            Rotation::Rotate90,
+            Rotation::Rotate180,
+            Rotation::Rotate270,
+        ] {
+            let physical = area.to_physical(rotation, 800, 600);
+            assert_eq!(physical.to_logical(rotation, 800, 600), area, "{:?}", rotation);
+        }
+    }
+
+    #[test]
+    fn test_area_to_physical_full_screen() {
+        let portrait = Area::new(0, 0, 600, 800);
+        assert_eq!(
+            portrait.to_physical(Rotation::Rotate90, 800, 600),
+            Area::new(0, 0, 800, 600)
+        );
+        assert_eq!(
+            portrait.to_physical(Rotation::Rotate270, 800, 600),
+            Area::new(0, 0, 800, 600)
+        );
+    }
+
+    #[test]
+    fn test_rotation_is_portrait() {
+        assert!(!Rotation::Rotate0.is_portrait());
+        assert!(Rotation::Rotate90.is_portrait());
+        assert!(!Rotation::Rotate180.is_portrait());
+        assert!(Rotation::Rotate270.is_portrait());
+    }
+
     #[test]
     fn test_area_from_tuple() {
         let area: Area = (10, 20, 100, 200).into();
@@ -375,4 +967,51 @@ mod tests {
         assert_eq!(area.width, 100);
         assert_eq!(area.height, 200);
     }
+
+    #[test]
+    fn test_firmware_version_parse() {
+        assert_eq!(FirmwareVersion::parse("SWv_0.1.1").number, Some((0, 1, 1)));
+        assert_eq!(FirmwareVersion::parse("0.2.1T").number, Some((0, 2, 1)));
+        assert_eq!(FirmwareVersion::parse("v1.3").number, Some((1, 3, 0)));
+        assert_eq!(FirmwareVersion::parse("test").number, None);
+        assert!(!FirmwareVersion::parse("test").at_least(0, 0, 0));
+        assert!(FirmwareVersion::parse("SWv_0.2.0").at_least(0, 1, 9));
+    }
+
+    #[test]
+    fn test_lut_version_parse() {
+        let lut = LutVersion::parse("M841_TFAB512");
+        assert_eq!(lut.model, "M841");
+        assert_eq!(lut.variant.as_deref(), Some("TFAB512"));
+        assert_eq!(lut.to_string(), "M841_TFAB512");
+
+        let lut = LutVersion::parse("M641");
+        assert_eq!(lut.model, "M641");
+        assert_eq!(lut.variant, None);
+    }
+
+    #[test]
+    fn test_vcom_parse() {
+        for text in ["-1.53V", "-1.53", "1.53 V", "-1.530v", " -1530mV ", "-1530 mv"] {
+            assert_eq!(text.parse::<Vcom>().unwrap().millivolts(), 1530, "{}", text);
+        }
+        assert_eq!("-2V".parse::<Vcom>().unwrap().millivolts(), 2000);
+        assert_eq!("-.5V".parse::<Vcom>().unwrap().millivolts(), 500);
+
+        for text in ["", "-", "V", "-1.5.3V", "-1.5345V", "-1.5mV", "abc", "--1.5V"] {
+            assert!(matches!(text.parse::<Vcom>(), Err(Error::InvalidParameter(_))), "{}", text);
+        }
+        assert!(matches!("-5.1V".parse::<Vcom>(), Err(Error::InvalidVcom(5100))));
+        assert!("-99999999V".parse::<Vcom>().is_err());
+    }
+
+    #[test]
+    fn test_vcom_display() {
+        let show = |mv| Vcom::from_millivolts(mv).unwrap().to_string();
+        assert_eq!(show(1530), "-1.53V");
+        assert_eq!(show(1500), "-1.50V");
+        assert_eq!(show(1535), "-1.535V");
+        assert_eq!(show(0), "0.00V");
+        assert!(matches!(Vcom::try_from(5001), Err(Error::InvalidVcom(5001))));
+    }
 }
@@ -0,0 +1,534 @@
+//! E Ink waveform (.wbf) parsing.
+//!
+//! The panel's waveform describes which update modes exist, how many
+//! frames each takes and which temperature ranges have their own tables.
+//! It is stored in the controller's SPI flash and distributed as `.wbf`
+//! files; [`DeviceInfo::lut_version`](crate::DeviceInfo::lut_version)
+//! names the waveform in use.
+//!
+//! # Layout
+//!
+//! All multi-byte fields are little-endian.
+//!
+//! | Offset | Size | Field                                  |
+//! |--------|------|----------------------------------------|
+//! | 0      | 4    | Checksum                               |
+//! | 4      | 4    | File size                              |
+//! | 8      | 4    | Serial number                          |
+//! | 12     | 1    | Run type                               |
+//! | 13     | 1    | FPL platform                           |
+//! | 14     | 2    | FPL lot                                |
+//! | 16     | 1    | Mode version                           |
+//! | 17     | 1    | Waveform version                       |
+//! | 18     | 1    | Waveform subversion                    |
+//! | 19     | 1    | Waveform type                          |
+//! | 20     | 1    | FPL size                               |
+//! | 21     | 1    | Manufacturer code                      |
+//! | 23     | 1    | Frame rate                             |
+//! | 32     | 3    | Mode table address                     |
+//! | 35     | 1    | Mode table address checksum            |
+//! | 37     | 1    | Mode count - 1                         |
+//! | 38     | 1    | Temperature range count - 1            |
+//! | 48     | n+2  | Temperature boundaries (°C), checksum  |
+//!
+//! The mode table holds one 4-byte pointer (3-byte address plus checksum)
+//! per mode, each to a table of one pointer per temperature range, each to
+//! run-length encoded waveform data.
+
+use crate::error::{Error, Result};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Size of the fixed waveform header.
+const HEADER_SIZE: usize = 48;
+
+/// Bytes per frame of a 16-level state table (16x16 transitions, 2 bits each).
+const FRAME_SIZE: usize = 64;
+
+/// Toggles run-length encoding in waveform data.
+const RLE_TOGGLE: u8 = 0xFC;
+
+/// Terminates waveform data.
+const DATA_END: u8 = 0xFF;
+
+/// Named update modes found in E Ink waveforms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ModeName {
+    /// Full clear to white
+    Init,
+    /// Direct update, black and white
+    Du,
+    /// 16-level grayscale with flashing
+    Gc16,
+    /// 16-level grayscale without flashing
+    Gl16,
+    /// GL16 with ghost reduction
+    Glr16,
+    /// GL16 with ghost reduction and dithering
+    Gld16,
+    /// Fast black and white animation
+    A2,
+    /// Direct update with 4 gray levels
+    Du4,
+}
+
+impl ModeName {
+    /// Returns the conventional name of the mode.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModeName::Init => "INIT",
+            ModeName::Du => "DU",
+            ModeName::Gc16 => "GC16",
+            ModeName::Gl16 => "GL16",
+            ModeName::Glr16 => "GLR16",
+            ModeName::Gld16 => "GLD16",
+            ModeName::A2 => "A2",
+            ModeName::Du4 => "DU4",
+        }
+    }
+
+    /// Returns the mode's name for a mode number, given the number of modes
+    /// in the waveform.
+    ///
+    /// Waveforms with 5 modes use INIT, DU, GC16, GL16, A2. Waveforms with
+    /// 7 or more use INIT, DU, GC16, GL16, GLR16, GLD16, A2, DU4.
+    pub fn for_mode(mode: u16, mode_count: usize) -> Option<Self> {
+        const SHORT: [ModeName; 5] = [
+            ModeName::Init,
+            ModeName::Du,
+            ModeName::Gc16,
+            ModeName::Gl16,
+            ModeName::A2,
+        ];
+        const LONG: [ModeName; 8] = [
+            ModeName::Init,
+            ModeName::Du,
+            ModeName::Gc16,
+            ModeName::Gl16,
+            ModeName::Glr16,
+            ModeName::Gld16,
+            ModeName::A2,
+            ModeName::Du4,
+        ];
+
+        let table: &[ModeName] = match mode_count {
+            5 => &SHORT,
+            n if n >= 7 => &LONG[..n.min(LONG.len())],
+            _ => &[],
+        };
+        table.get(mode as usize).copied()
+    }
+}
+
+impl fmt::Display for ModeName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ModeName {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_uppercase().as_str() {
+            "INIT" => Ok(ModeName::Init),
+            "DU" => Ok(ModeName::Du),
+            "GC16" => Ok(ModeName::Gc16),
+            "GL16" => Ok(ModeName::Gl16),
+            "GLR16" => Ok(ModeName::Glr16),
+            "GLD16" => Ok(ModeName::Gld16),
+            "A2" => Ok(ModeName::A2),
+            "DU4" => Ok(ModeName::Du4),
+            _ => Err(Error::InvalidParameter("unknown waveform mode name")),
+        }
+    }
+}
+
+/// A temperature range with its own waveform tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TemperatureRange {
+    /// Lowest temperature of the range, inclusive (°C)
+    pub min: i16,
+    /// Highest temperature of the range, exclusive (°C)
+    pub max: i16,
+}
+
+impl TemperatureRange {
+    /// Returns whether a temperature falls in this range.
+    pub fn contains(&self, celsius: i16) -> bool {
+        (self.min..self.max).contains(&celsius)
+    }
+}
+
+/// An update mode described by a waveform.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WaveformMode {
+    /// Mode number passed to the display commands
+    pub number: u16,
+    /// Conventional name, if known for this waveform layout
+    pub name: Option<ModeName>,
+    /// Number of frames per temperature range
+    pub frame_counts: Vec<usize>,
+}
+
+/// A parsed waveform file.
+///
+/// # Examples
+///
+/// ```ignore
+/// use it8951::waveform::{ModeName, Waveform};
+///
+/// let waveform = Waveform::from_file("panel.wbf")?;
+/// for mode in waveform.modes() {
+///     println!("{:?}: {:?} frames", mode.name, mode.frame_counts);
+/// }
+/// let a2 = waveform.mode_number(ModeName::A2);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Waveform {
+    /// File size recorded in the header
+    pub file_size: u32,
+    /// Panel serial number
+    pub serial: u32,
+    /// Run type
+    pub run_type: u8,
+    /// FPL platform
+    pub fpl_platform: u8,
+    /// FPL lot number
+    pub fpl_lot: u16,
+    /// Mode version
+    pub mode_version: u8,
+    /// Waveform version
+    pub waveform_version: u8,
+    /// Waveform subversion
+    pub waveform_subversion: u8,
+    /// Waveform type
+    pub waveform_type: u8,
+    /// FPL size code
+    pub fpl_size: u8,
+    /// Manufacturer code
+    pub mfg_code: u8,
+    /// Frame rate in Hz
+    pub frame_rate: u8,
+    temperature_ranges: Vec<TemperatureRange>,
+    modes: Vec<WaveformMode>,
+}
+
+impl Waveform {
+    /// Parses a waveform from the start of `data`.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < HEADER_SIZE {
+            return Err(invalid("file shorter than header"));
+        }
+
+        let file_size = u32::from_le_bytes([data[4], data[5], data[6], data[7]]);
+        if file_size as usize > data.len() || (file_size as usize) < HEADER_SIZE {
+            return Err(invalid("file size does not match header"));
+        }
+        let data = &data[..file_size as usize];
+
+        let mode_count = data[37] as usize + 1;
+        let range_count = data[38] as usize + 1;
+
+        // Temperature boundaries: range_count + 1 values followed by a checksum
+        let temps = data
+            .get(HEADER_SIZE..HEADER_SIZE + range_count + 2)
+            .ok_or_else(|| invalid("temperature table out of bounds"))?;
+        let (bounds, checksum) = temps.split_at(range_count + 1);
+        if sum(bounds) != checksum[0] {
+            return Err(invalid("temperature table checksum mismatch"));
+        }
+        let temperature_ranges = bounds
+            .windows(2)
+            .map(|pair| TemperatureRange {
+                min: pair[0] as i16,
+                max: pair[1] as i16,
+            })
+            .collect();
+
+        // Each mode points to a table with one pointer per temperature range
+        let mode_table = pointer(data, 32)?;
+        let mut data_starts = Vec::new();
+        let mut tables = Vec::with_capacity(mode_count);
+        for mode in 0..mode_count {
+            let table = pointer(data, mode_table + mode * 4)?;
+            let mut addrs = Vec::with_capacity(range_count);
+            for range in 0..range_count {
+                let addr = pointer(data, table + range * 4)?;
+                data_starts.push(addr);
+                addrs.push(addr);
+            }
+            tables.push(addrs);
+        }
+        data_starts.sort_unstable();
+        data_starts.dedup();
+
+        let modes = tables
+            .into_iter()
+            .enumerate()
+            .map(|(number, addrs)| {
+                let frame_counts = addrs
+                    .into_iter()
+                    .map(|addr| {
+                        // Data runs until the terminator or the next table
+                        let end = data_starts
+                            .iter()
+                            .find(|&&start| start > addr)
+                            .copied()
+                            .unwrap_or(data.len());
+                        decoded_len(&data[addr..end]) / FRAME_SIZE
+                    })
+                    .collect();
+                WaveformMode {
+                    number: number as u16,
+                    name: ModeName::for_mode(number as u16, mode_count),
+                    frame_counts,
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            file_size,
+            serial: u32::from_le_bytes([data[8], data[9], data[10], data[11]]),
+            run_type: data[12],
+            fpl_platform: data[13],
+            fpl_lot: u16::from_le_bytes([data[14], data[15]]),
+            mode_version: data[16],
+            waveform_version: data[17],
+            waveform_subversion: data[18],
+            waveform_type: data[19],
+            fpl_size: data[20],
+            mfg_code: data[21],
+            frame_rate: data[23],
+            temperature_ranges,
+            modes,
+        })
+    }
+
+    /// Reads and parses a `.wbf` file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::parse(&std::fs::read(path)?)
+    }
+
+    /// Finds and parses a waveform inside a flash dump.
+    ///
+    /// Waveforms start on a 4 KiB sector boundary; the first offset that
+    /// parses with valid checksums is returned together with the waveform.
+    pub fn find(dump: &[u8]) -> Result<(usize, Self)> {
+        (0..dump.len())
+            .step_by(crate::device::FLASH_SECTOR_SIZE)
+            .find_map(|offset| Self::parse(&dump[offset..]).ok().map(|w| (offset, w)))
+            .ok_or_else(|| invalid("no waveform found in flash dump"))
+    }
+
+    /// Returns the temperature ranges, coldest first.
+    pub fn temperature_ranges(&self) -> &[TemperatureRange] {
+        &self.temperature_ranges
+    }
+
+    /// Returns the index of the temperature range containing `celsius`.
+    pub fn temperature_range_index(&self, celsius: i16) -> Option<usize> {
+        self.temperature_ranges
+            .iter()
+            .position(|range| range.contains(celsius))
+    }
+
+    /// Returns the available update modes.
+    pub fn modes(&self) -> &[WaveformMode] {
+        &self.modes
+    }
+
+    /// Returns the mode with the given name, if the waveform has one.
+    pub fn mode(&self, name: ModeName) -> Option<&WaveformMode> {
+        self.modes.iter().find(|mode| mode.name == Some(name))
+    }
+
+    /// Returns the mode number for a named mode on this panel.
+    pub fn mode_number(&self, name: ModeName) -> Option<u16> {
+        self.mode(name).map(|mode| mode.number)
+    }
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::InvalidWaveform(reason.to_string())
+}
+
+/// Returns the 8-bit sum of `bytes`.
+fn sum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |acc, &b| acc.wrapping_add(b))
+}
+
+/// Reads a 3-byte address and its checksum byte at `offset`.
+fn pointer(data: &[u8], offset: usize) -> Result<usize> {
+    let bytes = data
+        .get(offset..offset + 4)
+        .ok_or_else(|| invalid("table pointer out of bounds"))?;
+    if sum(&bytes[..3]) != bytes[3] {
+        return Err(invalid("table pointer checksum mismatch"));
+    }
+
+    let addr = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]) as usize;
+    if addr >= data.len() {
+        return Err(invalid("table address out of bounds"));
+    }
+    Ok(addr)
+}
+
+/// Returns the decoded length of run-length encoded waveform data.
+///
+/// Data starts in run-length mode, where each byte is followed by a repeat
+/// count minus one. [`RLE_TOGGLE`] switches between run-length and literal
+/// bytes, and [`DATA_END`] ends the data.
+fn decoded_len(data: &[u8]) -> usize {
+    let mut len = 0;
+    let mut rle = true;
+    let mut i = 0;
+
+    while i < data.len() {
+        match data[i] {
+            DATA_END => break,
+            RLE_TOGGLE => {
+                rle = !rle;
+                i += 1;
+            }
+            _ if rle => {
+                len += data.get(i + 1).map_or(1, |&count| count as usize + 1);
+                i += 2;
+            }
+            _ => {
+                len += 1;
+                i += 1;
+            }
+        }
+    }
+
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_pointer(out: &mut Vec<u8>, addr: usize) {
+        let bytes = (addr as u32).to_le_bytes();
+        out.extend_from_slice(&bytes[..3]);
+        out.push(sum(&bytes[..3]));
+    }
+
+    /// Builds a waveform with `frames[mode][range]` frames of RLE data.
+    fn build(temps: &[u8], frames: &[Vec<usize>]) -> Vec<u8> {
+        let mut out = vec![0u8; HEADER_SIZE];
+        out[16] = 0x19;
+        out[23] = 85;
+        out[37] = (frames.len() - 1) as u8;
+        out[38] = (temps.len() - 2) as u8;
+        out.extend_from_slice(temps);
+        out.push(sum(temps));
+
+        let mode_table = out.len();
+        let tables = mode_table + frames.len() * 4;
+        let range_count = temps.len() - 1;
+        let mut data_addr = tables + frames.len() * range_count * 4;
+
+        let mut pointers = Vec::new();
+        let mut tables_bytes = Vec::new();
+        let mut data = Vec::new();
+        for (mode, counts) in frames.iter().enumerate() {
+            push_pointer(&mut pointers, tables + mode * range_count * 4);
+            for &count in counts {
+                push_pointer(&mut tables_bytes, data_addr);
+                // Frames as 0x55 runs of 64 bytes, then a literal section
+                let mut chunk = Vec::new();
+                for _ in 0..count {
+                    chunk.extend_from_slice(&[0x55, 31, 0xAA, 31]);
+                }
+                chunk.extend_from_slice(&[RLE_TOGGLE, DATA_END]);
+                data_addr += chunk.len();
+                data.extend(chunk);
+            }
+        }
+        out.extend(pointers);
+        out.extend(tables_bytes);
+        out.extend(data);
+
+        let mode_bytes = (mode_table as u32).to_le_bytes();
+        out[32..35].copy_from_slice(&mode_bytes[..3]);
+        out[35] = sum(&mode_bytes[..3]);
+        let size = out.len() as u32;
+        out[4..8].copy_from_slice(&size.to_le_bytes());
+        out
+    }
+
+    #[test]
+    fn test_parse_modes_and_ranges() {
+        let mut frames = vec![vec![10, 8, 6]; 5];
+        frames[4] = vec![3, 2, 1];
+        let wbf = build(&[0, 10, 25, 50], &frames);
+
+        let waveform = Waveform::parse(&wbf).unwrap();
+        assert_eq!(waveform.frame_rate, 85);
+        assert_eq!(waveform.mode_version, 0x19);
+        assert_eq!(
+            waveform.temperature_ranges(),
+            &[
+                TemperatureRange { min: 0, max: 10 },
+                TemperatureRange { min: 10, max: 25 },
+                TemperatureRange { min: 25, max: 50 },
+            ]
+        );
+        assert_eq!(waveform.temperature_range_index(24), Some(1));
+        assert_eq!(waveform.temperature_range_index(60), None);
+
+        assert_eq!(waveform.modes().len(), 5);
+        assert_eq!(waveform.mode(ModeName::Gc16).unwrap().frame_counts, vec![10, 8, 6]);
+        assert_eq!(waveform.mode(ModeName::A2).unwrap().frame_counts, vec![3, 2, 1]);
+        assert_eq!(waveform.mode_number(ModeName::A2), Some(4));
+        assert_eq!(waveform.mode_number(ModeName::Glr16), None);
+    }
+
+    #[test]
+    fn test_mode_names_for_larger_waveforms() {
+        let wbf = build(&[0, 50], &vec![vec![1]; 8]);
+        let waveform = Waveform::parse(&wbf).unwrap();
+
+        assert_eq!(waveform.mode_number(ModeName::Glr16), Some(4));
+        assert_eq!(waveform.mode_number(ModeName::A2), Some(6));
+        assert_eq!(waveform.mode_number(ModeName::Du4), Some(7));
+    }
+
+    #[test]
+    fn test_parse_rejects_corruption() {
+        let wbf = build(&[0, 50], &vec![vec![1]; 5]);
+
+        assert!(Waveform::parse(&wbf[..20]).is_err());
+        assert!(Waveform::parse(&wbf[..wbf.len() - 1]).is_err());
+
+        let mut bad = wbf.clone();
+        bad[35] ^= 1;
+        assert!(matches!(
+            Waveform::parse(&bad),
+            Err(Error::InvalidWaveform(_))
+        ));
+    }
+
+    #[test]
+    fn test_find_in_flash_dump() {
+        let wbf = build(&[0, 50], &vec![vec![1]; 5]);
+        let mut dump = vec![0xFF; 3 * 4096];
+        dump.extend_from_slice(&wbf);
+        dump.resize(dump.len() + 4096, 0xFF);
+
+        let (offset, waveform) = Waveform::find(&dump).unwrap();
+        assert_eq!(offset, 3 * 4096);
+        assert_eq!(waveform.modes().len(), 5);
+        assert!(Waveform::find(&[0xFF; 8192]).is_err());
+    }
+
+    #[test]
+    fn test_mode_name_parsing() {
+        assert_eq!("gc16".parse::<ModeName>().unwrap(), ModeName::Gc16);
+        assert_eq!(ModeName::Du4.to_string(), "DU4");
+        assert!("XYZ".parse::<ModeName>().is_err());
+    }
+}